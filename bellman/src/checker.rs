@@ -0,0 +1,258 @@
+use pairing::{
+    Engine
+};
+
+use ff::{
+    Field
+};
+
+use std::fmt;
+
+use ::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+/// Describes the first constraint which the assignment did not satisfy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unsatisfied {
+    /// Position of the constraint in the order it was enforced
+    pub index: usize,
+    /// Namespace path of the constraint, e.g.
+    /// `conditional reversal of preimage 7/enforce_reverse`
+    pub path: String
+}
+
+impl fmt::Display for Unsatisfied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "constraint {} `{}` is not satisfied", self.index, self.path)
+    }
+}
+
+/// A constraint system which checks every constraint against the
+/// assignment as soon as it is enforced. Unlike the prover it keeps no
+/// evaluations around, and unlike a test constraint system it does not
+/// record the constraints; namespace names are only used to describe
+/// the first constraint that is not satisfied.
+pub struct ConstraintChecker<E: Engine> {
+    current_namespace: Vec<String>,
+    num_constraints: usize,
+    unsatisfied: Option<Unsatisfied>,
+    input_assignment: Vec<E::Fr>,
+    aux_assignment: Vec<E::Fr>
+}
+
+impl<E: Engine> ConstraintChecker<E> {
+    pub fn new() -> ConstraintChecker<E> {
+        ConstraintChecker {
+            current_namespace: vec![],
+            num_constraints: 0,
+            unsatisfied: None,
+            input_assignment: vec![E::Fr::one()],
+            aux_assignment: vec![]
+        }
+    }
+
+    /// Synthesizes `circuit` with its assignment and returns the first
+    /// constraint it does not satisfy, if any.
+    pub fn check<C: Circuit<E>>(
+        circuit: C
+    ) -> Result<Option<Unsatisfied>, SynthesisError>
+    {
+        let mut checker = ConstraintChecker::new();
+
+        circuit.synthesize(&mut checker)?;
+
+        Ok(checker.unsatisfied)
+    }
+
+    pub fn which_is_unsatisfied(&self) -> Option<&Unsatisfied> {
+        self.unsatisfied.as_ref()
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_none()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn eval(&self, lc: &LinearCombination<E>) -> E::Fr {
+        let mut acc = E::Fr::zero();
+
+        for &(var, ref coeff) in lc.as_ref() {
+            let mut tmp = match var.get_unchecked() {
+                Index::Input(i) => self.input_assignment[i],
+                Index::Aux(i) => self.aux_assignment[i]
+            };
+
+            tmp.mul_assign(coeff);
+            acc.add_assign(&tmp);
+        }
+
+        acc
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ConstraintChecker<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.aux_assignment.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.input_assignment.push(f()?);
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let index = self.num_constraints;
+        self.num_constraints += 1;
+
+        // Only the first failure is reported, so there is
+        // nothing left to check once one has been found.
+        if self.unsatisfied.is_some() {
+            return;
+        }
+
+        let mut a = self.eval(&a(LinearCombination::zero()));
+        let b = self.eval(&b(LinearCombination::zero()));
+        let c = self.eval(&c(LinearCombination::zero()));
+
+        a.mul_assign(&b);
+
+        if a != c {
+            let mut path = self.current_namespace.join("/");
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&annotation().into());
+
+            self.unsatisfied = Some(Unsatisfied {
+                index: index,
+                path: path
+            });
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::PrimeField;
+    use pairing::bn256::{Bn256, Fr};
+
+    struct ProductCircuit {
+        a: Fr,
+        b: Fr,
+        c: Fr
+    }
+
+    impl Circuit<Bn256> for ProductCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let a = cs.alloc(|| "a", || Ok(self.a))?;
+            let b = cs.alloc(|| "b", || Ok(self.b))?;
+            let c = cs.alloc_input(|| "c", || Ok(self.c))?;
+
+            for i in 0..3 {
+                let mut cs = cs.namespace(|| format!("round {}", i));
+                let mut cs = cs.namespace(|| "inner");
+                cs.enforce(
+                    || "a*b=c",
+                    |lc| lc + a,
+                    |lc| lc + b,
+                    |lc| lc + c
+                );
+            }
+
+            cs.enforce(
+                || "c*1=c",
+                |lc| lc + c,
+                |lc| lc + CS::one(),
+                |lc| lc + c
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reports_first_unsatisfied_constraint() {
+        let circuit = ProductCircuit {
+            a: Fr::from_str("10").unwrap(),
+            b: Fr::from_str("4").unwrap(),
+            c: Fr::from_str("40").unwrap()
+        };
+        assert_eq!(ConstraintChecker::check(circuit).unwrap(), None);
+
+        let mut checker = ConstraintChecker::new();
+        ProductCircuit {
+            a: Fr::from_str("10").unwrap(),
+            b: Fr::from_str("4").unwrap(),
+            c: Fr::from_str("41").unwrap()
+        }.synthesize(&mut checker).unwrap();
+
+        assert!(!checker.is_satisfied());
+        assert_eq!(checker.num_constraints(), 4);
+        assert_eq!(checker.which_is_unsatisfied(), Some(&Unsatisfied {
+            index: 0,
+            path: "round 0/inner/a*b=c".into()
+        }));
+        assert_eq!(
+            checker.which_is_unsatisfied().unwrap().to_string(),
+            "constraint 0 `round 0/inner/a*b=c` is not satisfied"
+        );
+    }
+}
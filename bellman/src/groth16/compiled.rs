@@ -0,0 +1,338 @@
+use rand::Rng;
+
+use pairing::{
+    Engine
+};
+
+use ff::{
+    Field
+};
+
+use super::{
+    ParameterSource,
+    Proof
+};
+
+use super::prover::{
+    ProvingAssignment,
+    create_proof_from_assignment
+};
+
+use ::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+use ::witness::{
+    WitnessCalculator
+};
+
+use ::shape::{
+    ShapeFingerprint,
+    ShapeHasher
+};
+
+/// A circuit that has been synthesized once into the rows of its
+/// rank-1 constraint system. Proving against a compiled circuit only
+/// runs the circuit to compute its witness; the linear combinations of
+/// the constraints are evaluated from the cached rows instead of being
+/// rebuilt on every proof.
+pub struct CompiledCircuit<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+    a: Vec<LinearCombination<E>>,
+    b: Vec<LinearCombination<E>>,
+    c: Vec<LinearCombination<E>>,
+    fingerprint: ShapeFingerprint
+}
+
+impl<E: Engine> CompiledCircuit<E> {
+    /// Synthesizes the circuit without an assignment and records every
+    /// constraint, including the input constraints added by the prover.
+    pub fn compile<C: Circuit<E>>(
+        circuit: C
+    ) -> Result<CompiledCircuit<E>, SynthesisError>
+    {
+        let mut assembly = CompilingAssembly {
+            num_inputs: 0,
+            num_aux: 0,
+            a: vec![],
+            b: vec![],
+            c: vec![],
+            shape: ShapeHasher::new()
+        };
+
+        // Allocate the "one" input variable
+        assembly.alloc_input(|| "", || Ok(E::Fr::one()))?;
+
+        circuit.synthesize(&mut assembly)?;
+
+        // Input constraints to ensure full density of IC query
+        // x * 0 = 0
+        for i in 0..assembly.num_inputs {
+            assembly.enforce(|| "",
+                |lc| lc + Variable(Index::Input(i)),
+                |lc| lc,
+                |lc| lc,
+            );
+        }
+
+        Ok(CompiledCircuit {
+            num_inputs: assembly.num_inputs,
+            num_aux: assembly.num_aux,
+            a: assembly.a,
+            b: assembly.b,
+            c: assembly.c,
+            fingerprint: assembly.shape.finalize(assembly.num_inputs, assembly.num_aux)
+        })
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+
+    pub fn fingerprint(&self) -> ShapeFingerprint {
+        self.fingerprint
+    }
+}
+
+/// Records the linear combinations of each constraint. No assignment
+/// is ever computed.
+struct CompilingAssembly<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+    a: Vec<LinearCombination<E>>,
+    b: Vec<LinearCombination<E>>,
+    c: Vec<LinearCombination<E>>,
+    shape: ShapeHasher
+}
+
+impl<E: Engine> ConstraintSystem<E> for CompilingAssembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        self.shape.constraint(&a, &b, &c);
+
+        self.a.push(a);
+        self.b.push(b);
+        self.c.push(c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+pub fn create_random_proof_compiled<E, C, R, P: ParameterSource<E>>(
+    compiled: &CompiledCircuit<E>,
+    circuit: C,
+    params: P,
+    rng: &mut R
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine, C: Circuit<E>, R: Rng
+{
+    let r = rng.gen();
+    let s = rng.gen();
+
+    create_proof_compiled::<E, C, P>(compiled, circuit, params, r, s)
+}
+
+/// Creates a proof for `circuit`, which must have the same shape as the
+/// circuit `compiled` was built from. Only the witness of `circuit` is
+/// computed; its constraints are never synthesized.
+pub fn create_proof_compiled<E, C, P: ParameterSource<E>>(
+    compiled: &CompiledCircuit<E>,
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine, C: Circuit<E>
+{
+    let mut witness = WitnessCalculator::new();
+
+    circuit.synthesize(&mut witness)?;
+
+    create_proof_from_witness(compiled, witness, params, r, s)
+}
+
+/// Creates a proof from a witness that was computed ahead of time,
+/// for example one read back from a witness file.
+pub fn create_proof_from_witness<E, P: ParameterSource<E>>(
+    compiled: &CompiledCircuit<E>,
+    witness: WitnessCalculator<E>,
+    params: P,
+    r: E::Fr,
+    s: E::Fr
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine
+{
+    if witness.num_inputs() != compiled.num_inputs ||
+       witness.num_aux() != compiled.num_aux
+    {
+        return Err(SynthesisError::ShapeMismatch);
+    }
+
+    let (input_assignment, aux_assignment) = witness.into_assignments();
+    let mut prover = ProvingAssignment::from_witness(input_assignment, aux_assignment);
+
+    for ((a, b), c) in compiled.a.iter().zip(compiled.b.iter()).zip(compiled.c.iter()) {
+        prover.evaluate(a, b, c);
+    }
+
+    create_proof_from_assignment(prover, Some(compiled.fingerprint), params, r, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{
+        generate_random_parameters,
+        create_proof,
+        prepare_verifying_key,
+        verify_proof
+    };
+
+    use rand::{Rand, thread_rng};
+    use pairing::bls12_381::{Bls12, Fr};
+
+    struct CubeCircuit<E: Engine> {
+        x: Option<E::Fr>
+    }
+
+    impl<E: Engine> Circuit<E> for CubeCircuit<E> {
+        fn synthesize<CS: ConstraintSystem<E>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+
+            let x2_value = x_value.map(|mut e| { e.square(); e });
+            let x2 = cs.alloc(|| "x^2", || x2_value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "x * x = x^2", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+
+            let y = cs.alloc_input(|| "y", || {
+                let mut y = x2_value.ok_or(SynthesisError::AssignmentMissing)?;
+                y.mul_assign(&x_value.ok_or(SynthesisError::AssignmentMissing)?);
+                Ok(y)
+            })?;
+            cs.enforce(|| "x^2 * x = y", |lc| lc + x2, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn compiled_proof_matches_synthesized_proof() {
+        let rng = &mut thread_rng();
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            CubeCircuit { x: None },
+            rng
+        ).unwrap();
+        let pvk = prepare_verifying_key::<Bls12>(&params.vk);
+
+        let compiled = CompiledCircuit::compile(CubeCircuit::<Bls12> { x: None }).unwrap();
+        assert_eq!(compiled.num_inputs(), 2);
+        assert_eq!(compiled.num_aux(), 2);
+        assert_eq!(compiled.num_constraints(), 4);
+        assert_eq!(Some(compiled.fingerprint()), params.fingerprint);
+
+        for _ in 0..10 {
+            let x = Fr::rand(rng);
+            let mut y = x;
+            y.square();
+            y.mul_assign(&x);
+
+            let r = Fr::rand(rng);
+            let s = Fr::rand(rng);
+
+            let expected = create_proof(CubeCircuit { x: Some(x) }, &params, r, s).unwrap();
+            let proof = create_proof_compiled(&compiled, CubeCircuit { x: Some(x) }, &params, r, s).unwrap();
+
+            assert!(proof == expected);
+            assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+
+            let mut witness = WitnessCalculator::new();
+            CubeCircuit::<Bls12> { x: Some(x) }.synthesize(&mut witness).unwrap();
+            let mut v = vec![];
+            witness.write(&mut v).unwrap();
+            let witness = WitnessCalculator::read(&v[..], compiled.num_inputs()).unwrap();
+
+            let proof = create_proof_from_witness(&compiled, witness, &params, r, s).unwrap();
+            assert!(proof == expected);
+        }
+    }
+}
@@ -33,6 +33,10 @@ use ::domain::{
     Scalar
 };
 
+use ::shape::{
+    ShapeHasher
+};
+
 use ::multicore::{
     Worker
 };
@@ -76,7 +80,8 @@ struct KeypairAssembly<E: Engine> {
     ct_inputs: Vec<Vec<(E::Fr, usize)>>,
     at_aux: Vec<Vec<(E::Fr, usize)>>,
     bt_aux: Vec<Vec<(E::Fr, usize)>>,
-    ct_aux: Vec<Vec<(E::Fr, usize)>>
+    ct_aux: Vec<Vec<(E::Fr, usize)>>,
+    shape: ShapeHasher
 }
 
 impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
@@ -149,9 +154,15 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
             }
         }
 
-        eval(a(LinearCombination::zero()), &mut self.at_inputs, &mut self.at_aux, self.num_constraints);
-        eval(b(LinearCombination::zero()), &mut self.bt_inputs, &mut self.bt_aux, self.num_constraints);
-        eval(c(LinearCombination::zero()), &mut self.ct_inputs, &mut self.ct_aux, self.num_constraints);
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        self.shape.constraint(&a, &b, &c);
+
+        eval(a, &mut self.at_inputs, &mut self.at_aux, self.num_constraints);
+        eval(b, &mut self.bt_inputs, &mut self.bt_aux, self.num_constraints);
+        eval(c, &mut self.ct_inputs, &mut self.ct_aux, self.num_constraints);
 
         self.num_constraints += 1;
     }
@@ -194,7 +205,8 @@ pub fn generate_parameters<E, C>(
         ct_inputs: vec![],
         at_aux: vec![],
         bt_aux: vec![],
-        ct_aux: vec![]
+        ct_aux: vec![],
+        shape: ShapeHasher::new()
     };
 
     // Allocate the "one" input variable
@@ -213,6 +225,8 @@ pub fn generate_parameters<E, C>(
         );
     }
 
+    let fingerprint = assembly.shape.finalize(assembly.num_inputs, assembly.num_aux);
+
     // Create bases for blind evaluation of polynomials at tau
     let powers_of_tau = vec![Scalar::<E>(E::Fr::zero()); assembly.num_constraints];
     let mut powers_of_tau = EvaluationDomain::from_coeffs(powers_of_tau)?;
@@ -480,6 +494,7 @@ pub fn generate_parameters<E, C>(
         // Filter points at infinity away from A/B queries
         a: Arc::new(a.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect()),
         b_g1: Arc::new(b_g1.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect()),
-        b_g2: Arc::new(b_g2.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect())
+        b_g2: Arc::new(b_g2.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect()),
+        fingerprint: Some(fingerprint)
     })
 }
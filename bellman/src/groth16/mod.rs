@@ -8,6 +8,10 @@ use ::{
     SynthesisError
 };
 
+use ::shape::{
+    ShapeFingerprint
+};
+
 use multiexp::SourceBuilder;
 use std::io::{self, Read, Write};
 use std::sync::Arc;
@@ -19,10 +23,12 @@ mod tests;
 mod generator;
 mod prover;
 mod verifier;
+mod compiled;
 
 pub use self::generator::*;
 pub use self::prover::*;
 pub use self::verifier::*;
+pub use self::compiled::*;
 
 #[derive(Clone)]
 pub struct Proof<E: Engine> {
@@ -234,9 +240,17 @@ pub struct Parameters<E: Engine> {
     // G1 and G2 for C/B queries, respectively. Never contains points at
     // infinity for the same reason as the "A" polynomials.
     pub b_g1: Arc<Vec<E::G1Affine>>,
-    pub b_g2: Arc<Vec<E::G2Affine>>
+    pub b_g2: Arc<Vec<E::G2Affine>>,
+
+    // Shape of the constraint system these parameters were generated
+    // for. Absent from parameters written by older versions.
+    pub fingerprint: Option<ShapeFingerprint>
 }
 
+/// Magic and version of the shape fingerprint section, which is
+/// followed by 1 and the fingerprint, or by 0 if it is unknown.
+const FINGERPRINT_TAG: &'static [u8; 4] = b"SHP\x01";
+
 impl<E: Engine> PartialEq for Parameters<E> {
     fn eq(&self, other: &Self) -> bool {
         self.vk == other.vk &&
@@ -244,7 +258,8 @@ impl<E: Engine> PartialEq for Parameters<E> {
         self.l == other.l &&
         self.a == other.a &&
         self.b_g1 == other.b_g1 &&
-        self.b_g2 == other.b_g2
+        self.b_g2 == other.b_g2 &&
+        self.fingerprint == other.fingerprint
     }
 }
 
@@ -281,6 +296,15 @@ impl<E: Engine> Parameters<E> {
             writer.write_all(g.into_uncompressed().as_ref())?;
         }
 
+        writer.write_all(FINGERPRINT_TAG)?;
+        match self.fingerprint {
+            Some(ref fingerprint) => {
+                writer.write_u8(1)?;
+                writer.write_all(&fingerprint.0)?;
+            },
+            None => writer.write_u8(0)?
+        }
+
         Ok(())
     }
 
@@ -370,13 +394,44 @@ impl<E: Engine> Parameters<E> {
             }
         }
 
+        // Parameters written before the shape fingerprint
+        // was introduced end here, so they can only be told
+        // apart from newer ones at the end of the stream.
+        let mut tag = [0u8; 4];
+        let mut filled = 0;
+        while filled < tag.len() {
+            match reader.read(&mut tag[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        let fingerprint = match filled {
+            0 => None,
+            4 if &tag == FINGERPRINT_TAG => {
+                match reader.read_u8()? {
+                    0 => None,
+                    1 => {
+                        let mut fingerprint = [0u8; 32];
+                        reader.read_exact(&mut fingerprint)?;
+                        Some(ShapeFingerprint(fingerprint))
+                    },
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid shape fingerprint flag"))
+                }
+            },
+            4 => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown shape fingerprint tag")),
+            _ => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated shape fingerprint tag"))
+        };
+
         Ok(Parameters {
             vk: vk,
             h: Arc::new(h),
             l: Arc::new(l),
             a: Arc::new(a),
             b_g1: Arc::new(b_g1),
-            b_g2: Arc::new(b_g2)
+            b_g2: Arc::new(b_g2),
+            fingerprint: fingerprint
         })
     }
 }
@@ -423,6 +478,15 @@ pub trait ParameterSource<E: Engine> {
         num_inputs: usize,
         num_aux: usize
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError>;
+
+    /// Returns the shape of the constraint system the parameters
+    /// were generated for, if it is known.
+    fn get_fingerprint(
+        &mut self
+    ) -> Option<ShapeFingerprint>
+    {
+        None
+    }
 }
 
 impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
@@ -479,6 +543,13 @@ impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
     {
         Ok(((self.b_g2.clone(), 0), (self.b_g2.clone(), num_inputs)))
     }
+
+    fn get_fingerprint(
+        &mut self
+    ) -> Option<ShapeFingerprint>
+    {
+        self.fingerprint
+    }
 }
 
 #[cfg(test)]
@@ -535,13 +606,44 @@ mod test_with_bls12_381 {
             let mut v = vec![];
 
             params.write(&mut v).unwrap();
-            assert_eq!(v.len(), 2136);
+            assert_eq!(v.len(), 2136 + 4 + 1 + 32);
 
             let de_params = Parameters::read(&v[..], true).unwrap();
             assert!(params == de_params);
 
             let de_params = Parameters::read(&v[..], false).unwrap();
             assert!(params == de_params);
+
+            // Parameters written before the fingerprint have none...
+            let de_params = Parameters::<Bls12>::read(&v[..2136], true).unwrap();
+            assert!(de_params.fingerprint.is_none());
+
+            // ...but a partial fingerprint section is an error.
+            for len in 2137..2136 + 4 + 1 + 32 {
+                let err = Parameters::<Bls12>::read(&v[..len], true).err().unwrap();
+                assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+            }
+
+            // Bytes following the parameters in a stream are left unread,
+            // with or without a fingerprint.
+            let mut unfingerprinted = params.clone();
+            unfingerprinted.fingerprint = None;
+            for p in [&params, &unfingerprinted].iter() {
+                let mut stream = vec![];
+                p.write(&mut stream).unwrap();
+                stream.extend_from_slice(&[0x42; 40]);
+
+                let mut reader = &stream[..];
+                let de_params = Parameters::<Bls12>::read(&mut reader, true).unwrap();
+                assert!(**p == de_params);
+                assert_eq!(reader, &[0x42; 40][..]);
+            }
+
+            // Trailing bytes aren't mistaken for a fingerprint.
+            let mut stream = v[..2136].to_vec();
+            stream.extend_from_slice(&[0x42; 32]);
+            let err = Parameters::<Bls12>::read(&stream[..], true).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
         }
 
         let pvk = prepare_verifying_key::<Bls12>(&params.vk);
@@ -44,6 +44,11 @@ use ::multicore::{
     Worker
 };
 
+use ::shape::{
+    ShapeFingerprint,
+    ShapeHasher
+};
+
 fn eval<E: Engine>(
     lc: &LinearCombination<E>,
     mut input_density: Option<&mut DensityTracker>,
@@ -83,7 +88,7 @@ fn eval<E: Engine>(
     acc
 }
 
-struct ProvingAssignment<E: Engine> {
+pub(super) struct ProvingAssignment<E: Engine> {
     // Density of queries
     a_aux_density: DensityTracker,
     b_input_density: DensityTracker,
@@ -96,7 +101,87 @@ struct ProvingAssignment<E: Engine> {
 
     // Assignments of variables
     input_assignment: Vec<E::Fr>,
-    aux_assignment: Vec<E::Fr>
+    aux_assignment: Vec<E::Fr>,
+
+    // Shape of the constraints enforced during synthesis
+    shape: Option<ShapeHasher>
+}
+
+impl<E: Engine> ProvingAssignment<E> {
+    fn new() -> ProvingAssignment<E> {
+        ProvingAssignment {
+            a_aux_density: DensityTracker::new(),
+            b_input_density: DensityTracker::new(),
+            b_aux_density: DensityTracker::new(),
+            a: vec![],
+            b: vec![],
+            c: vec![],
+            input_assignment: vec![],
+            aux_assignment: vec![],
+            shape: None
+        }
+    }
+
+    /// Creates an assignment from a witness that was computed ahead of
+    /// time, so that constraints can be evaluated against it directly.
+    pub(super) fn from_witness(
+        input_assignment: Vec<E::Fr>,
+        aux_assignment: Vec<E::Fr>
+    ) -> ProvingAssignment<E>
+    {
+        let mut prover = ProvingAssignment::new();
+
+        for _ in 0..input_assignment.len() {
+            prover.b_input_density.add_element();
+        }
+        for _ in 0..aux_assignment.len() {
+            prover.a_aux_density.add_element();
+            prover.b_aux_density.add_element();
+        }
+
+        prover.input_assignment = input_assignment;
+        prover.aux_assignment = aux_assignment;
+
+        prover
+    }
+
+    /// Evaluates the constraint `a` * `b` = `c` at the current assignment.
+    pub(super) fn evaluate(
+        &mut self,
+        a: &LinearCombination<E>,
+        b: &LinearCombination<E>,
+        c: &LinearCombination<E>
+    )
+    {
+        self.a.push(Scalar(eval(
+            a,
+            // Inputs have full density in the A query
+            // because there are constraints of the
+            // form x * 0 = 0 for each input.
+            None,
+            Some(&mut self.a_aux_density),
+            &self.input_assignment,
+            &self.aux_assignment
+        )));
+        self.b.push(Scalar(eval(
+            b,
+            Some(&mut self.b_input_density),
+            Some(&mut self.b_aux_density),
+            &self.input_assignment,
+            &self.aux_assignment
+        )));
+        self.c.push(Scalar(eval(
+            c,
+            // There is no C polynomial query,
+            // though there is an (beta)A + (alpha)B + C
+            // query for all aux variables.
+            // However, that query has full density.
+            None,
+            None,
+            &self.input_assignment,
+            &self.aux_assignment
+        )));
+    }
 }
 
 impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
@@ -145,34 +230,11 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
         let b = b(LinearCombination::zero());
         let c = c(LinearCombination::zero());
 
-        self.a.push(Scalar(eval(
-            &a,
-            // Inputs have full density in the A query
-            // because there are constraints of the
-            // form x * 0 = 0 for each input.
-            None,
-            Some(&mut self.a_aux_density),
-            &self.input_assignment,
-            &self.aux_assignment
-        )));
-        self.b.push(Scalar(eval(
-            &b,
-            Some(&mut self.b_input_density),
-            Some(&mut self.b_aux_density),
-            &self.input_assignment,
-            &self.aux_assignment
-        )));
-        self.c.push(Scalar(eval(
-            &c,
-            // There is no C polynomial query,
-            // though there is an (beta)A + (alpha)B + C
-            // query for all aux variables.
-            // However, that query has full density.
-            None,
-            None,
-            &self.input_assignment,
-            &self.aux_assignment
-        )));
+        if let Some(ref mut shape) = self.shape {
+            shape.constraint(&a, &b, &c);
+        }
+
+        self.evaluate(&a, &b, &c);
     }
 
     fn push_namespace<NR, N>(&mut self, _: N)
@@ -212,16 +274,13 @@ pub fn create_proof<E, C, P: ParameterSource<E>>(
 ) -> Result<Proof<E>, SynthesisError>
     where E: Engine, C: Circuit<E>
 {
-    let mut prover = ProvingAssignment {
-        a_aux_density: DensityTracker::new(),
-        b_input_density: DensityTracker::new(),
-        b_aux_density: DensityTracker::new(),
-        a: vec![],
-        b: vec![],
-        c: vec![],
-        input_assignment: vec![],
-        aux_assignment: vec![]
-    };
+    let mut prover = ProvingAssignment::new();
+
+    // The constraints are only hashed when there is a shape
+    // to check them against.
+    if params.get_fingerprint().is_some() {
+        prover.shape = Some(ShapeHasher::new());
+    }
 
     prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
 
@@ -235,6 +294,32 @@ pub fn create_proof<E, C, P: ParameterSource<E>>(
         );
     }
 
+    let fingerprint = prover.shape.take().map(|shape| {
+        shape.finalize(prover.input_assignment.len(), prover.aux_assignment.len())
+    });
+
+    create_proof_from_assignment(prover, fingerprint, params, r, s)
+}
+
+/// Computes the proof for an assignment whose constraints have
+/// all been evaluated, including the input constraints. The
+/// constraint system must have the shape the parameters were
+/// generated for, which is checked when both fingerprints are known.
+pub(super) fn create_proof_from_assignment<E, P: ParameterSource<E>>(
+    prover: ProvingAssignment<E>,
+    fingerprint: Option<ShapeFingerprint>,
+    mut params: P,
+    r: E::Fr,
+    s: E::Fr
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine
+{
+    if let (Some(expected), Some(fingerprint)) = (params.get_fingerprint(), fingerprint) {
+        if expected != fingerprint {
+            return Err(SynthesisError::ShapeMismatch);
+        }
+    }
+
     let worker = Worker::new();
 
     let vk = params.get_vk(prover.input_assignment.len())?;
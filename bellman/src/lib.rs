@@ -4,6 +4,7 @@ extern crate futures;
 extern crate bit_vec;
 extern crate byteorder;
 extern crate ff;
+extern crate blake2_rfc;
 
 #[cfg(feature = "multithread")]
 extern crate futures_cpupool;
@@ -16,6 +17,9 @@ pub mod multicore;
 mod multiexp;
 pub mod domain;
 pub mod groth16;
+pub mod witness;
+pub mod checker;
+pub mod shape;
 
 use ff::{Field};
 use pairing::{Engine};
@@ -188,7 +192,10 @@ pub enum SynthesisError {
     /// During verification, our verifying key was malformed.
     MalformedVerifyingKey,
     /// During CRS generation, we observed an unconstrained auxillary variable
-    UnconstrainedVariable
+    UnconstrainedVariable,
+    /// During proving, the circuit did not have the shape the proving
+    /// material was built for
+    ShapeMismatch
 }
 
 impl From<io::Error> for SynthesisError {
@@ -207,7 +214,8 @@ impl Error for SynthesisError {
             SynthesisError::UnexpectedIdentity => "encountered an identity element in the CRS",
             SynthesisError::IoError(_) => "encountered an I/O error",
             SynthesisError::MalformedVerifyingKey => "malformed verifying key",
-            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained"
+            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained",
+            SynthesisError::ShapeMismatch => "circuit shape does not match the proving material"
         }
     }
 }
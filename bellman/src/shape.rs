@@ -0,0 +1,336 @@
+use pairing::{
+    Engine
+};
+
+use ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use std::fmt;
+use byteorder::{BigEndian, ByteOrder};
+use blake2_rfc::blake2s::Blake2s;
+
+use ::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+/// A fingerprint of the structure of a rank-1 constraint system: the
+/// number of variables and every constraint's linear combinations,
+/// including the input constraints, but none of the assignment. Two
+/// syntheses of a circuit have the same fingerprint exactly when
+/// parameters generated for one can be used to prove the other.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ShapeFingerprint(pub [u8; 32]);
+
+impl ShapeFingerprint {
+    /// Synthesizes `circuit` and fingerprints its constraint system.
+    /// The assignment of the circuit is never computed.
+    pub fn of<E, C>(
+        circuit: C
+    ) -> Result<ShapeFingerprint, SynthesisError>
+        where E: Engine, C: Circuit<E>
+    {
+        let mut recorder = ShapeRecorder {
+            num_inputs: 0,
+            num_aux: 0,
+            shape: ShapeHasher::new()
+        };
+
+        // Allocate the "one" input variable
+        ConstraintSystem::<E>::alloc_input(&mut recorder, || "", || Ok(E::Fr::one()))?;
+
+        circuit.synthesize(&mut recorder)?;
+
+        // Input constraints, which are part of every constraint
+        // system used for parameter generation and proving.
+        for i in 0..recorder.num_inputs {
+            ConstraintSystem::<E>::enforce(&mut recorder, || "",
+                |lc| lc + Variable(Index::Input(i)),
+                |lc| lc,
+                |lc| lc,
+            );
+        }
+
+        Ok(recorder.shape.finalize(recorder.num_inputs, recorder.num_aux))
+    }
+}
+
+impl fmt::Debug for ShapeFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "ShapeFingerprint({})", self)
+    }
+}
+
+impl fmt::Display for ShapeFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Synthesizes the same circuit under two assignments, typically the
+/// one used for parameter generation and one used for proving, and
+/// checks that both produce the same constraint system.
+pub fn check_same_shape<E, C1, C2>(
+    first: C1,
+    second: C2
+) -> Result<ShapeFingerprint, SynthesisError>
+    where E: Engine, C1: Circuit<E>, C2: Circuit<E>
+{
+    let first = ShapeFingerprint::of(first)?;
+    let second = ShapeFingerprint::of(second)?;
+
+    if first != second {
+        return Err(SynthesisError::ShapeMismatch);
+    }
+
+    Ok(first)
+}
+
+/// Incrementally hashes the constraints of a constraint system.
+pub(crate) struct ShapeHasher {
+    h: Blake2s,
+    num_constraints: usize
+}
+
+impl ShapeHasher {
+    pub(crate) fn new() -> ShapeHasher {
+        ShapeHasher {
+            h: Blake2s::new(32),
+            num_constraints: 0
+        }
+    }
+
+    pub(crate) fn constraint<E: Engine>(
+        &mut self,
+        a: &LinearCombination<E>,
+        b: &LinearCombination<E>,
+        c: &LinearCombination<E>
+    )
+    {
+        self.hash_lc(a);
+        self.hash_lc(b);
+        self.hash_lc(c);
+
+        self.num_constraints += 1;
+    }
+
+    pub(crate) fn finalize(
+        mut self,
+        num_inputs: usize,
+        num_aux: usize
+    ) -> ShapeFingerprint
+    {
+        let mut buf = [0u8; 24];
+        BigEndian::write_u64(&mut buf[0..8], num_inputs as u64);
+        BigEndian::write_u64(&mut buf[8..16], num_aux as u64);
+        BigEndian::write_u64(&mut buf[16..24], self.num_constraints as u64);
+        self.h.update(&buf);
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(self.h.finalize().as_bytes());
+
+        ShapeFingerprint(fingerprint)
+    }
+
+    fn hash_lc<E: Engine>(&mut self, lc: &LinearCombination<E>) {
+        // Normalize the linear combination so that the order of its
+        // terms and repeated variables do not affect the fingerprint.
+        let mut terms: Vec<((u8, usize), E::Fr)> = lc.as_ref().iter().map(|&(var, coeff)| {
+            match var.get_unchecked() {
+                Index::Input(i) => ((b'I', i), coeff),
+                Index::Aux(i) => ((b'A', i), coeff)
+            }
+        }).collect();
+        terms.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut merged: Vec<((u8, usize), E::Fr)> = vec![];
+        for (var, coeff) in terms {
+            match merged.last_mut() {
+                Some(&mut (last, ref mut acc)) if last == var => {
+                    acc.add_assign(&coeff);
+                    continue;
+                },
+                _ => {}
+            }
+            merged.push((var, coeff));
+        }
+        merged.retain(|&(_, ref coeff)| !coeff.is_zero());
+
+        let mut buf = [0u8; 9];
+        BigEndian::write_u64(&mut buf[0..8], merged.len() as u64);
+        self.h.update(&buf[0..8]);
+
+        for ((kind, index), coeff) in merged {
+            buf[0] = kind;
+            BigEndian::write_u64(&mut buf[1..9], index as u64);
+            self.h.update(&buf);
+
+            let mut repr = vec![];
+            coeff.into_repr().write_be(&mut repr).unwrap();
+            self.h.update(&repr);
+        }
+    }
+}
+
+/// Records the shape of a constraint system without an assignment.
+struct ShapeRecorder {
+    num_inputs: usize,
+    num_aux: usize,
+    shape: ShapeHasher
+}
+
+impl<E: Engine> ConstraintSystem<E> for ShapeRecorder {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        // The assignment does not contribute to the shape, so we
+        // don't even invoke the function for obtaining one.
+
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        self.shape.constraint(&a, &b, &c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pairing::bn256::{Bn256, Fr};
+
+    /// Squares `x` once for every present entry, skipping absent ones
+    /// the way a careless circuit would.
+    struct SkippingCircuit {
+        x: Option<Fr>,
+        rounds: Vec<Option<()>>,
+        swap: bool
+    }
+
+    impl Circuit<Bn256> for SkippingCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let mut value = self.x;
+            let mut x = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+            for (i, round) in self.rounds.iter().enumerate() {
+                if round.is_none() {
+                    continue;
+                }
+
+                value = value.map(|mut e| { e.square(); e });
+                let y = cs.alloc(|| format!("y {}", i), || value.ok_or(SynthesisError::AssignmentMissing))?;
+                cs.enforce(|| format!("square {}", i), |lc| lc + x, |lc| lc + x, |lc| lc + y);
+                x = y;
+            }
+
+            let out = cs.alloc_input(|| "out", || value.ok_or(SynthesisError::AssignmentMissing))?;
+            if self.swap {
+                cs.enforce(|| "out", |lc| lc + CS::one(), |lc| lc + x, |lc| lc + out);
+            } else {
+                cs.enforce(|| "out", |lc| lc + x + x - x, |lc| lc + CS::one(), |lc| lc + out);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_assignment() {
+        let fingerprint = check_same_shape(
+            SkippingCircuit { x: None, rounds: vec![Some(()); 3], swap: false },
+            SkippingCircuit { x: Some(Fr::one()), rounds: vec![Some(()); 3], swap: false }
+        ).unwrap();
+
+        assert_eq!(fingerprint, ShapeFingerprint::of(
+            SkippingCircuit { x: None, rounds: vec![Some(()); 3], swap: false }
+        ).unwrap());
+        assert_eq!(format!("{}", fingerprint).len(), 64);
+    }
+
+    #[test]
+    fn fingerprint_detects_witness_dependent_shape() {
+        match check_same_shape(
+            SkippingCircuit { x: None, rounds: vec![Some(()); 3], swap: false },
+            SkippingCircuit { x: Some(Fr::one()), rounds: vec![Some(()), None, Some(())], swap: false }
+        ) {
+            Err(SynthesisError::ShapeMismatch) => {},
+            _ => panic!("circuits of different shapes must not match")
+        }
+
+        match check_same_shape(
+            SkippingCircuit { x: None, rounds: vec![Some(()); 3], swap: false },
+            SkippingCircuit { x: None, rounds: vec![Some(()); 3], swap: true }
+        ) {
+            Err(SynthesisError::ShapeMismatch) => {},
+            _ => panic!("swapped linear combinations must not match")
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use pairing::{
+    Engine
+};
+
+use ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use std::io::{self, Read, Write};
+use byteorder::{LittleEndian, WriteBytesExt, ReadBytesExt};
+
+use ::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+/// Magic bytes at the start of a witness file.
+const WTNS_MAGIC: &'static [u8; 4] = b"wtns";
+const WTNS_VERSION: u32 = 2;
+const WTNS_HEADER_SECTION: u32 = 1;
+const WTNS_WITNESS_SECTION: u32 = 2;
+
+/// A constraint system which only computes the assignment of every
+/// variable. Constraints are never built, so this is the cheapest way
+/// to run a circuit when just its witness is needed.
+///
+/// The "one" input is allocated on creation, so the assignments line up
+/// with the ones the prover computes.
+pub struct WitnessCalculator<E: Engine> {
+    input_assignment: Vec<E::Fr>,
+    aux_assignment: Vec<E::Fr>
+}
+
+impl<E: Engine> WitnessCalculator<E> {
+    pub fn new() -> WitnessCalculator<E> {
+        WitnessCalculator {
+            input_assignment: vec![E::Fr::one()],
+            aux_assignment: vec![]
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.input_assignment.len()
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux_assignment.len()
+    }
+
+    pub fn input_assignment(&self) -> &[E::Fr] {
+        &self.input_assignment
+    }
+
+    pub fn aux_assignment(&self) -> &[E::Fr] {
+        &self.aux_assignment
+    }
+
+    /// Consumes the calculator, returning the input and auxillary assignments.
+    pub fn into_assignments(self) -> (Vec<E::Fr>, Vec<E::Fr>) {
+        (self.input_assignment, self.aux_assignment)
+    }
+
+    /// Writes the witness in the `.wtns` format: a header section holding
+    /// the field modulus and the number of values, followed by a section
+    /// with the inputs and then the auxillary variables, each as a
+    /// little-endian integer.
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W
+    ) -> io::Result<()>
+    {
+        let n8 = field_element_size::<E>();
+        let num_values = self.input_assignment.len() + self.aux_assignment.len();
+
+        writer.write_all(WTNS_MAGIC)?;
+        writer.write_u32::<LittleEndian>(WTNS_VERSION)?;
+        writer.write_u32::<LittleEndian>(2)?;
+
+        writer.write_u32::<LittleEndian>(WTNS_HEADER_SECTION)?;
+        writer.write_u64::<LittleEndian>((4 + n8 + 4) as u64)?;
+        writer.write_u32::<LittleEndian>(n8 as u32)?;
+        E::Fr::char().write_le(&mut writer)?;
+        writer.write_u32::<LittleEndian>(num_values as u32)?;
+
+        writer.write_u32::<LittleEndian>(WTNS_WITNESS_SECTION)?;
+        writer.write_u64::<LittleEndian>((num_values * n8) as u64)?;
+        for value in self.input_assignment.iter().chain(self.aux_assignment.iter()) {
+            value.into_repr().write_le(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a witness written by `write`. The file does not record which
+    /// values are inputs, so the number of inputs (including the "one"
+    /// input) has to be supplied.
+    pub fn read<R: Read>(
+        mut reader: R,
+        num_inputs: usize
+    ) -> io::Result<Self>
+    {
+        let n8 = field_element_size::<E>();
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != WTNS_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a witness file"));
+        }
+        if reader.read_u32::<LittleEndian>()? != WTNS_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported witness file version"));
+        }
+        if reader.read_u32::<LittleEndian>()? != 2 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected number of sections"));
+        }
+
+        if reader.read_u32::<LittleEndian>()? != WTNS_HEADER_SECTION ||
+           reader.read_u64::<LittleEndian>()? != (4 + n8 + 4) as u64
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed header section"));
+        }
+        if reader.read_u32::<LittleEndian>()? as usize != n8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected field element size"));
+        }
+        let mut modulus = <E::Fr as PrimeField>::Repr::default();
+        modulus.read_le(&mut reader)?;
+        if modulus != E::Fr::char() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "witness is over a different field"));
+        }
+        let num_values = reader.read_u32::<LittleEndian>()? as usize;
+        if num_inputs == 0 || num_inputs > num_values {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "witness is missing inputs"));
+        }
+
+        if reader.read_u32::<LittleEndian>()? != WTNS_WITNESS_SECTION ||
+           reader.read_u64::<LittleEndian>()? != (num_values * n8) as u64
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed witness section"));
+        }
+
+        let mut values = Vec::with_capacity(num_values);
+        for _ in 0..num_values {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_le(&mut reader)?;
+            values.push(
+                E::Fr::from_repr(repr)
+                      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            );
+        }
+
+        let aux_assignment = values.split_off(num_inputs);
+
+        Ok(WitnessCalculator {
+            input_assignment: values,
+            aux_assignment: aux_assignment
+        })
+    }
+}
+
+/// Size in bytes of an encoded field element.
+fn field_element_size<E: Engine>() -> usize {
+    E::Fr::char().as_ref().len() * 8
+}
+
+impl<E: Engine> ConstraintSystem<E> for WitnessCalculator<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.aux_assignment.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.input_assignment.push(f()?);
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        _: LA,
+        _: LB,
+        _: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        // Do nothing; we only care about the assignment in this context.
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rand, thread_rng};
+    use pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn witness_only_records_assignments() {
+        let rng = &mut thread_rng();
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let mut c = a;
+        c.mul_assign(&b);
+
+        let mut cs = WitnessCalculator::<Bn256>::new();
+        let a_var = cs.alloc(|| "a", || Ok(a)).unwrap();
+        let b_var = cs.alloc(|| "b", || Ok(b)).unwrap();
+        let c_var = cs.alloc_input(|| "c", || Ok(c)).unwrap();
+        cs.namespace(|| "product").enforce(
+            || "a*b=c",
+            |_| panic!("constraints must not be built"),
+            |lc| lc + b_var,
+            |lc| lc + c_var
+        );
+
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(cs.num_aux(), 2);
+        assert_eq!(cs.input_assignment(), &[Fr::one(), c][..]);
+        assert_eq!(cs.aux_assignment(), &[a, b][..]);
+        match a_var {
+            Variable(Index::Aux(0)) => {},
+            _ => panic!("unexpected variable")
+        }
+    }
+
+    #[test]
+    fn wtns_roundtrip() {
+        let rng = &mut thread_rng();
+
+        let mut cs = WitnessCalculator::<Bn256>::new();
+        for i in 0..10 {
+            cs.alloc(|| format!("aux {}", i), || Ok(Fr::rand(rng))).unwrap();
+        }
+        cs.alloc_input(|| "input", || Ok(Fr::rand(rng))).unwrap();
+
+        let mut v = vec![];
+        cs.write(&mut v).unwrap();
+        assert_eq!(&v[0..4], b"wtns");
+        assert_eq!(v.len(), 12 + 12 + 40 + 12 + 12 * 32);
+
+        let de = WitnessCalculator::<Bn256>::read(&v[..], 2).unwrap();
+        assert_eq!(de.input_assignment(), cs.input_assignment());
+        assert_eq!(de.aux_assignment(), cs.aux_assignment());
+
+        assert!(WitnessCalculator::<Bn256>::read(&v[..], 14).is_err());
+        assert!(WitnessCalculator::<Bn256>::read(&v[1..], 2).is_err());
+    }
+}
@@ -4,6 +4,8 @@ use ff::{Field, PrimeField, PrimeFieldRepr};
 #[derive(Copy, Clone)]
 pub enum Personalization {
     NoteCommitment,
+    PreimageCommitment,
+    OwnerCommitment,
     MerkleTree(usize)
 }
 
@@ -12,8 +14,12 @@ impl Personalization {
         match *self {
             Personalization::NoteCommitment =>
                 vec![true, true, true, true, true, true],
+            Personalization::PreimageCommitment =>
+                vec![false, true, true, true, true, true],
+            Personalization::OwnerCommitment =>
+                vec![true, false, true, true, true, true],
             Personalization::MerkleTree(num) => {
-                assert!(num < 62);
+                assert!(num < 61);
 
                 (0..6).map(|i| (num >> i) & 1 == 1).collect()
             }
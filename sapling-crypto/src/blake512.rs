@@ -0,0 +1,136 @@
+//! This is an implementation of the original BLAKE-512, the SHA-3
+//! finalist that circomlib uses to derive EdDSA keys and nonces. It is
+//! unrelated to BLAKE2b.
+
+use byteorder::{ByteOrder, BigEndian};
+
+/// Initial chaining value, shared with SHA-512.
+pub const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+];
+
+/// Constants of the round function, the first digits of pi.
+pub const CONSTANTS: [u64; 16] = [
+    0x243f6a8885a308d3, 0x13198a2e03707344, 0xa4093822299f31d0, 0x082efa98ec4e6c89,
+    0x452821e638d01377, 0xbe5466cf34e90c6c, 0xc0ac29b7c97c50dd, 0x3f84d5b5b5470917,
+    0x9216d5d98979fb1b, 0xd1310ba698dfb5ac, 0x2ffd72dbd01adfb7, 0xb8e1afed6a267e96,
+    0xba7c9045f12c7f99, 0x24a19947b3916cf7, 0x0801f2e2858efc16, 0x636920d871574e69
+];
+
+/// Message word permutations, round `r` uses `SIGMA[r % 10]`.
+pub const SIGMA: [[usize; 16]; 10] = [
+    [ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15],
+    [14, 10,  4,  8,  9, 15, 13,  6,  1, 12,  0,  2, 11,  7,  5,  3],
+    [11,  8, 12,  0,  5,  2, 15, 13, 10, 14,  3,  6,  7,  1,  9,  4],
+    [ 7,  9,  3,  1, 13, 12, 11, 14,  2,  6,  5, 10,  4,  0, 15,  8],
+    [ 9,  0,  5,  7,  2,  4, 10, 15, 14,  1, 11, 12,  6,  8,  3, 13],
+    [ 2, 12,  6, 10,  0, 11,  8,  3,  4, 13,  7,  5, 15, 14,  1,  9],
+    [12,  5,  1, 15, 14, 13,  4, 10,  0,  7,  6,  3,  9,  2,  8, 11],
+    [13, 11,  7, 14, 12,  1,  3,  9,  5,  0, 15,  4,  8,  6,  2, 10],
+    [ 6, 15, 14,  9, 11,  3,  0,  8, 12,  2, 13,  7,  1,  4, 10,  5],
+    [10,  2,  8,  4,  7,  6,  1,  5, 15, 11,  9, 14,  3, 12, 13,  0]
+];
+
+/// Block size of BLAKE-512 in bytes.
+pub const BLAKE512_BLOCK_SIZE: usize = 128;
+
+const ROUNDS: usize = 16;
+
+/// Compresses a block into the chaining value, `counter` being the
+/// number of message bits hashed up to the end of the block.
+fn compress(h: &mut [u64; 8], block: &[u8], counter: u128) {
+    let mut m = [0u64; 16];
+    BigEndian::read_u64_into(block, &mut m);
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(&h[..]);
+    v[8..].copy_from_slice(&CONSTANTS[..8]);
+    v[12] ^= counter as u64;
+    v[13] ^= counter as u64;
+    v[14] ^= (counter >> 64) as u64;
+    v[15] ^= (counter >> 64) as u64;
+
+    for r in 0..ROUNDS {
+        let s = &SIGMA[r % 10];
+        // columns, then diagonals
+        for (i, &(a, b, c, d)) in [
+            (0, 4, 8, 12), (1, 5, 9, 13), (2, 6, 10, 14), (3, 7, 11, 15),
+            (0, 5, 10, 15), (1, 6, 11, 12), (2, 7, 8, 13), (3, 4, 9, 14)
+        ].iter().enumerate() {
+            let (x, y) = (s[2 * i], s[2 * i + 1]);
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[x] ^ CONSTANTS[y]);
+            v[d] = (v[d] ^ v[a]).rotate_right(32);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(25);
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[y] ^ CONSTANTS[x]);
+            v[d] = (v[d] ^ v[a]).rotate_right(16);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(11);
+        }
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+pub fn blake512(input: &[u8]) -> [u8; 64] {
+    let bits = (input.len() as u128) * 8;
+
+    // A one bit, zeros, a one bit and the 128-bit length fill the last
+    // block, which may be an extra one
+    let mut padded = input.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLAKE512_BLOCK_SIZE != BLAKE512_BLOCK_SIZE - 16 {
+        padded.push(0x00);
+    }
+    *padded.last_mut().unwrap() |= 0x01;
+    let mut length = [0u8; 16];
+    BigEndian::write_u64(&mut length[..8], (bits >> 64) as u64);
+    BigEndian::write_u64(&mut length[8..], bits as u64);
+    padded.extend_from_slice(&length);
+
+    let mut h = IV;
+    for (i, block) in padded.chunks(BLAKE512_BLOCK_SIZE).enumerate() {
+        let start = (i * BLAKE512_BLOCK_SIZE * 8) as u128;
+        // A block holding only padding is compressed with a null counter
+        let counter = if start >= bits {
+            0
+        } else {
+            ::std::cmp::min(start + (BLAKE512_BLOCK_SIZE * 8) as u128, bits)
+        };
+        compress(&mut h, block, counter);
+    }
+
+    let mut output = [0u8; 64];
+    BigEndian::write_u64_into(&h, &mut output);
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blake512_empty() {
+        assert_eq!(
+            &blake512(&[])[..],
+            &hex!("a8cfbbd73726062df0c6864dda65defe58ef0cc52a5625090fa17601e1eecd1b628e94f396ae402a00acc9eab77b4d4c2e852aaaa25a636d80af3fc7913ef5b8")[..]
+        );
+    }
+
+    #[test]
+    fn test_blake512_reference_vectors() {
+        // The one and two block examples of the BLAKE submission
+        assert_eq!(
+            &blake512(&[0u8; 1])[..],
+            &hex!("97961587f6d970faba6d2478045de6d1fabd09b61ae50932054d52bc29d31be4ff9102b9f69e2bbdb83be13d4b9c06091e5fa0b48bd081b634058be0ec49beb3")[..]
+        );
+        assert_eq!(
+            &blake512(&[0u8; 144])[..],
+            &hex!("313717d608e9cf758dcb1eb0f0c3cf9fc150b2d500fb33f51c52afc99d358a2f1374b8a38bba7974e7f6ef79cab16f22ce1e649d6e01ad9589c213045d545dde")[..]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Point arithmetic gadgets over the alt-babyjubjub curve.
+//!
+//! `alt_babyjubjub` implements `jubjub::JubjubEngine` for `Bn256`, so its
+//! points share the `a = -1` twisted Edwards formulas of `circuit::ecc`,
+//! which cost fewer constraints than the `circuit::baby_ecc` ones. These
+//! functions specialise those gadgets to `AltJubjubBn256`.
+
+use pairing::bn256::Bn256;
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use ::alt_babyjubjub::{
+    AltJubjubBn256,
+    FixedGenerators,
+    edwards
+};
+
+use super::ecc;
+
+use super::boolean::Boolean;
+
+pub type EdwardsPoint = ecc::EdwardsPoint<Bn256>;
+
+/// Witnesses a point of the curve inside the constraint system.
+pub fn witness<Order, CS>(
+    cs: CS,
+    p: Option<edwards::Point<Bn256, Order>>,
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    EdwardsPoint::witness(cs, p, params)
+}
+
+/// Adds two points.
+pub fn add<CS>(
+    cs: CS,
+    a: &EdwardsPoint,
+    b: &EdwardsPoint,
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    a.add(cs, b, params)
+}
+
+/// Doubles a point.
+pub fn double<CS>(
+    cs: CS,
+    p: &EdwardsPoint,
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    p.double(cs, params)
+}
+
+/// Multiplies a point by a scalar given in little-endian bits.
+pub fn mul<CS>(
+    cs: CS,
+    p: &EdwardsPoint,
+    by: &[Boolean],
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    p.mul(cs, by, params)
+}
+
+/// Multiplies a point by a scalar given in little-endian bits, using
+/// 2-bit windows.
+pub fn mul_windowed<CS>(
+    cs: CS,
+    p: &EdwardsPoint,
+    by: &[Boolean],
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    p.mul_windowed(cs, by, params)
+}
+
+/// Multiplies a fixed generator by a scalar given in little-endian bits,
+/// using the 3-bit window tables of `AltJubjubBn256`.
+pub fn fixed_base_multiplication<CS>(
+    cs: CS,
+    base: FixedGenerators,
+    by: &[Boolean],
+    params: &AltJubjubBn256
+) -> Result<EdwardsPoint, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    ecc::fixed_base_multiplication(cs, base, by, params)
+}
+
+/// Enforces that a point is not of small order, i.e. does not vanish
+/// when multiplied by the cofactor.
+pub fn assert_not_small_order<CS>(
+    cs: CS,
+    p: &EdwardsPoint,
+    params: &AltJubjubBn256
+) -> Result<(), SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    p.assert_not_small_order(cs, params)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use pairing::bn256::{Bn256, Fr};
+    use ff::{BitIterator, Field, PrimeField};
+    use ::circuit::test::*;
+    use ::circuit::baby_ecc;
+    use ::babyjubjub::{self, JubjubBn256};
+    use ::alt_babyjubjub::{
+        edwards,
+        AltJubjubBn256,
+        JubjubParams,
+        FixedGenerators,
+        Unknown
+    };
+    use ::alt_babyjubjub::fs::Fs;
+    use super::super::boolean::{
+        Boolean,
+        AllocatedBit
+    };
+    use super::{
+        witness,
+        add,
+        double,
+        mul,
+        mul_windowed,
+        fixed_base_multiplication,
+        assert_not_small_order
+    };
+
+    fn scalar_bits<CS: ConstraintSystem<Bn256>>(mut cs: CS, s: Fs) -> Vec<Boolean> {
+        let mut s_bits = BitIterator::new(s.into_repr()).collect::<Vec<_>>();
+        s_bits.reverse();
+        s_bits.truncate(Fs::NUM_BITS as usize);
+
+        s_bits.into_iter()
+              .enumerate()
+              .map(|(i, b)| Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("scalar bit {}", i)), Some(b)).unwrap()))
+              .collect()
+    }
+
+    #[test]
+    fn test_alt_baby_arithmetic() {
+        let params = &AltJubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..20 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let p1 = edwards::Point::<Bn256, _>::rand(rng, params);
+            let p2 = edwards::Point::<Bn256, _>::rand(rng, params);
+            let s = Fs::rand(rng);
+
+            let a = witness(cs.namespace(|| "a"), Some(p1.clone()), params).unwrap();
+            let b = witness(cs.namespace(|| "b"), Some(p2.clone()), params).unwrap();
+            let bits = scalar_bits(cs.namespace(|| "s"), s);
+
+            let sum = add(cs.namespace(|| "add"), &a, &b, params).unwrap();
+            let doubled = double(cs.namespace(|| "double"), &a, params).unwrap();
+            let product = mul(cs.namespace(|| "mul"), &a, &bits, params).unwrap();
+            let windowed = mul_windowed(cs.namespace(|| "windowed mul"), &a, &bits, params).unwrap();
+            let fixed = fixed_base_multiplication(
+                cs.namespace(|| "fixed base"),
+                FixedGenerators::SpendingKeyGenerator,
+                &bits,
+                params
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            let check = |p: &super::EdwardsPoint, q: edwards::Point<Bn256, Unknown>| {
+                let (x, y) = q.into_xy();
+                assert_eq!(p.get_x().get_value().unwrap(), x);
+                assert_eq!(p.get_y().get_value().unwrap(), y);
+            };
+            check(&sum, p1.add(&p2, params));
+            check(&doubled, p1.double(params));
+            check(&product, p1.mul(s, params));
+            check(&windowed, p1.mul(s, params));
+            let (x, y) = params.generator(FixedGenerators::SpendingKeyGenerator).mul(s, params).into_xy();
+            assert_eq!(fixed.get_x().get_value().unwrap(), x);
+            assert_eq!(fixed.get_y().get_value().unwrap(), y);
+        }
+    }
+
+    #[test]
+    fn test_alt_baby_assert_not_small_order() {
+        let params = &AltJubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let p = edwards::Point::<Bn256, _>::rand(rng, params);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let p = witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+        assert_not_small_order(cs.namespace(|| "check"), &p, params).unwrap();
+        assert!(cs.is_satisfied());
+
+        // (0, -1) has order 2
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let small = edwards::Point::<Bn256, Unknown>::get_for_y(minus_one, false, params).unwrap();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let small = witness(cs.namespace(|| "p"), Some(small), params).unwrap();
+        assert!(assert_not_small_order(cs.namespace(|| "check"), &small, params).is_err());
+    }
+
+    #[test]
+    fn test_alt_baby_addition_is_cheaper() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let params = &AltJubjubBn256::new();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let a = witness(cs.namespace(|| "a"), Some(edwards::Point::<Bn256, _>::rand(rng, params)), params).unwrap();
+        let b = witness(cs.namespace(|| "b"), Some(edwards::Point::<Bn256, _>::rand(rng, params)), params).unwrap();
+        let before = cs.num_constraints();
+        add(cs.namespace(|| "add"), &a, &b, params).unwrap();
+        let alt_cost = cs.num_constraints() - before;
+
+        let params = &JubjubBn256::new();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let a = baby_ecc::EdwardsPoint::witness(
+            cs.namespace(|| "a"),
+            Some(babyjubjub::edwards::Point::<Bn256, _>::rand(rng, params)),
+            params
+        ).unwrap();
+        let b = baby_ecc::EdwardsPoint::witness(
+            cs.namespace(|| "b"),
+            Some(babyjubjub::edwards::Point::<Bn256, _>::rand(rng, params)),
+            params
+        ).unwrap();
+        let before = cs.num_constraints();
+        a.add(cs.namespace(|| "add"), &b, params).unwrap();
+        let baby_cost = cs.num_constraints() - before;
+
+        assert!(alt_cost < baby_cost, "{} >= {}", alt_cost, baby_cost);
+    }
+}
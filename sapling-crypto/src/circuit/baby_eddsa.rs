@@ -30,7 +30,6 @@ use super::lookup::{
 
 use super::boolean::{
     Boolean, 
-    field_into_boolean_vec_le,
 };
 
 use super::ecc::EdwardsPoint;
@@ -60,12 +59,11 @@ impl <E: JubjubEngine>EddsaSignature<E> {
         where CS: ConstraintSystem<E>
     {
         // TODO check that s < Fs::Char
-        let scalar_bits = field_into_boolean_vec_le(
-            cs.namespace(|| "Get S bits"),
-            self.s.get_value()
+        let scalar_bits = self.s.into_bits_le(
+            cs.namespace(|| "Get S bits")
         )?;
 
-        let sb = generator.mul(
+        let sb = generator.mul_windowed(
             cs.namespace(|| "S*B computation"),
             &scalar_bits, params
         )?;
@@ -81,8 +79,9 @@ impl <E: JubjubEngine>EddsaSignature<E> {
 
         let mut hash_bits: Vec<Boolean> = vec![];
 
-        let r_x_serialized = field_into_boolean_vec_le(
-            cs.namespace(|| "Serialize R_X"), self.r.get_x().get_value()
+        // the bits must be bound to R, or the hash may be of any other point
+        let r_x_serialized = self.r.get_x().into_bits_le_strict(
+            cs.namespace(|| "Serialize R_X")
         )?;
 
         hash_bits.extend(r_x_serialized.into_iter());
@@ -99,7 +98,7 @@ impl <E: JubjubEngine>EddsaSignature<E> {
             MATTER_EDDSA_BLAKE2S_PERSONALIZATION
         )?;
         
-        let pk_mul_hash = self.pk.mul(
+        let pk_mul_hash = self.pk.mul_windowed(
             cs.namespace(|| "Calculate h*PK"), 
             &h, 
             params
@@ -169,7 +168,7 @@ impl <E: JubjubEngine>EddsaSignature<E> {
         //     &params
         // )?;
 
-        let sb = generator.mul(
+        let sb = generator.mul_windowed(
             cs.namespace(|| "S*B computation"),
             &scalar_bits, 
             params
@@ -188,7 +187,7 @@ impl <E: JubjubEngine>EddsaSignature<E> {
 
         assert_eq!(h.len(), 256);
         
-        let pk_mul_hash = self.pk.mul(
+        let pk_mul_hash = self.pk.mul_windowed(
             cs.namespace(|| "Calculate h*PK"), 
             &h, 
             params
@@ -234,8 +233,9 @@ mod test {
     use ::circuit::test::*;
     use ::circuit::boolean::{Boolean, AllocatedBit};
     use pairing::bn256::{Bn256, Fr};
-    use ff::{PrimeField, PrimeFieldRepr};
+    use ff::{BitIterator, PrimeField, PrimeFieldRepr};
     use ::alt_babyjubjub::AltJubjubBn256;
+    use ::util::scalar_to_base;
     
     #[test]
     fn test_valid_for_snark_signatures() {
@@ -261,7 +261,7 @@ mod test {
             }
         }
 
-        let sig1 = sk.sign_for_snark(msg1, &mut rng, p_g, params);
+        let sig1 = sk.sign_for_snark(msg1, p_g, params);
         assert!(vk.verify_for_snark(msg1, &sig1, p_g, params));
 
         let input_bools: Vec<Boolean> = input.iter().enumerate().map(|(i, b)| {
@@ -297,6 +297,47 @@ mod test {
         print!("EdDSA variant for snark verification takes constraints: {}\n", cs.num_constraints());
     }
 
+    #[test]
+    fn test_for_snark_signature_binds_r() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let sk = PrivateKey::<Bn256>(rng.gen());
+        let vk = PublicKey::from_private(&sk, p_g, params);
+
+        let msg1 = b"Foo bar pad to16";
+        let sig1 = sk.sign_for_snark(msg1, p_g, params);
+
+        let input_bools: Vec<Boolean> = msg1.iter().flat_map(|b| (0..8).map(move |i| (b >> i) & 1 == 1)).enumerate().map(|(i, b)| {
+            Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(b)).unwrap()
+            )
+        }).collect();
+
+        let s = AllocatedNum::alloc(cs.namespace(|| "allocate s"), || Ok(scalar_to_base::<Bn256>(sig1.s))).unwrap();
+
+        let public_generator = params.generator(p_g).clone();
+        let generator = EdwardsPoint::witness(cs.namespace(|| "allocate public generator"), Some(public_generator), params).unwrap();
+        let r = EdwardsPoint::witness(cs.namespace(|| "allocate r"), Some(sig1.r), params).unwrap();
+        let pk = EdwardsPoint::witness(cs.namespace(|| "allocate pk"), Some(vk.0), params).unwrap();
+
+        let signature = EddsaSignature{r, s, pk};
+        signature.verify_eddsa_for_snark(cs.namespace(|| "verify signature"), params, &input_bools, generator).unwrap();
+        assert!(cs.is_satisfied());
+
+        // hashing the x coordinate of another point instead of R's
+        let other = edwards::Point::<Bn256, _>::rand(&mut rng, params);
+        let (other_x, _) = other.into_xy();
+        for (i, bit) in BitIterator::new(other_x.into_repr()).skip(256 - Fr::NUM_BITS as usize).enumerate() {
+            cs.set(
+                &format!("verify signature/Serialize R_X/bit {}/boolean", i),
+                if bit { Fr::one() } else { Fr::zero() }
+            );
+        }
+        assert!(cs.which_is_unsatisfied().unwrap().starts_with("verify signature/Serialize R_X/"));
+    }
+
     #[test]
     fn test_valid_raw_message_signatures() {
         let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
@@ -321,7 +362,7 @@ mod test {
         }
 
         // test for maximum message length of 16 bytes
-        let sig1 = sk.sign_raw_message(msg1, &mut rng, p_g, params, 16);
+        let sig1 = sk.sign_raw_message(msg1, p_g, params, 16);
         assert!(vk.verify_for_raw_message(msg1, &sig1, p_g, params, 16));
 
         let input_bools: Vec<Boolean> = input.iter().enumerate().map(|(i, b)| {
@@ -0,0 +1,269 @@
+use pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use super::uint64::{
+    UInt64
+};
+
+use super::multieq::MultiEq;
+
+/*
+   BLAKE2b differs from BLAKE2s (see the `blake2s` gadget) in its
+   parameters: 64 bit words, 12 rounds, 128 byte blocks, up to 64 bytes
+   of output, a 16 byte personalization and the rotation constants
+   (R1, R2, R3, R4) = (32, 24, 16, 63).
+*/
+
+const R1: usize = 32;
+const R2: usize = 24;
+const R3: usize = 16;
+const R4: usize = 63;
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+];
+
+fn mixing_g<E: Engine, CS: ConstraintSystem<E>, M>(
+    mut cs: M,
+    v: &mut [UInt64],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: &UInt64,
+    y: &UInt64
+) -> Result<(), SynthesisError>
+    where M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+{
+    v[a] = UInt64::addmany(cs.namespace(|| "mixing step 1"), &[v[a].clone(), v[b].clone(), x.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 2"), &v[a])?.rotr(R1);
+    v[c] = UInt64::addmany(cs.namespace(|| "mixing step 3"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 4"), &v[c])?.rotr(R2);
+    v[a] = UInt64::addmany(cs.namespace(|| "mixing step 5"), &[v[a].clone(), v[b].clone(), y.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 6"), &v[a])?.rotr(R3);
+    v[c] = UInt64::addmany(cs.namespace(|| "mixing step 7"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 8"), &v[c])?.rotr(R4);
+
+    Ok(())
+}
+
+fn blake2b_compression<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    h: &mut [UInt64],
+    m: &[UInt64],
+    t: u64,
+    f: bool
+) -> Result<(), SynthesisError>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(m.len(), 16);
+
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(h);
+    v.extend(IV.iter().map(|&iv| UInt64::constant(iv)));
+
+    assert_eq!(v.len(), 16);
+
+    // The high word of the offset is always zero, as the
+    // length of the input fits in 64 bits
+    v[12] = v[12].xor(cs.namespace(|| "first xor"), &UInt64::constant(t))?;
+
+    if f {
+        v[14] = v[14].xor(cs.namespace(|| "third xor"), &UInt64::constant(u64::max_value()))?;
+    }
+
+    {
+        let mut cs = MultiEq::new(&mut cs);
+
+        for i in 0..12 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+
+            let s = SIGMA[i % 10];
+
+            mixing_g(cs.namespace(|| "mixing invocation 1"), &mut v, 0, 4,  8, 12, &m[s[ 0]], &m[s[ 1]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 2"), &mut v, 1, 5,  9, 13, &m[s[ 2]], &m[s[ 3]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 3"), &mut v, 2, 6, 10, 14, &m[s[ 4]], &m[s[ 5]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 4"), &mut v, 3, 7, 11, 15, &m[s[ 6]], &m[s[ 7]])?;
+
+            mixing_g(cs.namespace(|| "mixing invocation 5"), &mut v, 0, 5, 10, 15, &m[s[ 8]], &m[s[ 9]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 6"), &mut v, 1, 6, 11, 12, &m[s[10]], &m[s[11]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 7"), &mut v, 2, 7,  8, 13, &m[s[12]], &m[s[13]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 8"), &mut v, 3, 4,  9, 14, &m[s[14]], &m[s[15]])?;
+        }
+    }
+
+    for i in 0..8 {
+        let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i=i));
+
+        h[i] = h[i].xor(cs.namespace(|| "first xor"), &v[i])?;
+        h[i] = h[i].xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+/// Computes the BLAKE2b digest of `input` with an `output_len` byte
+/// output and no key. The personalization is at most 16 bytes and is
+/// padded with zeros, like in `blake2_rfc`. Bits are little-endian
+/// within each byte, like in the `blake2s` gadget.
+pub fn blake2b<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+    output_len: usize,
+    personalization: &[u8]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    use byteorder::{ByteOrder, LittleEndian};
+    assert!(output_len >= 1 && output_len <= 64);
+    assert!(personalization.len() <= 16);
+    assert!(input.len() % 8 == 0);
+
+    let mut padded_personalization = [0u8; 16];
+    padded_personalization[0..personalization.len()].copy_from_slice(personalization);
+
+    let mut h = Vec::with_capacity(8);
+    h.push(UInt64::constant(IV[0] ^ 0x01010000 ^ (output_len as u64)));
+    h.push(UInt64::constant(IV[1]));
+    h.push(UInt64::constant(IV[2]));
+    h.push(UInt64::constant(IV[3]));
+    h.push(UInt64::constant(IV[4]));
+    h.push(UInt64::constant(IV[5]));
+
+    // Personalization is stored here
+    h.push(UInt64::constant(IV[6] ^ LittleEndian::read_u64(&padded_personalization[0..8])));
+    h.push(UInt64::constant(IV[7] ^ LittleEndian::read_u64(&padded_personalization[8..16])));
+
+    let mut blocks: Vec<Vec<UInt64>> = vec![];
+
+    for block in input.chunks(1024) {
+        let mut this_block = Vec::with_capacity(16);
+        for word in block.chunks(64) {
+            let mut tmp = word.to_vec();
+            while tmp.len() < 64 {
+                tmp.push(Boolean::constant(false));
+            }
+            this_block.push(UInt64::from_bits(&tmp));
+        }
+        while this_block.len() < 16 {
+            this_block.push(UInt64::constant(0));
+        }
+        blocks.push(this_block);
+    }
+
+    if blocks.len() == 0 {
+        blocks.push((0..16).map(|_| UInt64::constant(0)).collect());
+    }
+
+    for (i, block) in blocks[0..blocks.len() - 1].iter().enumerate() {
+        let cs = cs.namespace(|| format!("block {}", i));
+
+        blake2b_compression(cs, &mut h, block, ((i as u64) + 1) * 128, false)?;
+    }
+
+    {
+        let cs = cs.namespace(|| "final block");
+
+        blake2b_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+    }
+
+    Ok(h.iter().flat_map(|b| b.into_bits()).take(output_len * 8).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use pairing::bn256::{Bn256};
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::test::TestConstraintSystem;
+    use super::blake2b;
+    use bellman::{ConstraintSystem};
+    use ::util::{blake2b_hash, blake2_256};
+
+    fn assert_digest(bits: &[Boolean], expected: &[u8]) {
+        let expected: Vec<bool> = expected.iter()
+                                          .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                                          .collect();
+        let bits: Vec<bool> = bits.iter().map(|b| b.get_value().unwrap()).collect();
+
+        assert_eq!(bits, expected);
+    }
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let out = blake2b(&mut cs, &[], 32, &[]).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+
+        // >>> hashlib.blake2b(digest_size=32).hexdigest()
+        assert_digest(&out, &hex!("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"));
+        assert_digest(&out, &blake2_256(&[]));
+    }
+
+    #[test]
+    fn test_blake2b_constraints() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input_bits: Vec<_> = (0..1024).map(|i| AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(true)).unwrap().into()).collect();
+        blake2b(&mut cs, &input_bits, 32, &[]).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - 1024, 49856);
+    }
+
+    #[test]
+    fn test_blake2b() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &(input_len, output_len, personalization) in [
+            (0, 64, &b""[..]),
+            (1, 32, &b""[..]),
+            (32, 32, &b"substrate"[..]),
+            (127, 16, &b"0123456789abcdef"[..]),
+            (128, 32, &b""[..]),
+            (200, 20, &b"mixer"[..])
+        ].iter() {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let mut input_bits = vec![];
+
+            for (byte_i, input_byte) in data.iter().enumerate() {
+                for bit_i in 0..8 {
+                    let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+
+                    input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+                }
+            }
+
+            let r = blake2b(&mut cs, &input_bits, output_len, personalization).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.len(), output_len * 8);
+            assert_digest(&r, &blake2b_hash(output_len, personalization, &data));
+        }
+    }
+}
@@ -260,30 +260,86 @@ fn blake2s_compression<E: Engine, CS: ConstraintSystem<E>>(
 */
 
 pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
-    mut cs: CS,
+    cs: CS,
     input: &[Boolean],
     personalization: &[u8]
 ) -> Result<Vec<Boolean>, SynthesisError>
 {
-    use byteorder::{ByteOrder, LittleEndian};
     assert_eq!(personalization.len(), 8);
     assert!(input.len() % 8 == 0);
 
+    blake2s_with_params(cs, input, 32, &[], &[], personalization)
+}
+
+/// Computes the BLAKE2s digest of `input` with an `output_len` byte
+/// output, like `blake2_rfc::blake2s::Blake2s::with_params`.
+///
+/// The key is given as bits so that it can be a witness; it is a whole
+/// number of bytes, at most 32 of them, and an empty key means no key.
+/// The salt and the personalization are at most 8 bytes each and are
+/// padded with zeros.
+///
+/// Bits are little-endian within each byte. The input doesn't need to
+/// be a whole number of bytes: its last byte is completed with zero
+/// bits, which are its most significant ones, and the number of those
+/// bits is stored in the inner length byte of the parameter block, so
+/// that inputs of different bit lengths never collide. It is zero for
+/// whole bytes, whose digest is the standard one.
+pub fn blake2s_with_params<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+    output_len: usize,
+    key: &[Boolean],
+    salt: &[u8],
+    personalization: &[u8]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    use byteorder::{ByteOrder, LittleEndian};
+    assert!(output_len >= 1 && output_len <= 32);
+    assert!(key.len() % 8 == 0 && key.len() <= 256);
+    assert!(salt.len() <= 8);
+    assert!(personalization.len() <= 8);
+
+    let key_len = key.len() / 8;
+    let padding_bits = (8 - input.len() % 8) % 8;
+
+    let mut padded_salt = [0u8; 8];
+    padded_salt[0..salt.len()].copy_from_slice(salt);
+
+    let mut padded_personalization = [0u8; 8];
+    padded_personalization[0..personalization.len()].copy_from_slice(personalization);
+
     let mut h = Vec::with_capacity(8);
-    h.push(UInt32::constant(0x6A09E667 ^ 0x01010000 ^ 32));
+    h.push(UInt32::constant(0x6A09E667 ^ 0x01010000 ^ ((key_len as u32) << 8) ^ (output_len as u32)));
     h.push(UInt32::constant(0xBB67AE85));
     h.push(UInt32::constant(0x3C6EF372));
-    h.push(UInt32::constant(0xA54FF53A));
-    h.push(UInt32::constant(0x510E527F));
-    h.push(UInt32::constant(0x9B05688C));
+    h.push(UInt32::constant(0xA54FF53A ^ ((padding_bits as u32) << 24)));
+
+    // Salt is stored here
+    h.push(UInt32::constant(0x510E527F ^ LittleEndian::read_u32(&padded_salt[0..4])));
+    h.push(UInt32::constant(0x9B05688C ^ LittleEndian::read_u32(&padded_salt[4..8])));
 
     // Personalization is stored here
-    h.push(UInt32::constant(0x1F83D9AB ^ LittleEndian::read_u32(&personalization[0..4])));
-    h.push(UInt32::constant(0x5BE0CD19 ^ LittleEndian::read_u32(&personalization[4..8])));
+    h.push(UInt32::constant(0x1F83D9AB ^ LittleEndian::read_u32(&padded_personalization[0..4])));
+    h.push(UInt32::constant(0x5BE0CD19 ^ LittleEndian::read_u32(&padded_personalization[4..8])));
+
+    // The key, if any, is padded to a whole block and comes first
+    let mut data = Vec::with_capacity(512 + input.len() + 7);
+    if key_len > 0 {
+        data.extend_from_slice(key);
+        while data.len() < 512 {
+            data.push(Boolean::constant(false));
+        }
+    }
+
+    data.extend_from_slice(input);
+    while data.len() % 8 != 0 {
+        data.push(Boolean::constant(false));
+    }
 
     let mut blocks: Vec<Vec<UInt32>> = vec![];
 
-    for block in input.chunks(512) {
+    for block in data.chunks(512) {
         let mut this_block = Vec::with_capacity(16);
         for word in block.chunks(32) {
             let mut tmp = word.to_vec();
@@ -311,10 +367,10 @@ pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
     {
         let cs = cs.namespace(|| "final block");
 
-        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (data.len() / 8) as u64, true)?;
     }
 
-    Ok(h.iter().flat_map(|b| b.into_bits()).collect())
+    Ok(h.iter().flat_map(|b| b.into_bits()).take(output_len * 8).collect())
 }
 
 #[cfg(test)]
@@ -323,9 +379,9 @@ mod test {
     use pairing::bls12_381::{Bls12};
     use ::circuit::boolean::{Boolean, AllocatedBit};
     use ::circuit::test::TestConstraintSystem;
-    use super::blake2s;
+    use super::{blake2s, blake2s_with_params};
     use bellman::{ConstraintSystem};
-    use blake2_rfc::blake2s::Blake2s;
+    use blake2_rfc::blake2s::{Blake2s, Blake2sResult};
 
     #[test]
     fn test_blank_hash() {
@@ -434,4 +490,127 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_blake2s_with_params() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &(input_bits_len, output_len, key_len, salt, personalization) in [
+            (0, 32, 0, &b""[..], &b""[..]),
+            (0, 16, 32, &b""[..], &b""[..]),
+            (3, 32, 0, &b""[..], &b"12345678"[..]),
+            (8, 1, 1, &b"salt"[..], &b"persona"[..]),
+            (255, 20, 16, &b"saltsalt"[..], &b""[..]),
+            (512, 32, 32, &b""[..], &b"12345678"[..]),
+            (777, 28, 7, &b"s"[..], &b"p"[..])
+        ].iter() {
+            let input: Vec<bool> = (0..input_bits_len).map(|_| rng.gen()).collect();
+            let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+
+            // The last byte is completed with zero bits
+            let data: Vec<u8> = input.chunks(8).map(|byte| {
+                byte.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | ((b as u8) << i))
+            }).collect();
+
+            let hash_result = if input_bits_len % 8 == 0 {
+                let mut h = Blake2s::with_params(output_len, &key, salt, personalization);
+                h.update(&data);
+                h.finalize()
+            } else {
+                blake2s_padded_bits(&data, input_bits_len, output_len, &key, salt, personalization)
+            };
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let input_bits: Vec<Boolean> = input.iter().enumerate().map(|(i, &b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap().into()
+            }).collect();
+
+            let key_bits: Vec<Boolean> = key.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                                            .enumerate()
+                                            .map(|(i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("key bit {}", i)), Some(b)).unwrap().into()
+            }).collect();
+
+            let r = blake2s_with_params(
+                cs.namespace(|| "blake2s"),
+                &input_bits,
+                output_len,
+                &key_bits,
+                salt,
+                personalization
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.len(), output_len * 8);
+
+            let expected: Vec<bool> = hash_result.as_ref().iter()
+                                                 .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                                                 .collect();
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+
+            assert_eq!(r, expected);
+        }
+    }
+
+    /// BLAKE2s of bytes completed from `bits_len` bits, with the number
+    /// of padding bits in the inner length byte of the parameter block.
+    fn blake2s_padded_bits(
+        data: &[u8],
+        bits_len: usize,
+        output_len: usize,
+        key: &[u8],
+        salt: &[u8],
+        personalization: &[u8]
+    ) -> Blake2sResult
+    {
+        let mut pb = [0u8; 32];
+        pb[0] = output_len as u8;
+        pb[1] = key.len() as u8;
+        pb[2] = 1;
+        pb[3] = 1;
+        pb[15] = ((8 - bits_len % 8) % 8) as u8;
+        pb[16..16 + salt.len()].copy_from_slice(salt);
+        pb[24..24 + personalization.len()].copy_from_slice(personalization);
+
+        let mut p = [0u32; 8];
+        for (word, bytes) in p.iter_mut().zip(pb.chunks(4)) {
+            *word = bytes.iter().enumerate().fold(0, |acc, (i, &b)| acc | ((b as u32) << (8 * i)));
+        }
+
+        let mut h = Blake2s::with_parameter_block(&p);
+        if key.len() > 0 {
+            let mut key_block = [0u8; 64];
+            key_block[0..key.len()].copy_from_slice(key);
+            h.update(&key_block);
+        }
+        h.update(data);
+        h.finalize()
+    }
+
+    #[test]
+    fn test_blake2s_with_params_bit_length() {
+        // 101, 1010 and 10100000 complete to the same byte
+        let mut digests = vec![];
+        for input in [&[true, false, true][..], &[true, false, true, false][..], &[true, false, true, false, false, false, false, false][..]].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits: Vec<Boolean> = input.iter().map(|&b| Boolean::constant(b)).collect();
+            let r = blake2s_with_params(&mut cs, &input_bits, 32, &[], &[], b"12345678").unwrap();
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+            assert!(!digests.contains(&r));
+            digests.push(r);
+        }
+    }
+
+    #[test]
+    fn test_blake2s_with_params_key_constraints() {
+        // A key costs an extra block, which is cheaper than a full one
+        // of witnesses as its padding is constant
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits: Vec<_> = (0..512).map(|i| AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(true)).unwrap().into()).collect();
+        let key_bits: Vec<_> = (0..256).map(|i| AllocatedBit::alloc(cs.namespace(|| format!("key bit {}", i)), Some(false)).unwrap().into()).collect();
+        blake2s_with_params(&mut cs, &input_bits, 32, &key_bits, &[], b"12345678").unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - 768, 42396);
+    }
 }
@@ -0,0 +1,218 @@
+//! Gadget verifying EdDSA-Poseidon signatures over alt-babyjubjub, the
+//! counterpart of `eddsa_poseidon` and of circomlib's
+//! `EdDSAPoseidonVerifier`.
+
+use ff::{Field, PrimeField};
+use pairing::bn256::{Bn256, Fr, FrRepr};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+use super::num::AllocatedNum;
+use super::ecc::EdwardsPoint;
+use super::poseidon::poseidon_hash;
+
+use ::alt_babyjubjub::{AltJubjubBn256, JubjubParams, fs::Fs};
+use ::eddsa_poseidon::base8;
+use ::poseidon::PoseidonParams;
+
+#[derive(Clone)]
+pub struct EddsaPoseidonSignature {
+    pub r: EdwardsPoint<Bn256>,
+    pub s: AllocatedNum<Bn256>,
+    pub pk: EdwardsPoint<Bn256>
+}
+
+/// Returns the x coordinate of a point in circomlib's form of the curve.
+fn circomlib_x<CS>(
+    mut cs: CS,
+    p: &EdwardsPoint<Bn256>,
+    params: &AltJubjubBn256
+) -> Result<AllocatedNum<Bn256>, SynthesisError>
+    where CS: ConstraintSystem<Bn256>
+{
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+        let mut x = *p.get_x().get_value().get()?;
+        x.mul_assign(&params.scale().inverse().expect("scale is nonzero"));
+        Ok(x)
+    })?;
+
+    cs.enforce(
+        || "x is scaled",
+        |lc| lc + x.get_variable(),
+        |lc| lc + (*params.scale(), CS::one()),
+        |lc| lc + p.get_x().get_variable()
+    );
+
+    Ok(x)
+}
+
+impl EddsaPoseidonSignature {
+    /// Enforces that the signature of `message` is valid, that is
+    /// `S . Base8 = R + Poseidon(R, A, M) . (8 . A)` with `S` below the
+    /// subgroup order and `A` not of small order.
+    pub fn verify<CS>(
+        &self,
+        mut cs: CS,
+        message: &AllocatedNum<Bn256>,
+        params: &AltJubjubBn256,
+        poseidon_params: &PoseidonParams<Bn256>
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<Bn256>
+    {
+        assert_eq!(poseidon_params.num_inputs(), 5);
+
+        // S < l, otherwise S + l would be another valid signature
+        let mut max = Fr::from_repr(FrRepr(Fs::char().0)).unwrap();
+        max.sub_assign(&Fr::one());
+        self.s.assert_in_range(
+            cs.namespace(|| "S is below the subgroup order"),
+            &Fr::zero(),
+            &max,
+            Fs::NUM_BITS as usize
+        )?;
+        let s_bits = self.s.into_bits_le(cs.namespace(|| "S bits"))?;
+
+        let base = base8(params);
+        let (base_x, base_y) = base.into_xy();
+        let base = EdwardsPoint::witness(cs.namespace(|| "Base8"), Some(base), params)?;
+        cs.enforce(
+            || "Base8 x is fixed",
+            |lc| lc + base.get_x().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (base_x, CS::one())
+        );
+        cs.enforce(
+            || "Base8 y is fixed",
+            |lc| lc + base.get_y().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (base_y, CS::one())
+        );
+
+        let sb = base.mul_windowed(
+            cs.namespace(|| "S*Base8 computation"),
+            &s_bits,
+            params
+        )?;
+
+        // h = Poseidon(R_x, R_y, A_x, A_y, M) in circomlib's coordinates
+        let r_x = circomlib_x(cs.namespace(|| "R_x in circomlib coordinates"), &self.r, params)?;
+        let pk_x = circomlib_x(cs.namespace(|| "A_x in circomlib coordinates"), &self.pk, params)?;
+        let h = poseidon_hash(
+            cs.namespace(|| "Calculate EdDSA hash"),
+            &[r_x, self.r.get_y().clone(), pk_x, self.pk.get_y().clone(), message.clone()],
+            poseidon_params
+        )?;
+        let h_bits = h.into_bits_le_strict(cs.namespace(|| "EdDSA hash bits"))?;
+
+        // 8*A is the neutral element (0, 1) iff A has small order
+        let pk_8 = self.pk.double(cs.namespace(|| "2*A"), params)?;
+        let pk_8 = pk_8.double(cs.namespace(|| "4*A"), params)?;
+        let pk_8 = pk_8.double(cs.namespace(|| "8*A"), params)?;
+        pk_8.get_x().assert_nonzero(cs.namespace(|| "A is not of small order"))?;
+
+        let pk_mul_hash = pk_8.mul_windowed(
+            cs.namespace(|| "Calculate h*8*A"),
+            &h_bits,
+            params
+        )?;
+
+        let rhs = pk_mul_hash.add(
+            cs.namespace(|| "Make signature RHS"),
+            &self.r,
+            params
+        )?;
+
+        cs.enforce(
+            || "check x coordinate of signature",
+            |lc| lc + rhs.get_x().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + sb.get_x().get_variable()
+        );
+        cs.enforce(
+            || "check y coordinate of signature",
+            |lc| lc + rhs.get_y().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + sb.get_y().get_variable()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+    use pairing::bn256::{Bn256, Fr, FrRepr};
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::ConstraintSystem;
+
+    use ::alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use ::circuit::test::*;
+    use ::eddsa_poseidon::{PrivateKey, PublicKey, Signature};
+    use ::poseidon::PoseidonParams;
+    use ::util::scalar_to_base;
+    use super::*;
+
+    fn verify_in_circuit(
+        pk: &PublicKey,
+        sig: &Signature,
+        s: Fr,
+        msg: Fr,
+        params: &AltJubjubBn256,
+        poseidon_params: &PoseidonParams<Bn256>
+    ) -> TestConstraintSystem<Bn256> {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let message = AllocatedNum::alloc(cs.namespace(|| "message"), || Ok(msg)).unwrap();
+        let signature = EddsaPoseidonSignature {
+            r: EdwardsPoint::witness(cs.namespace(|| "r"), Some(sig.r.clone()), params).unwrap(),
+            s: AllocatedNum::alloc(cs.namespace(|| "s"), || Ok(s)).unwrap(),
+            pk: EdwardsPoint::witness(cs.namespace(|| "pk"), Some(pk.0.clone()), params).unwrap(),
+        };
+        signature.verify(cs.namespace(|| "verify signature"), &message, params, poseidon_params).unwrap();
+        cs
+    }
+
+    #[test]
+    fn test_eddsa_poseidon_gadget() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let poseidon_params = &PoseidonParams::new(5);
+
+        for _ in 0..5 {
+            let sk = PrivateKey(rng.gen());
+            let pk = PublicKey::from_private(&sk, params);
+            let msg: Fr = rng.gen();
+            let sig = sk.sign(msg, params, poseidon_params);
+
+            let cs = verify_in_circuit(&pk, &sig, scalar_to_base::<Bn256>(sig.s), msg, params, poseidon_params);
+            assert!(cs.is_satisfied());
+
+            let cs = verify_in_circuit(&pk, &sig, scalar_to_base::<Bn256>(sig.s), rng.gen(), params, poseidon_params);
+            assert_eq!(cs.which_is_unsatisfied(), Some("verify signature/check x coordinate of signature"));
+
+            // S + l passes the curve equation but not the range check
+            let mut s = scalar_to_base::<Bn256>(sig.s);
+            s.add_assign(&Fr::from_repr(FrRepr(Fs::char().0)).unwrap());
+            let cs = verify_in_circuit(&pk, &sig, s, msg, params, poseidon_params);
+            assert!(cs.which_is_unsatisfied().unwrap().starts_with("verify signature/S is below the subgroup order"));
+        }
+    }
+
+    #[test]
+    fn test_eddsa_poseidon_gadget_circomlib_key() {
+        let params = &AltJubjubBn256::new();
+        let poseidon_params = &PoseidonParams::new(5);
+        let sk = PrivateKey(hex!("0001020304050607080900010203040506070809000102030405060708090001"));
+        let pk = PublicKey::from_private(&sk, params);
+        let msg = Fr::from_str("1234").unwrap();
+        let sig = sk.sign(msg, params, poseidon_params);
+
+        let cs = verify_in_circuit(&pk, &sig, scalar_to_base::<Bn256>(sig.s), msg, params, poseidon_params);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 7056);
+    }
+}
@@ -0,0 +1,224 @@
+use super::boolean::Boolean;
+use bellman::{ConstraintSystem, SynthesisError};
+use pairing::Engine;
+
+use ::keccak::{ROUND_CONSTANTS, ROTATION_OFFSETS, KECCAK256_RATE};
+
+/// A 64 bit lane of the Keccak state, least significant bit first.
+type Lane = Vec<Boolean>;
+
+fn lane_constant(value: u64) -> Lane {
+    (0..64).map(|i| Boolean::constant((value >> i) & 1 == 1)).collect()
+}
+
+fn lane_rotate_left(lane: &Lane, by: u32) -> Lane {
+    let by = by as usize % 64;
+    (0..64).map(|i| lane[(i + 64 - by) % 64].clone()).collect()
+}
+
+fn lane_xor<E, CS>(
+    mut cs: CS,
+    a: &Lane,
+    b: &Lane
+) -> Result<Lane, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    a.iter().zip(b.iter()).enumerate().map(|(i, (a, b))| {
+        Boolean::xor(cs.namespace(|| format!("xor bit {}", i)), a, b)
+    }).collect()
+}
+
+/// Computes `a ^ (!b & c)`, which is the χ step for a single lane.
+fn lane_chi<E, CS>(
+    mut cs: CS,
+    a: &Lane,
+    b: &Lane,
+    c: &Lane
+) -> Result<Lane, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut result = Vec::with_capacity(64);
+
+    for i in 0..64 {
+        let mut cs = cs.namespace(|| format!("bit {}", i));
+
+        let tmp = Boolean::and(cs.namespace(|| "and not"), &b[i].not(), &c[i])?;
+        result.push(Boolean::xor(cs.namespace(|| "xor"), &a[i], &tmp)?);
+    }
+
+    Ok(result)
+}
+
+/// Applies Keccak-f[1600] to the state, whose lanes are indexed by
+/// `x + 5 * y`. See `keccak::keccak_f1600`.
+pub fn keccak_f1600<E, CS>(
+    mut cs: CS,
+    state: &[Lane]
+) -> Result<Vec<Lane>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), 25);
+    assert!(state.iter().all(|lane| lane.len() == 64));
+
+    let mut state = state.to_vec();
+
+    for (round, rc) in ROUND_CONSTANTS.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        // θ
+        let mut c = Vec::with_capacity(5);
+        for x in 0..5 {
+            let mut cs = cs.namespace(|| format!("theta column {}", x));
+            let mut parity = state[x].clone();
+            for y in 1..5 {
+                parity = lane_xor(cs.namespace(|| format!("row {}", y)), &parity, &state[x + 5 * y])?;
+            }
+            c.push(parity);
+        }
+        for x in 0..5 {
+            let mut cs = cs.namespace(|| format!("theta lane {}", x));
+            let d = lane_xor(
+                cs.namespace(|| "d"),
+                &c[(x + 4) % 5],
+                &lane_rotate_left(&c[(x + 1) % 5], 1)
+            )?;
+            for y in 0..5 {
+                state[x + 5 * y] = lane_xor(cs.namespace(|| format!("row {}", y)), &state[x + 5 * y], &d)?;
+            }
+        }
+
+        // ρ and π
+        let mut b = vec![vec![]; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = lane_rotate_left(&state[x + 5 * y], ROTATION_OFFSETS[x + 5 * y]);
+            }
+        }
+
+        // χ
+        for x in 0..5 {
+            for y in 0..5 {
+                state[x + 5 * y] = lane_chi(
+                    cs.namespace(|| format!("chi lane {}", x + 5 * y)),
+                    &b[x + 5 * y],
+                    &b[(x + 1) % 5 + 5 * y],
+                    &b[(x + 2) % 5 + 5 * y]
+                )?;
+            }
+        }
+
+        // ι, which only flips bits, so it is free
+        state[0] = state[0].iter().zip(lane_constant(*rc).iter()).map(|(a, rc)| {
+            if rc.get_value() == Some(true) { a.not() } else { a.clone() }
+        }).collect();
+    }
+
+    Ok(state)
+}
+
+/// Computes Keccak-256 of `input`, as used by Ethereum. The input
+/// and the 256 output bits are bytes, with the most significant bit
+/// of each byte first, like in the `sha256` gadget.
+pub fn keccak256<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    // Keccak reads bytes into lanes in little-endian order
+    let mut padded: Vec<Boolean> = input.chunks(8).flat_map(|byte| byte.iter().rev().cloned()).collect();
+
+    // pad10*1
+    padded.push(Boolean::constant(true));
+    while (padded.len() + 1) % (KECCAK256_RATE * 8) != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    padded.push(Boolean::constant(true));
+
+    let mut state: Vec<Lane> = (0..25).map(|_| lane_constant(0)).collect();
+
+    for (i, block) in padded.chunks(KECCAK256_RATE * 8).enumerate() {
+        let mut cs = cs.namespace(|| format!("block {}", i));
+
+        for (j, lane) in block.chunks(64).enumerate() {
+            state[j] = lane_xor(cs.namespace(|| format!("absorb lane {}", j)), &state[j], &lane.to_vec())?;
+        }
+
+        state = keccak_f1600(cs.namespace(|| "keccak-f"), &state)?;
+    }
+
+    Ok(state[0..4].iter()
+        .flat_map(|lane| lane.iter())
+        .cloned()
+        .collect::<Vec<_>>()
+        .chunks(8)
+        .flat_map(|byte| byte.iter().rev().cloned().collect::<Vec<_>>())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use circuit::boolean::AllocatedBit;
+    use pairing::bn256::Bn256;
+    use circuit::test::TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+
+    fn bytes_to_bits_be(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    #[test]
+    fn test_keccak256_constant_input() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let out = keccak256(&mut cs, &[]).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+
+        let expected = hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+        let out: Vec<bool> = out.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(out, bytes_to_bits_be(&expected));
+    }
+
+    #[test]
+    fn test_keccak256_against_native() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // Lengths around the rate exercise the padding and a second block
+        for &input_len in [1, 32, 64, KECCAK256_RATE - 1, KECCAK256_RATE].iter() {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let input_bits: Vec<Boolean> = bytes_to_bits_be(&data).into_iter().enumerate().map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap()
+                )
+            }).collect();
+
+            let out = keccak256(cs.namespace(|| "keccak256"), &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let out: Vec<bool> = out.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(out, bytes_to_bits_be(&::keccak::keccak256(&data)));
+        }
+    }
+
+    #[test]
+    fn test_keccak256_constraints() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input_bits: Vec<Boolean> = (0..512).map(|i| {
+            Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(i % 3 == 0)).unwrap()
+            )
+        }).collect();
+
+        keccak256(cs.namespace(|| "keccak256"), &input_bits).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - 512, 151424);
+    }
+}
@@ -7,7 +7,9 @@ use super::num::{
 };
 use super::boolean::Boolean;
 use bellman::{
-    ConstraintSystem
+    ConstraintSystem,
+    LinearCombination,
+    Variable
 };
 
 // Synthesize the constants for each base pattern.
@@ -193,6 +195,264 @@ pub fn lookup3_xy_with_conditional_negation<E: Engine, CS>(
     Ok((x, y.into()))
 }
 
+/// Performs a `k`-bit window table lookup, where `k` is the
+/// length of `bits` in little-endian order. Every entry of
+/// `coords` holds the same number of coordinates, one number
+/// being allocated for each of them.
+///
+/// This costs `2^(k-1) - k` constraints for the products of
+/// the bits, shared by all coordinates, and one constraint per
+/// coordinate.
+pub fn lookup_k<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    coords: &[Vec<E::Fr>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let window_size = bits.len();
+    assert!(window_size > 0);
+    assert_eq!(coords.len(), 1 << window_size);
+    let num_coordinates = coords[0].len();
+    assert!(num_coordinates > 0);
+    assert!(coords.iter().all(|c| c.len() == num_coordinates));
+
+    // Calculate the index into `coords`
+    let mut i = Some(0);
+    for (j, bit) in bits.iter().enumerate() {
+        i = match (i, bit.get_value()) {
+            (Some(i), Some(true)) => Some(i | (1 << j)),
+            (Some(i), Some(false)) => Some(i),
+            _ => None
+        };
+    }
+
+    // The product of the bits of every subset of `bits`, indexed
+    // like `coords`; products involving bits[0] are not needed
+    // as the lookup constraints multiply by bits[0] themselves
+    let mut products = vec![Boolean::constant(true)];
+    for (j, bit) in bits.iter().enumerate().skip(1) {
+        for subset in 0..(1 << j) {
+            if subset & 1 == 1 {
+                continue;
+            }
+            let product = if subset == 0 {
+                bit.clone()
+            } else {
+                Boolean::and(
+                    cs.namespace(|| format!("precomp {}", subset | (1 << j))),
+                    &products[subset >> 1],
+                    bit
+                )?
+            };
+            products.push(product);
+        }
+    }
+
+    let one = CS::one();
+    let mut res = Vec::with_capacity(num_coordinates);
+    for c in 0..num_coordinates {
+        let res_c = AllocatedNum::alloc(
+            cs.namespace(|| format!("coordinate {}", c)),
+            || {
+                Ok(coords[*i.get()?][c])
+            }
+        )?;
+
+        // Compute the coefficients for the lookup constraint
+        let mut coeffs = vec![E::Fr::zero(); 1 << window_size];
+        synth::<E, _>(window_size, coords.iter().map(|p| &p[c]), &mut coeffs);
+
+        let mut with_bit0 = LinearCombination::<E>::zero();
+        let mut without_bit0 = LinearCombination::<E>::zero();
+        for (subset, product) in products.iter().enumerate() {
+            with_bit0 = with_bit0 + &product.lc::<E>(one, coeffs[(subset << 1) | 1]);
+            without_bit0 = without_bit0 + &product.lc::<E>(one, coeffs[subset << 1]);
+        }
+
+        cs.enforce(
+            || format!("coordinate {} lookup", c),
+            |lc| lc + &with_bit0,
+            |lc| lc + &bits[0].lc::<E>(one, E::Fr::one()),
+            |lc| lc + res_c.get_variable() - &without_bit0
+        );
+
+        res.push(res_c);
+    }
+
+    Ok(res)
+}
+
+/// A coordinate of a multiplexed entry, which is either known
+/// ahead of time or allocated.
+enum Coordinate<E: Engine> {
+    Constant(E::Fr),
+    Allocated(AllocatedNum<E>)
+}
+
+impl<E: Engine> Clone for Coordinate<E> {
+    fn clone(&self) -> Self {
+        match *self {
+            Coordinate::Constant(value) => Coordinate::Constant(value),
+            Coordinate::Allocated(ref num) => Coordinate::Allocated(num.clone())
+        }
+    }
+}
+
+impl<E: Engine> Coordinate<E> {
+    fn get_value(&self) -> Option<E::Fr> {
+        match *self {
+            Coordinate::Constant(value) => Some(value),
+            Coordinate::Allocated(ref num) => num.get_value()
+        }
+    }
+
+    fn lc(&self, one: Variable) -> LinearCombination<E> {
+        match *self {
+            Coordinate::Constant(value) => LinearCombination::<E>::zero() + (value, one),
+            Coordinate::Allocated(ref num) => LinearCombination::<E>::zero() + num.get_variable()
+        }
+    }
+
+    // Allocated coordinates are copies of the same variable
+    fn is_copy_of(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&Coordinate::Allocated(ref a), &Coordinate::Allocated(ref b)) => {
+                a.get_variable().get_unchecked() == b.get_variable().get_unchecked()
+            },
+            _ => false
+        }
+    }
+}
+
+/// Selects the entry of `entries` at the index given by `bits`
+/// in little-endian order, where the entries are allocated
+/// numbers rather than constants. Every entry holds the same
+/// number of coordinates.
+///
+/// This costs at most `2^k - 1` constraints per coordinate; a
+/// selection between two copies of the same variable is free.
+pub fn multiplex_k<E: Engine, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    entries: &[Vec<AllocatedNum<E>>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let entries: Vec<Vec<Coordinate<E>>> = entries.iter().map(|e| {
+        e.iter().map(|c| Coordinate::Allocated(c.clone())).collect()
+    }).collect();
+
+    multiplex_coordinates(cs, bits, &entries)
+}
+
+fn multiplex_coordinates<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    entries: &[Vec<Coordinate<E>>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    assert!(bits.len() > 0);
+    assert_eq!(entries.len(), 1 << bits.len());
+    let num_coordinates = entries[0].len();
+    assert!(entries.iter().all(|e| e.len() == num_coordinates));
+
+    let one = CS::one();
+    let mut res = Vec::with_capacity(num_coordinates);
+    for c in 0..num_coordinates {
+        let mut layer: Vec<Coordinate<E>> = entries.iter().map(|e| e[c].clone()).collect();
+
+        // Halve the candidates with each bit, from the least
+        // significant one
+        for (j, bit) in bits.iter().enumerate() {
+            let mut next = Vec::with_capacity(layer.len() / 2);
+            for (k, pair) in layer.chunks(2).enumerate() {
+                // The difference of a variable and itself is a zero
+                // A query, which the parameters do not hold a base for
+                if pair[0].is_copy_of(&pair[1]) {
+                    next.push(pair[0].clone());
+                    continue;
+                }
+
+                let selected = AllocatedNum::alloc(
+                    cs.namespace(|| format!("coordinate {} bit {} selection {}", c, j, k)),
+                    || {
+                        if *bit.get_value().get()? {
+                            Ok(*pair[1].get_value().get()?)
+                        } else {
+                            Ok(*pair[0].get_value().get()?)
+                        }
+                    }
+                )?;
+
+                // bit * (b - a) = selected - a
+                cs.enforce(
+                    || format!("coordinate {} bit {} selection {} computation", c, j, k),
+                    |lc| lc + &pair[1].lc(one) - &pair[0].lc(one),
+                    |lc| lc + &bit.lc::<E>(one, E::Fr::one()),
+                    |lc| lc + selected.get_variable() - &pair[0].lc(one)
+                );
+
+                next.push(Coordinate::Allocated(selected));
+            }
+            layer = next;
+        }
+
+        // Every selection is allocated, or a copy of an allocation
+        match layer.pop() {
+            Some(Coordinate::Allocated(num)) => res.push(num),
+            _ => unreachable!()
+        }
+    }
+
+    Ok(res)
+}
+
+/// Selects the entry of a table of the multiples 0 to `2^k - 1`
+/// of a point for every `k`-bit window of `by`, in little-endian
+/// bit order. The neutral element is given by its constant
+/// coordinates, and the other multiples as allocated ones. The
+/// bits are padded to a whole number of windows, and the
+/// selections are returned from the most significant window on,
+/// the order in which a double-and-add loop consumes them.
+///
+/// This costs `2^k - 1` constraints per coordinate and window.
+pub fn lookup_windows<E: Engine, CS>(
+    mut cs: CS,
+    by: &[Boolean],
+    neutral: &[E::Fr],
+    multiples: &[Vec<AllocatedNum<E>>]
+) -> Result<Vec<Vec<AllocatedNum<E>>>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let table_len = multiples.len() + 1;
+    assert!(table_len > 1 && table_len.is_power_of_two());
+    let window_size = table_len.trailing_zeros() as usize;
+
+    let mut table = Vec::with_capacity(table_len);
+    table.push(neutral.iter().map(|c| Coordinate::Constant(*c)).collect::<Vec<_>>());
+    for multiple in multiples {
+        table.push(multiple.iter().map(|c| Coordinate::Allocated(c.clone())).collect());
+    }
+
+    let mut bits = by.to_vec();
+    while bits.len() % window_size != 0 {
+        bits.push(Boolean::constant(false));
+    }
+
+    let mut res = Vec::with_capacity(bits.len() / window_size);
+    for (i, window) in bits.chunks(window_size).enumerate().rev() {
+        res.push(multiplex_coordinates(
+            cs.namespace(|| format!("lookup {}", i)),
+            window,
+            &table
+        )?);
+    }
+
+    Ok(res)
+}
+
 #[cfg(test)]
 mod test {
     use rand::{SeedableRng, Rand, Rng, XorShiftRng};
@@ -282,6 +542,171 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_lookup_k() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0656]);
+
+        for window_size in 1..6 {
+            for num_coordinates in 1..4 {
+                for _ in 0..10 {
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                    let bit_values: Vec<bool> = (0..window_size).map(|_| rng.gen()).collect();
+                    let bits: Vec<Boolean> = bit_values.iter().enumerate().map(|(i, b)| {
+                        Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap())
+                    }).collect();
+
+                    let coords: Vec<Vec<Fr>> = (0..(1 << window_size)).map(|_| {
+                        (0..num_coordinates).map(|_| rng.gen()).collect()
+                    }).collect();
+
+                    let res = lookup_k(cs.namespace(|| "lookup"), &bits, &coords).unwrap();
+
+                    assert!(cs.is_satisfied());
+                    assert_eq!(
+                        cs.num_constraints() - window_size,
+                        (1 << (window_size - 1)) - window_size + num_coordinates
+                    );
+
+                    let index = bit_values.iter().enumerate().fold(0, |acc, (i, b)| if *b { acc | (1 << i) } else { acc });
+                    for c in 0..num_coordinates {
+                        assert_eq!(res[c].get_value().unwrap(), coords[index][c]);
+                    }
+
+                    cs.set("lookup/coordinate 0/num", rng.gen());
+                    assert_eq!(cs.which_is_unsatisfied(), Some("lookup/coordinate 0 lookup"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_k_matches_lookup3_xy() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0656]);
+
+        let points: Vec<(Fr, Fr)> = (0..8).map(|_| (rng.gen(), rng.gen())).collect();
+        let coords: Vec<Vec<Fr>> = points.iter().map(|p| vec![p.0, p.1]).collect();
+
+        for index in 0..8 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let bits: Vec<Boolean> = (0..3).map(|i| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(index & (1 << i) != 0)).unwrap())
+            }).collect();
+
+            let before = cs.num_constraints();
+            let expected = lookup3_xy(cs.namespace(|| "lookup3"), &bits, &points).unwrap();
+            let lookup3_constraints = cs.num_constraints() - before;
+
+            let before = cs.num_constraints();
+            let res = lookup_k(cs.namespace(|| "lookup_k"), &bits, &coords).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints() - before, lookup3_constraints);
+            assert_eq!(res[0].get_value(), expected.0.get_value());
+            assert_eq!(res[1].get_value(), expected.1.get_value());
+        }
+    }
+
+    #[test]
+    fn test_multiplex_k() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0656]);
+
+        for window_size in 1..4 {
+            for _ in 0..10 {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let bit_values: Vec<bool> = (0..window_size).map(|_| rng.gen()).collect();
+                let bits: Vec<Boolean> = bit_values.iter().enumerate().map(|(i, b)| {
+                    Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap())
+                }).collect();
+
+                let entries: Vec<Vec<AllocatedNum<Bls12>>> = (0..(1 << window_size)).map(|i| {
+                    (0..2).map(|c| {
+                        AllocatedNum::alloc(cs.namespace(|| format!("entry {} {}", i, c)), || Ok(rng.gen())).unwrap()
+                    }).collect()
+                }).collect();
+
+                let res = multiplex_k(cs.namespace(|| "multiplex"), &bits, &entries).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints() - window_size, 2 * ((1 << window_size) - 1));
+
+                let index = bit_values.iter().enumerate().fold(0, |acc, (i, b)| if *b { acc | (1 << i) } else { acc });
+                assert_eq!(res[0].get_value(), entries[index][0].get_value());
+                assert_eq!(res[1].get_value(), entries[index][1].get_value());
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiplex_k_repeated_entries() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0656]);
+
+        for index in 0..4 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let bits: Vec<Boolean> = (0..2).map(|i| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(index & (1 << i) != 0)).unwrap())
+            }).collect();
+
+            // The orderings of a quaternary merkle tree node, where
+            // neighbouring entries share all but one coordinate
+            let nums: Vec<AllocatedNum<Bls12>> = (0..4).map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(rng.gen())).unwrap()
+            }).collect();
+            let entries: Vec<Vec<AllocatedNum<Bls12>>> = (0..4).map(|position| {
+                let mut entry = nums[1..].to_vec();
+                entry.insert(position, nums[0].clone());
+                entry
+            }).collect();
+
+            let res = multiplex_k(cs.namespace(|| "multiplex"), &bits, &entries).unwrap();
+
+            assert!(cs.is_satisfied());
+            // Each coordinate has one pair of copies at the first bit
+            assert_eq!(cs.num_constraints() - 2, 4 * 2);
+            for c in 0..4 {
+                assert_eq!(res[c].get_value(), entries[index][c].get_value());
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_windows() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0656]);
+
+        for window_size in 1..4 {
+            for num_bits in 1..8 {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let bit_values: Vec<bool> = (0..num_bits).map(|_| rng.gen()).collect();
+                let bits: Vec<Boolean> = bit_values.iter().enumerate().map(|(i, b)| {
+                    Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap())
+                }).collect();
+
+                let neutral: Fr = rng.gen();
+                let multiples: Vec<Vec<AllocatedNum<Bls12>>> = (1..(1 << window_size)).map(|i| {
+                    vec![AllocatedNum::alloc(cs.namespace(|| format!("entry {}", i)), || Ok(rng.gen())).unwrap()]
+                }).collect();
+
+                let before = cs.num_constraints();
+                let res = lookup_windows(cs.namespace(|| "lookup"), &bits, &[neutral], &multiples).unwrap();
+
+                assert!(cs.is_satisfied());
+                let num_windows = (num_bits + window_size - 1) / window_size;
+                assert_eq!(res.len(), num_windows);
+                assert_eq!(cs.num_constraints() - before, num_windows * ((1 << window_size) - 1));
+
+                let mut padded = bit_values.clone();
+                padded.resize(num_windows * window_size, false);
+                for (selected, window) in res.iter().zip(padded.chunks(window_size).rev()) {
+                    let index = window.iter().enumerate().fold(0, |acc, (i, b)| if *b { acc | (1 << i) } else { acc });
+                    let expected = if index == 0 { Some(neutral) } else { multiples[index - 1][0].get_value() };
+                    assert_eq!(selected[0].get_value(), expected);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_synth() {
         let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
@@ -0,0 +1,491 @@
+use pairing::{Engine};
+use bellman::{ConstraintSystem, SynthesisError};
+
+use super::boolean::Boolean;
+use super::num::AllocatedNum;
+use super::{baby_pedersen_hash, blake2s, lookup, poseidon};
+use ::babyjubjub::JubjubEngine;
+use ::poseidon::PoseidonParams;
+
+/// A hash function for the nodes of a binary Merkle tree.
+pub trait MerkleHasherGadget<E: Engine> {
+    /// The in-circuit representation of a node.
+    type Node: Clone;
+
+    /// Returns (b, a) if the condition is true, and (a, b) otherwise.
+    fn conditionally_reverse<CS>(
+        &self,
+        cs: CS,
+        a: &Self::Node,
+        b: &Self::Node,
+        condition: &Boolean
+    ) -> Result<(Self::Node, Self::Node), SynthesisError>
+        where CS: ConstraintSystem<E>;
+
+    /// Hashes two children at `depth`, which is zero for the parents
+    /// of the leaves.
+    fn hash_node<CS>(
+        &self,
+        cs: CS,
+        depth: usize,
+        left: &Self::Node,
+        right: &Self::Node
+    ) -> Result<Self::Node, SynthesisError>
+        where CS: ConstraintSystem<E>;
+}
+
+/// Computes the root of the tree from a leaf and its authentication
+/// path. `path_bits[i]` is set when `siblings[i]` is the right child,
+/// which is the meaning of the side in the Merkle proofs of the mixer.
+/// The bits are only constrained to be boolean by the caller, e.g. by
+/// allocating them with `AllocatedBit::alloc`; the side they pick is
+/// enforced here.
+pub fn compute_root<E, CS, H>(
+    mut cs: CS,
+    leaf: &H::Node,
+    path_bits: &[Boolean],
+    siblings: &[H::Node],
+    hasher: &H
+) -> Result<H::Node, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasherGadget<E>
+{
+    assert_eq!(path_bits.len(), siblings.len());
+
+    let mut cur = leaf.clone();
+
+    for (i, (bit, sibling)) in path_bits.iter().zip(siblings.iter()).enumerate() {
+        let mut cs = cs.namespace(|| format!("merkle tree hash {}", i));
+
+        // Swap the two if the sibling is on the right
+        let (xl, xr) = hasher.conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            sibling,
+            &cur,
+            bit
+        )?;
+
+        cur = hasher.hash_node(cs.namespace(|| "node hash"), i, &xl, &xr)?;
+    }
+
+    Ok(cur)
+}
+
+/// Hashes nodes like the Pedersen Merkle tree circuit of the mixer:
+/// the x coordinate of the Pedersen hash of the bits of both children,
+/// personalized with the depth.
+pub struct PedersenHasher<'a, E: JubjubEngine + 'a> {
+    params: &'a E::Params
+}
+
+impl<'a, E: JubjubEngine> PedersenHasher<'a, E> {
+    pub fn new(params: &'a E::Params) -> Self {
+        PedersenHasher {
+            params: params
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> MerkleHasherGadget<E> for PedersenHasher<'a, E> {
+    type Node = AllocatedNum<E>;
+
+    fn conditionally_reverse<CS>(
+        &self,
+        cs: CS,
+        a: &AllocatedNum<E>,
+        b: &AllocatedNum<E>,
+        condition: &Boolean
+    ) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        AllocatedNum::conditionally_reverse(cs, a, b, condition)
+    }
+
+    fn hash_node<CS>(
+        &self,
+        mut cs: CS,
+        depth: usize,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let mut preimage = left.into_bits_le_strict(cs.namespace(|| "left into bits"))?;
+        preimage.extend(right.into_bits_le_strict(cs.namespace(|| "right into bits"))?);
+
+        Ok(baby_pedersen_hash::pedersen_hash(
+            cs.namespace(|| "pedersen hash"),
+            baby_pedersen_hash::Personalization::MerkleTree(depth),
+            &preimage,
+            self.params
+        )?.get_x().clone())
+    }
+}
+
+/// Hashes nodes of 256 bits with BLAKE2s of their concatenation. The
+/// bits are little-endian within each byte, like in the `blake2s`
+/// gadget.
+pub struct Blake2sHasher<'a> {
+    personalization: &'a [u8]
+}
+
+impl<'a> Blake2sHasher<'a> {
+    pub fn new(personalization: &'a [u8]) -> Self {
+        assert_eq!(personalization.len(), 8);
+
+        Blake2sHasher {
+            personalization: personalization
+        }
+    }
+}
+
+impl<'a, E: Engine> MerkleHasherGadget<E> for Blake2sHasher<'a> {
+    type Node = Vec<Boolean>;
+
+    fn conditionally_reverse<CS>(
+        &self,
+        mut cs: CS,
+        a: &Vec<Boolean>,
+        b: &Vec<Boolean>,
+        condition: &Boolean
+    ) -> Result<(Vec<Boolean>, Vec<Boolean>), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert_eq!(a.len(), b.len());
+
+        let mut c = Vec::with_capacity(a.len());
+        let mut d = Vec::with_capacity(a.len());
+
+        for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+
+            // sha256_ch(x, y, z) is y if x is set, and z otherwise
+            c.push(Boolean::sha256_ch(cs.namespace(|| "first"), condition, b, a)?);
+            d.push(Boolean::sha256_ch(cs.namespace(|| "second"), condition, a, b)?);
+        }
+
+        Ok((c, d))
+    }
+
+    fn hash_node<CS>(
+        &self,
+        cs: CS,
+        _depth: usize,
+        left: &Vec<Boolean>,
+        right: &Vec<Boolean>
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert_eq!(left.len(), 256);
+        assert_eq!(right.len(), 256);
+
+        let mut preimage = left.clone();
+        preimage.extend(right.iter().cloned());
+
+        blake2s::blake2s(cs, &preimage, self.personalization)
+    }
+}
+
+/// Hashes nodes with Poseidon, which needs parameters for 2 inputs.
+pub struct PoseidonHasher<'a, E: Engine + 'a> {
+    params: &'a PoseidonParams<E>
+}
+
+impl<'a, E: Engine> PoseidonHasher<'a, E> {
+    pub fn new(params: &'a PoseidonParams<E>) -> Self {
+        assert_eq!(params.num_inputs(), 2);
+
+        PoseidonHasher {
+            params: params
+        }
+    }
+}
+
+impl<'a, E: Engine> MerkleHasherGadget<E> for PoseidonHasher<'a, E> {
+    type Node = AllocatedNum<E>;
+
+    fn conditionally_reverse<CS>(
+        &self,
+        cs: CS,
+        a: &AllocatedNum<E>,
+        b: &AllocatedNum<E>,
+        condition: &Boolean
+    ) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        AllocatedNum::conditionally_reverse(cs, a, b, condition)
+    }
+
+    fn hash_node<CS>(
+        &self,
+        cs: CS,
+        _depth: usize,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        poseidon::poseidon_hash(cs, &[left.clone(), right.clone()], self.params)
+    }
+}
+
+/// Computes the root of a quaternary tree, whose nodes are the Poseidon
+/// hash of their four children, from a leaf and its authentication path.
+/// `path_bits[i]` is the position of the current node among its siblings
+/// in little-endian order and `siblings[i]` holds the three other children
+/// in order. The bits are only constrained to be boolean by the caller;
+/// a 4-way multiplexer puts the current node at that position.
+pub fn compute_root_quaternary<E, CS>(
+    mut cs: CS,
+    leaf: &AllocatedNum<E>,
+    path_bits: &[(Boolean, Boolean)],
+    siblings: &[Vec<AllocatedNum<E>>],
+    params: &PoseidonParams<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(path_bits.len(), siblings.len());
+    assert_eq!(params.num_inputs(), 4);
+
+    let mut cur = leaf.clone();
+
+    for (i, (bits, siblings)) in path_bits.iter().zip(siblings.iter()).enumerate() {
+        assert_eq!(siblings.len(), 3);
+        let mut cs = cs.namespace(|| format!("merkle tree hash {}", i));
+
+        // The children for every position of the current node
+        let orders: Vec<Vec<AllocatedNum<E>>> = (0..4).map(|position| {
+            let mut children = siblings.clone();
+            children.insert(position, cur.clone());
+            children
+        }).collect();
+
+        let children = lookup::multiplex_k(
+            cs.namespace(|| "ordering of children"),
+            &[bits.0.clone(), bits.1.clone()],
+            &orders
+        )?;
+
+        cur = poseidon::poseidon_hash(cs.namespace(|| "node hash"), &children, params)?;
+    }
+
+    Ok(cur)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use pairing::bn256::{Bn256, Fr};
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+    use blake2_rfc::blake2s::Blake2s;
+    use ::babyjubjub::JubjubBn256;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::num::AllocatedNum;
+    use ::circuit::test::*;
+    use ::poseidon::PoseidonParams;
+    use super::*;
+
+    const DEPTH: usize = 4;
+
+    fn alloc_path<CS: ConstraintSystem<Bn256>>(cs: &mut CS, path: &[bool]) -> Vec<Boolean> {
+        path.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("path bit {}", i)), Some(b)).unwrap())
+        }).collect()
+    }
+
+    fn alloc_nums<CS: ConstraintSystem<Bn256>>(cs: &mut CS, name: &str, values: &[Fr]) -> Vec<AllocatedNum<Bn256>> {
+        values.iter().enumerate().map(|(i, v)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("{} {}", name, i)), || Ok(*v)).unwrap()
+        }).collect()
+    }
+
+    fn native_root<N: Clone, F: Fn(usize, &N, &N) -> N>(leaf: &N, path: &[bool], siblings: &[N], hash: F) -> N {
+        let mut cur = leaf.clone();
+        for (i, (&bit, sibling)) in path.iter().zip(siblings.iter()).enumerate() {
+            cur = if bit { hash(i, &cur, sibling) } else { hash(i, sibling, &cur) };
+        }
+
+        cur
+    }
+
+    #[test]
+    fn test_compute_root_pedersen() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBn256::new();
+
+        let leaf: Fr = rng.gen();
+        let path: Vec<bool> = (0..DEPTH).map(|_| rng.gen()).collect();
+        let siblings: Vec<Fr> = (0..DEPTH).map(|_| rng.gen()).collect();
+
+        let expected = native_root(&leaf, &path, &siblings, |depth, l, r| {
+            let bits_le = |x: &Fr| {
+                let repr = x.into_repr();
+                (0..Fr::NUM_BITS as usize).map(move |i| (repr.as_ref()[i / 64] >> (i % 64)) & 1 == 1).collect::<Vec<_>>()
+            };
+
+            ::baby_pedersen_hash::pedersen_hash::<Bn256, _>(
+                ::baby_pedersen_hash::Personalization::MerkleTree(depth),
+                bits_le(l).into_iter().chain(bits_le(r).into_iter()),
+                params
+            ).into_xy().0
+        });
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+        let path_bits = alloc_path(&mut cs, &path);
+        let siblings = alloc_nums(&mut cs, "sibling", &siblings);
+
+        let root = compute_root(
+            cs.namespace(|| "root"),
+            &leaf,
+            &path_bits,
+            &siblings,
+            &PedersenHasher::new(params)
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(root.get_value().unwrap() == expected);
+    }
+
+    #[test]
+    fn test_compute_root_blake2s() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let leaf: [u8; 32] = rng.gen();
+        let path: Vec<bool> = (0..DEPTH).map(|_| rng.gen()).collect();
+        let siblings: Vec<[u8; 32]> = (0..DEPTH).map(|_| rng.gen()).collect();
+
+        let expected = native_root(&leaf, &path, &siblings, |_, l, r| {
+            let mut h = Blake2s::with_params(32, &[], &[], b"12345678");
+            h.update(l);
+            h.update(r);
+            let mut result = [0u8; 32];
+            result.copy_from_slice(h.finalize().as_ref());
+            result
+        });
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let alloc_bytes = |cs: &mut TestConstraintSystem<Bn256>, name: String, bytes: &[u8; 32]| -> Vec<Boolean> {
+            bytes.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).enumerate().map(|(i, b)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), Some(b)).unwrap())
+            }).collect()
+        };
+
+        let leaf_bits = alloc_bytes(&mut cs, "leaf".to_string(), &leaf);
+        let path_bits = alloc_path(&mut cs, &path);
+        let siblings: Vec<_> = siblings.iter().enumerate().map(|(i, s)| alloc_bytes(&mut cs, format!("sibling {}", i), s)).collect();
+
+        let root = compute_root(
+            cs.namespace(|| "root"),
+            &leaf_bits,
+            &path_bits,
+            &siblings,
+            &Blake2sHasher::new(b"12345678")
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let expected: Vec<bool> = expected.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).collect();
+        let root: Vec<bool> = root.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(root, expected);
+
+        // The side of the sibling is enforced
+        cs.set("path bit 1/boolean", if path[1] { Fr::zero() } else { Fr::one() });
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_compute_root_poseidon() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bn256>::new(2);
+
+        for _ in 0..4 {
+            let leaf: Fr = rng.gen();
+            let path: Vec<bool> = (0..DEPTH).map(|_| rng.gen()).collect();
+            let siblings: Vec<Fr> = (0..DEPTH).map(|_| rng.gen()).collect();
+
+            let expected = native_root(&leaf, &path, &siblings, |_, l, r| {
+                ::poseidon::poseidon_hash(&params, &[*l, *r])
+            });
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+            let path_bits = alloc_path(&mut cs, &path);
+            let siblings = alloc_nums(&mut cs, "sibling", &siblings);
+
+            let root = compute_root(
+                cs.namespace(|| "root"),
+                &leaf,
+                &path_bits,
+                &siblings,
+                &PoseidonHasher::new(&params)
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(root.get_value().unwrap() == expected);
+
+            // Two constraints for the reversal and the hash on each level
+            assert_eq!(cs.num_constraints() - DEPTH, DEPTH * (2 + 244));
+
+            // The side of the sibling is enforced on every level
+            for i in 0..DEPTH {
+                let path_bit = format!("path bit {}/boolean", i);
+                let value = cs.get(&path_bit);
+                cs.set(&path_bit, if value.is_zero() { Fr::one() } else { Fr::zero() });
+                assert!(!cs.is_satisfied());
+                cs.set(&path_bit, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_root_quaternary() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bn256>::new(4);
+
+        for _ in 0..4 {
+            let leaf: Fr = rng.gen();
+            let positions: Vec<usize> = (0..DEPTH).map(|_| rng.gen::<usize>() % 4).collect();
+            let siblings: Vec<Vec<Fr>> = (0..DEPTH).map(|_| (0..3).map(|_| rng.gen()).collect()).collect();
+
+            let mut expected = leaf;
+            for (&position, siblings) in positions.iter().zip(siblings.iter()) {
+                let mut children = siblings.clone();
+                children.insert(position, expected);
+                expected = ::poseidon::poseidon_hash(&params, &children);
+            }
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+            let path_bits: Vec<(Boolean, Boolean)> = positions.iter().enumerate().map(|(i, &position)| {
+                let bits = alloc_path(&mut cs.namespace(|| format!("position {}", i)), &[position & 1 == 1, position & 2 == 2]);
+                (bits[0].clone(), bits[1].clone())
+            }).collect();
+            let siblings: Vec<Vec<AllocatedNum<Bn256>>> = siblings.iter().enumerate().map(|(i, siblings)| {
+                alloc_nums(&mut cs, &format!("sibling {}", i), siblings)
+            }).collect();
+
+            let root = compute_root_quaternary(
+                cs.namespace(|| "root"),
+                &leaf,
+                &path_bits,
+                &siblings,
+                &params
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(root.get_value().unwrap() == expected);
+
+            // The position of the node is enforced on every level
+            for i in 0..DEPTH {
+                for j in 0..2 {
+                    let path_bit = format!("position {}/path bit {}/boolean", i, j);
+                    let value = cs.get(&path_bit);
+                    cs.set(&path_bit, if value.is_zero() { Fr::one() } else { Fr::zero() });
+                    assert!(!cs.is_satisfied());
+                    cs.set(&path_bit, value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use pairing::{Engine};
+use bellman::{ConstraintSystem, SynthesisError, LinearCombination};
+use ff::{Field};
+
+use super::Assignment;
+use super::num::AllocatedNum;
+use ::mimc::MiMCSpongeParams;
+
+/// One half of the Feistel state, which is a linear combination
+/// of allocated variables until it goes through a round.
+#[derive(Clone)]
+struct Half<E: Engine> {
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>
+}
+
+impl<E: Engine> Half<E> {
+    fn zero() -> Self {
+        Half {
+            value: Some(E::Fr::zero()),
+            lc: LinearCombination::zero()
+        }
+    }
+
+    fn add_num(self, num: &AllocatedNum<E>) -> Self {
+        Half {
+            value: match (self.value, num.get_value()) {
+                (Some(mut a), Some(b)) => {
+                    a.add_assign(&b);
+                    Some(a)
+                },
+                _ => None
+            },
+            lc: self.lc + num.get_variable()
+        }
+    }
+}
+
+impl<E: Engine> From<AllocatedNum<E>> for Half<E> {
+    fn from(num: AllocatedNum<E>) -> Half<E> {
+        Half {
+            value: num.get_value(),
+            lc: LinearCombination::zero() + num.get_variable()
+        }
+    }
+}
+
+/// Applies the MiMC-Feistel permutation keyed with the constant `key`,
+/// using three constraints per round. See `mimc::mimc_feistel`.
+fn mimc_feistel<E, CS>(
+    mut cs: CS,
+    mut xl: Half<E>,
+    mut xr: Half<E>,
+    key: &E::Fr,
+    params: &MiMCSpongeParams<E>
+) -> Result<(Half<E>, Half<E>), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let rounds = params.rounds();
+
+    for (i, c) in params.round_constants().iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+
+        // t = xl + key + c
+        let mut constant = *key;
+        constant.add_assign(c);
+        let t_value = xl.value.map(|mut v| { v.add_assign(&constant); v });
+        let t_lc = xl.lc.clone() + (constant, CS::one());
+
+        let t2 = AllocatedNum::alloc(cs.namespace(|| "t^2"), || {
+            let mut tmp = *t_value.get()?;
+            tmp.square();
+            Ok(tmp)
+        })?;
+        cs.enforce(
+            || "t^2 constraint",
+            |lc| lc + &t_lc,
+            |lc| lc + &t_lc,
+            |lc| lc + t2.get_variable()
+        );
+
+        let t4 = t2.square(cs.namespace(|| "t^4"))?;
+
+        // out = xr + t^5
+        let out = AllocatedNum::alloc(cs.namespace(|| "xr + t^5"), || {
+            let mut tmp = *t4.get_value().get()?;
+            tmp.mul_assign(t_value.get()?);
+            tmp.add_assign(xr.value.get()?);
+            Ok(tmp)
+        })?;
+        cs.enforce(
+            || "t^5 constraint",
+            |lc| lc + t4.get_variable(),
+            |lc| lc + &t_lc,
+            |lc| lc + out.get_variable() - &xr.lc
+        );
+
+        if i < rounds - 1 {
+            xr = xl;
+            xl = out.into();
+        } else {
+            xr = out.into();
+        }
+    }
+
+    Ok((xl, xr))
+}
+
+/// Hashes `inputs` into `num_outputs` elements with the MiMC-sponge,
+/// keyed with the constant `key`. The result is the same as
+/// `mimc::mimc_sponge`.
+pub fn mimc_sponge<E, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<E>],
+    key: &E::Fr,
+    num_outputs: usize,
+    params: &MiMCSpongeParams<E>
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut xl = Half::zero();
+    let mut xr = Half::zero();
+
+    for (i, input) in inputs.iter().enumerate() {
+        let (l, r) = mimc_feistel(
+            cs.namespace(|| format!("absorb input {}", i)),
+            xl.add_num(input),
+            xr,
+            key,
+            params
+        )?;
+        xl = l;
+        xr = r;
+    }
+
+    let mut outputs = vec![];
+    for i in 0..num_outputs {
+        if i > 0 {
+            let (l, r) = mimc_feistel(
+                cs.namespace(|| format!("squeeze output {}", i)),
+                xl,
+                xr,
+                key,
+                params
+            )?;
+            xl = l;
+            xr = r;
+        }
+
+        let output = AllocatedNum::alloc(cs.namespace(|| format!("output {}", i)), || {
+            Ok(*xl.value.get()?)
+        })?;
+        cs.enforce(
+            || format!("output {} constraint", i),
+            |lc| lc + &xl.lc,
+            |lc| lc + CS::one(),
+            |lc| lc + output.get_variable()
+        );
+
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use super::*;
+    use ::circuit::test::*;
+    use ff::PrimeField;
+    use pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_mimc_sponge() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = MiMCSpongeParams::<Bn256>::new();
+
+        for &(num_inputs, num_outputs) in [(1, 1), (2, 1), (3, 2)].iter() {
+            let values: Vec<Fr> = (0..num_inputs).map(|_| rng.gen()).collect();
+            let key: Fr = rng.gen();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let inputs: Vec<_> = values.iter().enumerate().map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*v)).unwrap()
+            }).collect();
+
+            let outputs = mimc_sponge(cs.namespace(|| "mimc"), &inputs, &key, num_outputs, &params).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected = ::mimc::mimc_sponge(&params, &values, &key, num_outputs);
+            let outputs: Vec<Fr> = outputs.iter().map(|o| o.get_value().unwrap()).collect();
+            assert!(outputs == expected);
+
+            // Three constraints per round, plus one per output
+            let permutations = num_inputs + num_outputs - 1;
+            assert_eq!(cs.num_constraints(), 3 * 220 * permutations + num_outputs);
+        }
+    }
+
+    #[test]
+    fn test_mimc_sponge_vector() {
+        let params = MiMCSpongeParams::<Bn256>::new();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("1").unwrap())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("2").unwrap())).unwrap();
+        let outputs = mimc_sponge(cs.namespace(|| "mimc"), &[a, b], &Fr::from_str("3").unwrap(), 2, &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            outputs[0].get_value().unwrap(),
+            Fr::from_str("19409956100751005455347415409363035086671136487007611500396612831725872564866").unwrap()
+        );
+        assert_eq!(
+            outputs[1].get_value().unwrap(),
+            Fr::from_str("10913281018962515006253917430560591967394682882756766498820716411830155541327").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mimc_sponge_rejects_wrong_output() {
+        let params = MiMCSpongeParams::<Bn256>::new();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::one())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::zero())).unwrap();
+        mimc_sponge(cs.namespace(|| "mimc"), &[a, b], &Fr::zero(), 1, &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1321);
+
+        cs.set("mimc/output 0/num", Fr::one());
+        assert_eq!(cs.which_is_unsatisfied(), Some("mimc/output 0 constraint"));
+
+        cs.set("mimc/absorb input 1/round 7/xr + t^5/num", Fr::one());
+        assert_eq!(cs.which_is_unsatisfied(), Some("mimc/absorb input 1/round 7/t^5 constraint"));
+    }
+}
@@ -1,21 +1,32 @@
 // #[cfg(test)]
+#[macro_use]
 pub mod test;
 
 pub mod boolean;
 pub mod multieq;
+#[macro_use]
+mod uint;
+pub mod uint8;
 pub mod uint32;
+pub mod uint64;
 pub mod blake2s;
+pub mod blake2b;
 pub mod num;
 pub mod lookup;
+pub mod alt_baby_ecc;
 pub mod baby_ecc;
 pub mod ecc;
 pub mod pedersen_hash;
 pub mod baby_pedersen_hash;
 pub mod multipack;
 pub mod sha256;
+pub mod keccak256;
 pub mod baby_eddsa;
+pub mod eddsa_poseidon;
 pub mod float_point;
-// pub mod shark_mimc;
+pub mod poseidon;
+pub mod mimc;
+pub mod merkle;
 
 pub mod sapling;
 pub mod sprout;
@@ -347,6 +347,100 @@ impl<E: Engine> AllocatedNum<E> {
         Ok(())
     }
 
+    /// Returns whether the number is zero. Costs 3 constraints.
+    pub fn is_zero<CS>(
+        &self,
+        cs: CS
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        is_zero_lc(cs, LinearCombination::zero() + self.variable, self.value)
+    }
+
+    /// Returns whether `a == b`. Costs 3 constraints.
+    pub fn equals<CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let value = match (a.value, b.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                Some(a)
+            },
+            _ => None
+        };
+
+        is_zero_lc(cs, LinearCombination::zero() + a.variable - b.variable, value)
+    }
+
+    /// Returns the multiplicative inverse of the number, and
+    /// makes the circuit unsatisfiable if it is zero.
+    pub fn inverse<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let inv = Self::alloc(cs.namespace(|| "inverse"), || {
+            let tmp = *self.value.get()?;
+
+            if tmp.is_zero() {
+                Err(SynthesisError::DivisionByZero)
+            } else {
+                Ok(tmp.inverse().unwrap())
+            }
+        })?;
+
+        // Constrain a * inv = 1, which zero can't satisfy
+        cs.enforce(
+            || "inverse constraint",
+            |lc| lc + self.variable,
+            |lc| lc + inv.variable,
+            |lc| lc + CS::one()
+        );
+
+        Ok(inv)
+    }
+
+    /// Returns `self / other`, and makes the circuit unsatisfiable
+    /// if `other` is zero. Costs 2 constraints.
+    pub fn div<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        // Without this, 0 / 0 could be anything
+        other.assert_nonzero(cs.namespace(|| "nonzero divisor"))?;
+
+        let quotient = Self::alloc(cs.namespace(|| "quotient"), || {
+            let tmp = *other.value.get()?;
+
+            if tmp.is_zero() {
+                Err(SynthesisError::DivisionByZero)
+            } else {
+                let mut quotient = tmp.inverse().unwrap();
+                quotient.mul_assign(self.value.get()?);
+
+                Ok(quotient)
+            }
+        })?;
+
+        // Constrain other * quotient = self
+        cs.enforce(
+            || "division constraint",
+            |lc| lc + other.variable,
+            |lc| lc + quotient.variable,
+            |lc| lc + self.variable
+        );
+
+        Ok(quotient)
+    }
+
     /// Takes two allocated numbers (a, b) and returns
     /// (b, a) if the condition is true, and (a, b)
     /// otherwise.
@@ -486,6 +580,125 @@ impl<E: Engine> AllocatedNum<E> {
         Ok(())
     }
 
+    /// Returns whether `a < b`, where both numbers are assumed to fit
+    /// in `num_bits` bits; the circuit is unsatisfiable otherwise, unless
+    /// the difference happens to fit. Costs `num_bits + 2` constraints.
+    pub fn less_than<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        num_bits: usize
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        // a - b + 2^num_bits has its top bit set iff a >= b
+        let top_bit = Self::shifted_difference_top_bit(
+            cs.namespace(|| "a - b"),
+            a,
+            b,
+            num_bits
+        )?;
+
+        Ok(top_bit.not())
+    }
+
+    /// Returns whether `a <= b`, where both numbers are assumed to fit
+    /// in `num_bits` bits, like in `less_than`. Costs `num_bits + 2`
+    /// constraints.
+    pub fn less_than_or_equal<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        num_bits: usize
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        // b - a + 2^num_bits has its top bit set iff a <= b
+        Self::shifted_difference_top_bit(
+            cs.namespace(|| "b - a"),
+            b,
+            a,
+            num_bits
+        )
+    }
+
+    /// Enforces `min <= self <= max`, where `max - min` fits in
+    /// `num_bits` bits. Nothing is assumed about `self`. Costs
+    /// `2 * num_bits + 2` constraints.
+    pub fn assert_in_range<CS>(
+        &self,
+        mut cs: CS,
+        min: &E::Fr,
+        max: &E::Fr,
+        num_bits: usize
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+        assert!(min.into_repr() <= max.into_repr());
+        {
+            let mut width = *max;
+            width.sub_assign(min);
+            assert!(width.into_repr().num_bits() as usize <= num_bits);
+        }
+
+        // Both self - min and max - self fit in num_bits bits, which
+        // can't happen with a wrap around the modulus
+        let above_min = self.value.map(|mut v| { v.sub_assign(min); v });
+        unpack_lc_le(
+            cs.namespace(|| "self - min"),
+            LinearCombination::zero() + self.variable - (*min, CS::one()),
+            above_min,
+            num_bits
+        )?;
+
+        let below_max = self.value.map(|v| { let mut tmp = *max; tmp.sub_assign(&v); tmp });
+        unpack_lc_le(
+            cs.namespace(|| "max - self"),
+            LinearCombination::zero() + (*max, CS::one()) - self.variable,
+            below_max,
+            num_bits
+        )?;
+
+        Ok(())
+    }
+
+    /// Unpacks `a - b + 2^num_bits` into `num_bits + 1` bits and returns
+    /// the top one.
+    fn shifted_difference_top_bit<CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        num_bits: usize
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        let mut shift = E::Fr::one();
+        for _ in 0..num_bits {
+            shift.double();
+        }
+
+        let value = match (a.value, b.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                a.add_assign(&shift);
+                Some(a)
+            },
+            _ => None
+        };
+
+        let mut bits = unpack_lc_le(
+            cs,
+            LinearCombination::zero() + a.variable - b.variable + (shift, CS::one()),
+            value,
+            num_bits + 1
+        )?;
+
+        Ok(Boolean::from(bits.pop().unwrap()))
+    }
+
     pub fn get_value(&self) -> Option<E::Fr> {
         self.value
     }
@@ -495,6 +708,84 @@ impl<E: Engine> AllocatedNum<E> {
     }
 }
 
+/// Returns whether `lc`, whose value is `value`, is zero.
+fn is_zero_lc<E, CS>(
+    mut cs: CS,
+    lc: LinearCombination<E>,
+    value: Option<E::Fr>
+) -> Result<Boolean, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let result = AllocatedBit::alloc(
+        cs.namespace(|| "is zero"),
+        value.map(|v| v.is_zero())
+    )?;
+
+    // The inverse when lc is not zero, and anything otherwise
+    let inv = cs.alloc(|| "ephemeral inverse", || {
+        let tmp = *value.get()?;
+
+        Ok(tmp.inverse().unwrap_or(E::Fr::zero()))
+    })?;
+
+    // lc * inv = 1 - result, so that result is unset when lc
+    // is not zero
+    cs.enforce(
+        || "inverse constraint",
+        |_| lc.clone(),
+        |lc| lc + inv,
+        |lc| lc + CS::one() - result.get_variable()
+    );
+
+    // lc * result = 0, so that result is unset when lc is zero
+    // only if the previous constraint can't hold
+    cs.enforce(
+        || "zero constraint",
+        |_| lc,
+        |lc| lc + result.get_variable(),
+        |lc| lc
+    );
+
+    Ok(Boolean::from(result))
+}
+
+/// Allocates the `num_bits` lowest bits of `value` and enforces that
+/// they pack into `lc`, so that `lc` is known to fit in `num_bits` bits.
+fn unpack_lc_le<E, CS>(
+    mut cs: CS,
+    lc: LinearCombination<E>,
+    value: Option<E::Fr>,
+    num_bits: usize
+) -> Result<Vec<AllocatedBit>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let repr = value.map(|v| v.into_repr());
+
+    let mut bits = Vec::with_capacity(num_bits);
+    for i in 0..num_bits {
+        bits.push(AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            repr.as_ref().map(|r| (r.as_ref()[i / 64] >> (i % 64)) & 1 == 1)
+        )?);
+    }
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        packed = packed + (coeff, bit.get_variable());
+        coeff.double();
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |_| packed,
+        |lc| lc + CS::one(),
+        |_| lc
+    );
+
+    Ok(bits)
+}
+
 pub struct Num<E: Engine> {
     value: Option<E::Fr>,
     lc: LinearCombination<E>
@@ -673,6 +964,189 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_less_than() {
+        for num_bits in 1..5 {
+            for a in 0..(1u64 << num_bits) {
+                for b in 0..(1u64 << num_bits) {
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                    let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str(&a.to_string()).unwrap())).unwrap();
+                    let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str(&b.to_string()).unwrap())).unwrap();
+
+                    let lt = AllocatedNum::less_than(cs.namespace(|| "lt"), &a_num, &b_num, num_bits).unwrap();
+                    let le = AllocatedNum::less_than_or_equal(cs.namespace(|| "le"), &a_num, &b_num, num_bits).unwrap();
+
+                    assert!(cs.is_satisfied());
+                    assert_eq!(cs.num_constraints(), 2 * (num_bits + 2));
+                    assert_eq!(lt.get_value().unwrap(), a < b);
+                    assert_eq!(le.get_value().unwrap(), a <= b);
+
+                    // The result can't be flipped
+                    let path = format!("lt/a - b/bit {}/boolean", num_bits);
+                    let flipped = if cs.get(&path).is_zero() { Fr::one() } else { Fr::zero() };
+                    cs.set(&path, flipped);
+                    assert!(!cs.is_satisfied());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_less_than_out_of_range() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("20").unwrap())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("1").unwrap())).unwrap();
+
+        AllocatedNum::less_than(cs.namespace(|| "lt"), &a, &b, 4).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_assert_in_range() {
+        for num_bits in 1..4 {
+            for min in 0..(1u64 << num_bits) {
+                for max in min..(min + (1u64 << num_bits)) {
+                    for x in 0..(max + 3) {
+                        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                        let x_num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::from_str(&x.to_string()).unwrap())).unwrap();
+                        x_num.assert_in_range(
+                            cs.namespace(|| "range"),
+                            &Fr::from_str(&min.to_string()).unwrap(),
+                            &Fr::from_str(&max.to_string()).unwrap(),
+                            num_bits
+                        ).unwrap();
+
+                        assert_eq!(cs.num_constraints(), 2 * num_bits + 2);
+                        assert_eq!(cs.is_satisfied(), min <= x && x <= max);
+                    }
+                }
+            }
+        }
+
+        // Negative numbers don't wrap around
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut minus_one = Fr::zero();
+        minus_one.sub_assign(&Fr::one());
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(minus_one)).unwrap();
+        x.assert_in_range(cs.namespace(|| "range"), &Fr::zero(), &Fr::from_str("7").unwrap(), 3).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_is_zero() {
+        for &(value, expected) in [("0", true), ("1", false), ("12345", false)].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(Fr::from_str(value).unwrap())).unwrap();
+            let is_zero = n.is_zero(cs.namespace(|| "is zero")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3);
+            assert_eq!(is_zero.get_value().unwrap(), expected);
+
+            // The result can't be flipped, whatever the inverse
+            cs.set("is zero/is zero/boolean", if expected { Fr::zero() } else { Fr::one() });
+            assert!(!cs.is_satisfied());
+            for inv in ["0", "1", "2"].iter() {
+                cs.set("is zero/ephemeral inverse", Fr::from_str(inv).unwrap());
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_equals() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let a: Fr = rng.gen();
+            let b: Fr = rng.gen();
+
+            for &(a, b) in [(a, b), (a, a)].iter() {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+                let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+                let equal = AllocatedNum::equals(cs.namespace(|| "equals"), &a_num, &b_num).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(equal.get_value().unwrap(), a == b);
+
+                cs.set("equals/is zero/boolean", if a == b { Fr::zero() } else { Fr::one() });
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_inverse() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let a: Fr = rng.gen();
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(a)).unwrap();
+        let inv = n.inverse(cs.namespace(|| "inverse")).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(inv.get_value().unwrap() == a.inverse().unwrap());
+
+        cs.set("inverse/inverse/num", a);
+        assert!(!cs.is_satisfied());
+
+        // Zero has no inverse
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(Fr::zero())).unwrap();
+        assert!(n.inverse(cs.namespace(|| "inverse")).is_err());
+    }
+
+    #[test]
+    fn test_num_div() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &a in [rng.gen(), Fr::zero()].iter() {
+            let b: Fr = rng.gen();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+            let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+            let q = a_num.div(cs.namespace(|| "div"), &b_num).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 2);
+
+            let mut expected = b.inverse().unwrap();
+            expected.mul_assign(&a);
+            assert!(q.get_value().unwrap() == expected);
+
+            cs.set("div/quotient/num", Fr::one());
+            assert!(!cs.is_satisfied());
+        }
+
+        // Dividing by zero is an error, and 0 / 0 can't be proven to be
+        // anything, even with a made up inverse
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let zero = AllocatedNum::alloc(cs.namespace(|| "zero"), || Ok(Fr::zero())).unwrap();
+        assert!(zero.div(cs.namespace(|| "div"), &zero).is_err());
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::zero())).unwrap();
+        let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::one())).unwrap();
+        a_num.div(cs.namespace(|| "div"), &b_num).unwrap();
+        assert!(cs.is_satisfied());
+
+        cs.set("b/num", Fr::zero());
+        cs.set("div/quotient/num", Fr::from_str("5").unwrap());
+        assert!(!cs.is_satisfied());
+        for inv in ["0", "1", "2"].iter() {
+            cs.set("div/nonzero divisor/ephemeral inverse", Fr::from_str(inv).unwrap());
+            assert!(!cs.is_satisfied());
+        }
+    }
+
     #[test]
     fn test_num_nonzero() {
         {
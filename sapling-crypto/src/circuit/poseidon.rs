@@ -0,0 +1,274 @@
+use pairing::{Engine};
+use bellman::{ConstraintSystem, SynthesisError, LinearCombination, Variable};
+use ff::{Field};
+
+use super::Assignment;
+use super::num::AllocatedNum;
+use ::poseidon::PoseidonParams;
+
+/// An element of the Poseidon state. Adding round constants and mixing
+/// are linear, so the state is kept as linear combinations of the
+/// allocated variables with their terms merged; otherwise the mixing
+/// layers of the partial rounds would grow them by a factor of the
+/// width on every round.
+#[derive(Clone)]
+struct StateElement<E: Engine> {
+    value: Option<E::Fr>,
+    terms: Vec<(Variable, E::Fr)>
+}
+
+impl<E: Engine> StateElement<E> {
+    fn zero() -> Self {
+        StateElement {
+            value: Some(E::Fr::zero()),
+            terms: vec![]
+        }
+    }
+
+    fn from_num(num: &AllocatedNum<E>) -> Self {
+        StateElement {
+            value: num.get_value(),
+            terms: vec![(num.get_variable(), E::Fr::one())]
+        }
+    }
+
+    fn add_term(&mut self, var: Variable, coeff: E::Fr) {
+        for term in self.terms.iter_mut() {
+            if term.0.get_unchecked() == var.get_unchecked() {
+                term.1.add_assign(&coeff);
+                return;
+            }
+        }
+
+        self.terms.push((var, coeff));
+    }
+
+    fn add_constant(&mut self, one: Variable, constant: &E::Fr) {
+        self.value = self.value.map(|mut v| { v.add_assign(constant); v });
+        self.add_term(one, *constant);
+    }
+
+    /// Computes the dot product of `row` and `state`.
+    fn mix(row: &[E::Fr], state: &[StateElement<E>]) -> Self {
+        let mut result = StateElement::<E>::zero();
+
+        for (coeff, elt) in row.iter().zip(state.iter()) {
+            result.value = match (result.value, elt.value) {
+                (Some(mut acc), Some(mut v)) => {
+                    v.mul_assign(coeff);
+                    acc.add_assign(&v);
+                    Some(acc)
+                },
+                _ => None
+            };
+
+            for &(var, ref c) in elt.terms.iter() {
+                let mut c = *c;
+                c.mul_assign(coeff);
+                result.add_term(var, c);
+            }
+        }
+
+        result
+    }
+
+    fn lc(&self) -> LinearCombination<E> {
+        self.terms.iter().fold(LinearCombination::zero(), |lc, &(var, coeff)| lc + (coeff, var))
+    }
+
+    /// Raises the element to the fifth power, using three constraints.
+    fn sbox<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let square = AllocatedNum::alloc(cs.namespace(|| "x^2"), || {
+            let mut tmp = *self.value.get()?;
+            tmp.square();
+            Ok(tmp)
+        })?;
+        cs.enforce(
+            || "x^2 constraint",
+            |_| self.lc(),
+            |_| self.lc(),
+            |lc| lc + square.get_variable()
+        );
+
+        let quad = square.square(cs.namespace(|| "x^4"))?;
+
+        let fifth = AllocatedNum::alloc(cs.namespace(|| "x^5"), || {
+            let mut tmp = *quad.get_value().get()?;
+            tmp.mul_assign(self.value.get()?);
+            Ok(tmp)
+        })?;
+        cs.enforce(
+            || "x^5 constraint",
+            |lc| lc + quad.get_variable(),
+            |_| self.lc(),
+            |lc| lc + fifth.get_variable()
+        );
+
+        Ok(StateElement::from_num(&fifth))
+    }
+}
+
+/// Hashes `inputs` with the Poseidon permutation. The result is the
+/// same as `poseidon::poseidon_hash` and circomlib's `Poseidon`
+/// template.
+pub fn poseidon_hash<E, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<E>],
+    params: &PoseidonParams<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(inputs.len(), params.num_inputs());
+
+    let one = CS::one();
+
+    let mut state = vec![StateElement::zero()];
+    state.extend(inputs.iter().map(StateElement::from_num));
+
+    for round in 0..(params.full_rounds() + params.partial_rounds()) {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for (elt, c) in state.iter_mut().zip(params.round_constants(round)) {
+            elt.add_constant(one, c);
+        }
+
+        if params.is_full_round(round) {
+            for (i, elt) in state.iter_mut().enumerate() {
+                *elt = elt.sbox(cs.namespace(|| format!("sbox {}", i)))?;
+            }
+        } else {
+            state[0] = state[0].sbox(cs.namespace(|| "sbox 0"))?;
+        }
+
+        state = params.mds_matrix().iter().map(|row| StateElement::mix(row, &state)).collect();
+    }
+
+    let hash = AllocatedNum::alloc(cs.namespace(|| "hash"), || {
+        Ok(*state[0].value.get()?)
+    })?;
+    cs.enforce(
+        || "hash constraint",
+        |_| state[0].lc(),
+        |lc| lc + one,
+        |lc| lc + hash.get_variable()
+    );
+
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use super::*;
+    use ::circuit::test::*;
+    use ::circuit::boolean::Boolean;
+    use ::circuit::{baby_pedersen_hash, blake2s};
+    use ::babyjubjub::JubjubBn256;
+    use pairing::bn256::{Bn256, Fr};
+    use ff::PrimeField;
+
+    #[test]
+    fn test_poseidon_hash() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for num_inputs in 1..6 {
+            let params = PoseidonParams::<Bn256>::new(num_inputs);
+            let values: Vec<Fr> = (0..num_inputs).map(|_| rng.gen()).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let inputs: Vec<_> = values.iter().enumerate().map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*v)).unwrap()
+            }).collect();
+
+            let hash = poseidon_hash(cs.namespace(|| "poseidon"), &inputs, &params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(hash.get_value().unwrap(), ::poseidon::poseidon_hash(&params, &values));
+
+            // Three constraints per S-box, plus one for the output
+            let t = num_inputs + 1;
+            let expected = 3 * (params.full_rounds() * t + params.partial_rounds()) + 1;
+            assert_eq!(cs.num_constraints(), expected);
+        }
+    }
+
+    #[test]
+    fn test_poseidon_circomlib_vector() {
+        let params = PoseidonParams::<Bn256>::new(2);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("1").unwrap())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("2").unwrap())).unwrap();
+        let hash = poseidon_hash(cs.namespace(|| "poseidon"), &[a, b], &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(hash.get_value().unwrap(), Fr::from_str(
+            "7853200120776062878684798364095072458815029376092732009249414926327459813530"
+        ).unwrap());
+        assert_eq!(cs.num_constraints(), 244);
+
+        // Tampering with the output is caught
+        cs.set("poseidon/hash/num", Fr::from_str("1").unwrap());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_poseidon_cheaper_than_merkle_hashes() {
+        // Hashing two field elements the way the Merkle circuits hash
+        // a pair of nodes
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let left: Fr = rng.gen();
+        let right: Fr = rng.gen();
+
+        let poseidon_constraints = {
+            let params = PoseidonParams::<Bn256>::new(2);
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let l = AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(left)).unwrap();
+            let r = AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(right)).unwrap();
+            poseidon_hash(cs.namespace(|| "poseidon"), &[l, r], &params).unwrap();
+            assert!(cs.is_satisfied());
+            cs.num_constraints()
+        };
+
+        let pedersen_constraints = {
+            let params = &JubjubBn256::new();
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let l = AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(left)).unwrap();
+            let r = AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(right)).unwrap();
+            let mut preimage = l.into_bits_le_strict(cs.namespace(|| "left bits")).unwrap();
+            preimage.extend(r.into_bits_le_strict(cs.namespace(|| "right bits")).unwrap());
+            baby_pedersen_hash::pedersen_hash(
+                cs.namespace(|| "pedersen"),
+                baby_pedersen_hash::Personalization::MerkleTree(0),
+                &preimage,
+                params
+            ).unwrap();
+            assert!(cs.is_satisfied());
+            cs.num_constraints()
+        };
+
+        let blake2s_constraints = {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let l = AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(left)).unwrap();
+            let r = AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(right)).unwrap();
+            let mut preimage = l.into_bits_le_strict(cs.namespace(|| "left bits")).unwrap();
+            preimage.push(Boolean::constant(false));
+            preimage.push(Boolean::constant(false));
+            preimage.extend(r.into_bits_le_strict(cs.namespace(|| "right bits")).unwrap());
+            preimage.push(Boolean::constant(false));
+            preimage.push(Boolean::constant(false));
+            blake2s::blake2s(cs.namespace(|| "blake2s"), &preimage, b"12345678").unwrap();
+            assert!(cs.is_satisfied());
+            cs.num_constraints()
+        };
+
+        assert_eq!(poseidon_constraints, 244);
+        assert_eq!(pedersen_constraints, 1576);
+        assert_eq!(blake2s_constraints, 21714);
+    }
+}
@@ -1,8 +1,10 @@
 use super::uint32::UInt32;
 use super::multieq::MultiEq;
-use super::boolean::Boolean;
+use super::boolean::{Boolean, AllocatedBit};
+use super::num::AllocatedNum;
 use bellman::{ConstraintSystem, SynthesisError};
 use pairing::Engine;
+use ff::{Field, PrimeField};
 
 const ROUND_CONSTANTS: [u32; 64] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
@@ -74,6 +76,216 @@ pub fn sha256<E, CS>(
     .collect())
 }
 
+/// Computes SHA-256 of the first `length` bytes of `input`, where
+/// `length` is only known to the prover. `input` holds the maximum
+/// number of bytes, most significant bit first like in `sha256`, and
+/// the bytes past `length` are ignored.
+///
+/// The padding is built in the circuit for every possible length, all
+/// the blocks that the longest message needs are compressed, and the
+/// state after the last block of the actual message is returned. The
+/// circuit is unsatisfiable if `length` is larger than the input.
+pub fn sha256_var<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    length: &AllocatedNum<E>
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    let max_len = input.len() / 8;
+    let num_blocks = (max_len + 9 + 63) / 64;
+
+    let length_value = length.get_value().map(|v| {
+        let repr = v.into_repr();
+        if repr.as_ref()[1..].iter().all(|&limb| limb == 0) {
+            repr.as_ref()[0]
+        } else {
+            u64::max_value()
+        }
+    });
+
+    // before[i] is set when byte i is part of the message. These bits
+    // are decreasing, so that their sum is the length.
+    let mut before: Vec<AllocatedBit> = Vec::with_capacity(max_len);
+    for i in 0..max_len {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("byte {} is before the end", i)),
+            length_value.map(|l| (i as u64) < l)
+        )?;
+
+        if i > 0 {
+            cs.enforce(
+                || format!("byte {} is before the end only if byte {} is", i, i - 1),
+                |lc| lc + bit.get_variable(),
+                |lc| lc + CS::one() - before[i - 1].get_variable(),
+                |lc| lc
+            );
+        }
+
+        before.push(bit);
+    }
+
+    cs.enforce(
+        || "length is the number of message bytes",
+        |lc| before.iter().fold(lc, |lc, bit| lc + bit.get_variable()),
+        |lc| lc + CS::one(),
+        |lc| lc + length.get_variable()
+    );
+
+    let before: Vec<Boolean> = before.into_iter().map(Boolean::from).collect();
+
+    // at_end[i] is set when byte i directly follows the message, which
+    // is where the '1' bit of the padding goes
+    let mut at_end = Vec::with_capacity(max_len + 1);
+    for i in 0..(max_len + 1) {
+        at_end.push(if max_len == 0 {
+            Boolean::constant(true)
+        } else if i == 0 {
+            before[0].not()
+        } else if i == max_len {
+            before[max_len - 1].clone()
+        } else {
+            Boolean::and(
+                cs.namespace(|| format!("byte {} is at the end", i)),
+                &before[i - 1],
+                &before[i].not()
+            )?
+        });
+    }
+
+    // used[k] is set when the padded message has more than k blocks,
+    // and last[k] when block k is its last block
+    let used: Vec<Boolean> = (0..num_blocks).map(|k| {
+        if k == 0 {
+            Boolean::constant(true)
+        } else {
+            // The message fills more than k blocks when it has at least
+            // 64k - 8 bytes, as the padding takes at least 9 bytes
+            before[64 * k - 9].clone()
+        }
+    }).collect();
+
+    let mut last = Vec::with_capacity(num_blocks);
+    for k in 0..num_blocks {
+        last.push(if k == num_blocks - 1 {
+            used[k].clone()
+        } else {
+            Boolean::and(
+                cs.namespace(|| format!("block {} is the last one", k)),
+                &used[k],
+                &used[k + 1].not()
+            )?
+        });
+    }
+
+    // The length in bytes fits in this many bits
+    let length_bits_len = 64 - (max_len as u64).leading_zeros() as usize;
+    let mut length_bits = Vec::with_capacity(length_bits_len);
+    for i in 0..length_bits_len {
+        length_bits.push(AllocatedBit::alloc(
+            cs.namespace(|| format!("length bit {}", i)),
+            length_value.map(|l| (l >> i) & 1 == 1)
+        )?);
+    }
+
+    cs.enforce(
+        || "length bit decomposition",
+        |lc| {
+            let mut coeff = E::Fr::one();
+            length_bits.iter().fold(lc, |lc, bit| {
+                let lc = lc + (coeff, bit.get_variable());
+                coeff.double();
+                lc
+            })
+        },
+        |lc| lc + CS::one(),
+        |lc| lc + length.get_variable()
+    );
+
+    let length_bits: Vec<Boolean> = length_bits.into_iter().map(Boolean::from).collect();
+
+    let mut padded = Vec::with_capacity(num_blocks * 512);
+    for i in 0..(num_blocks * 64) {
+        let mut cs = cs.namespace(|| format!("padded byte {}", i));
+
+        for j in 0..8 {
+            // The parts below are never set together, so they can be
+            // combined with xor
+            let mut bit = if i < max_len {
+                Boolean::and(
+                    cs.namespace(|| format!("message bit {}", j)),
+                    &input[i * 8 + j],
+                    &before[i]
+                )?
+            } else {
+                Boolean::constant(false)
+            };
+
+            if j == 0 && i <= max_len {
+                bit = Boolean::xor(
+                    cs.namespace(|| "padding bit"),
+                    &bit,
+                    &at_end[i]
+                )?;
+            }
+
+            // The last 8 bytes of the last block hold the length in bits,
+            // as a big-endian integer
+            let block = i / 64;
+            let position = (i % 64) * 8 + j;
+            if position >= 448 {
+                let exponent = 511 - position;
+                if exponent >= 3 && exponent - 3 < length_bits_len {
+                    let length_bit = Boolean::and(
+                        cs.namespace(|| format!("length bit {}", j)),
+                        &last[block],
+                        &length_bits[exponent - 3]
+                    )?;
+
+                    bit = Boolean::xor(
+                        cs.namespace(|| format!("length xor {}", j)),
+                        &bit,
+                        &length_bit
+                    )?;
+                }
+            }
+
+            padded.push(bit);
+        }
+    }
+
+    let mut cur = get_sha256_iv();
+    let mut out: Vec<Boolean> = vec![];
+    for (k, block) in padded.chunks(512).enumerate() {
+        cur = sha256_compression_function(
+            cs.namespace(|| format!("block {}", k)),
+            block,
+            &cur
+        )?;
+
+        let state: Vec<Boolean> = cur.iter().flat_map(|e| e.into_bits_be()).collect();
+
+        out = if k == 0 {
+            state
+        } else {
+            let mut cs = cs.namespace(|| format!("select block {}", k));
+
+            state.iter().zip(out.iter()).enumerate().map(|(i, (a, b))| {
+                Boolean::sha256_ch(
+                    cs.namespace(|| format!("bit {}", i)),
+                    &used[k],
+                    a,
+                    b
+                )
+            }).collect::<Result<_, _>>()?
+        };
+    }
+
+    Ok(out)
+}
+
 pub fn get_sha256_iv() -> Vec<UInt32> {
     IV.iter().map(|&v| UInt32::constant(v)).collect()
 }
@@ -414,4 +626,108 @@ mod test {
             }
         }
     }
+
+    fn alloc_bytes_be<CS: ConstraintSystem<Bls12>>(cs: &mut CS, data: &[u8]) -> Vec<Boolean> {
+        data.iter().enumerate().flat_map(|(byte_i, &byte)| {
+            (0..8).rev().map(move |bit_i| (byte_i, bit_i, (byte >> bit_i) & 1u8 == 1u8))
+        }).map(|(byte_i, bit_i, b)| {
+            AllocatedBit::alloc(cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)), Some(b)).unwrap().into()
+        }).collect()
+    }
+
+    #[test]
+    fn test_sha256_var() {
+        use crypto::sha2::Sha256;
+        use crypto::digest::Digest;
+        use pairing::bls12_381::Fr;
+
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &(max_len, length) in [
+            (0, 0),
+            (10, 0),
+            (10, 7),
+            (55, 55),
+            (56, 55),
+            (56, 56),
+            (100, 63),
+            (100, 64),
+            (130, 119),
+            (130, 120)
+        ].iter() {
+            // The bytes past the length are garbage
+            let data: Vec<u8> = (0..max_len).map(|_| rng.gen()).collect();
+
+            let mut h = Sha256::new();
+            h.input(&data[0..length]);
+            let mut hash_result = [0u8; 32];
+            h.result(&mut hash_result[..]);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_bytes_be(&mut cs, &data);
+            let length = AllocatedNum::alloc(cs.namespace(|| "length"), || {
+                Ok(Fr::from_str(&length.to_string()).unwrap())
+            }).unwrap();
+
+            let r = sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected: Vec<bool> = hash_result.iter()
+                                                 .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                                                 .collect();
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+
+            assert_eq!(r, expected);
+        }
+    }
+
+    #[test]
+    fn test_sha256_var_length_out_of_range() {
+        use pairing::bls12_381::Fr;
+
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let data: Vec<u8> = (0..20).map(|_| rng.gen()).collect();
+
+        for &length in [21, 1000].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_bytes_be(&mut cs, &data);
+            let length = AllocatedNum::alloc(cs.namespace(|| "length"), || {
+                Ok(Fr::from_str(&length.to_string()).unwrap())
+            }).unwrap();
+
+            sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+
+            assert!(!cs.is_satisfied());
+        }
+
+        // A witness can't claim a shorter length with the same bits
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_bytes_be(&mut cs, &data);
+        let length = AllocatedNum::alloc(cs.namespace(|| "length"), || {
+            Ok(Fr::from_str("10").unwrap())
+        }).unwrap();
+
+        sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+        assert!(cs.is_satisfied());
+
+        cs.set("sha256/byte 10 is before the end/boolean", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_sha256_var_constraints() {
+        use pairing::bls12_381::Fr;
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_bytes_be(&mut cs, &[0u8; 64]);
+        let length = AllocatedNum::alloc(cs.namespace(|| "length"), || {
+            Ok(Fr::from_str("64").unwrap())
+        }).unwrap();
+
+        sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - 512, 52069);
+    }
 }
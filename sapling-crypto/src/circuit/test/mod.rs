@@ -3,6 +3,7 @@ use pairing::{
 };
 
 use ff::{
+    BitIterator,
     Field,
     PrimeField,
     PrimeFieldRepr
@@ -26,6 +27,8 @@ use std::collections::HashSet;
 
 use blake2_rfc::blake2s::Blake2s;
 
+use super::boolean::{Boolean, AllocatedBit};
+
 #[derive(Debug)]
 enum NamedObject {
     Constraint(usize),
@@ -540,3 +543,90 @@ fn test_cs() {
 
     assert!(cs.get("test1/test2/hehe") == Fr::one());
 }
+
+/// Allocates the `num_bits` least significant bits of the scalar `s`
+/// in little-endian order, and returns them with the scalar they
+/// represent.
+pub fn alloc_scalar_bits<E, F, CS>(
+    mut cs: CS,
+    s: F,
+    num_bits: usize
+) -> (Vec<Boolean>, F)
+    where E: Engine, F: PrimeField, CS: ConstraintSystem<E>
+{
+    let mut s_bits = BitIterator::new(s.into_repr()).collect::<Vec<_>>();
+    s_bits.reverse();
+    s_bits.truncate(num_bits);
+
+    // the scalar the truncated bits represent
+    let mut truncated = F::zero();
+    for bit in s_bits.iter().rev() {
+        truncated.double();
+        if *bit {
+            truncated.add_assign(&F::one());
+        }
+    }
+
+    let s_bits = s_bits.into_iter()
+                       .enumerate()
+                       .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("scalar bit {}", i)), Some(b)).unwrap())
+                       .map(|v| Boolean::from(v))
+                       .collect::<Vec<_>>();
+
+    (s_bits, truncated)
+}
+
+/// Tests `mul_windowed` of the `EdwardsPoint` of the parent module
+/// on the curve of the `$curve` module with the `$params` parameters,
+/// where a full scalar costs `$constraints` constraints.
+#[cfg(test)]
+macro_rules! test_edwards_windowed_multiplication {
+    ($curve:ident, $params:expr, $constraints:expr) => {
+        #[test]
+        fn test_edwards_windowed_multiplication() {
+            use bellman::ConstraintSystem;
+            use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+            use pairing::bn256::Bn256;
+            use ff::PrimeField;
+            use circuit::test::{TestConstraintSystem, alloc_scalar_bits};
+            use $curve::edwards;
+            use $curve::fs::Fs;
+            use super::EdwardsPoint;
+
+            let params = &$params;
+            let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+            for num_bits in [1, 2, 3, 10, Fs::NUM_BITS as usize].iter().cycle().take(25) {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+
+                let p = edwards::Point::<Bn256, _>::rand(rng, params);
+                let (s_bits, s) = alloc_scalar_bits(cs.namespace(|| "s"), Fs::rand(rng), *num_bits);
+                let (x1, y1) = p.mul(s, params).into_xy();
+
+                let p = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+                let before = cs.num_constraints();
+                let q = p.mul_windowed(
+                    cs.namespace(|| "scalar mul"),
+                    &s_bits,
+                    params
+                ).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(q.x.get_value().unwrap(), x1);
+                assert_eq!(q.y.get_value().unwrap(), y1);
+                if *num_bits == Fs::NUM_BITS as usize {
+                    let windowed = cs.num_constraints() - before;
+                    assert_eq!(windowed, $constraints);
+
+                    // The same scalar costs more bit by bit
+                    let before = cs.num_constraints();
+                    p.mul(cs.namespace(|| "bitwise scalar mul"), &s_bits, params).unwrap();
+                    assert!(windowed < cs.num_constraints() - before);
+                }
+
+                cs.set("scalar mul/window lookups/lookup 0/coordinate 0 bit 1 selection 0/num", rng.gen());
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Shared implementation of the fixed width unsigned integers
+//! `UInt8` and `UInt64`, so that both get the same overflow and
+//! underflow handling.
+//!
+//! `uint_impl!` expands in the module of the integer, which must
+//! import what `uint64.rs` imports.
+
+macro_rules! uint_impl {
+    ($name:ident, $native:ty, $bits:expr, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone)]
+        pub struct $name {
+            // Least significant bit first
+            bits: Vec<Boolean>,
+            value: Option<$native>
+        }
+
+        impl $name {
+            /// Construct a constant from a native integer
+            pub fn constant(value: $native) -> Self
+            {
+                let mut bits = Vec::with_capacity($bits);
+
+                let mut tmp = value;
+                for _ in 0..$bits {
+                    if tmp & 1 == 1 {
+                        bits.push(Boolean::constant(true))
+                    } else {
+                        bits.push(Boolean::constant(false))
+                    }
+
+                    tmp >>= 1;
+                }
+
+                $name {
+                    bits: bits,
+                    value: Some(value)
+                }
+            }
+
+            /// Allocate the integer in the constraint system
+            pub fn alloc<E, CS>(
+                mut cs: CS,
+                value: Option<$native>
+            ) -> Result<Self, SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                let values = match value {
+                    Some(mut val) => {
+                        let mut v = Vec::with_capacity($bits);
+
+                        for _ in 0..$bits {
+                            v.push(Some(val & 1 == 1));
+                            val >>= 1;
+                        }
+
+                        v
+                    },
+                    None => vec![None; $bits]
+                };
+
+                let bits = values.into_iter()
+                                 .enumerate()
+                                 .map(|(i, v)| {
+                                    Ok(Boolean::from(AllocatedBit::alloc(
+                                        cs.namespace(|| format!("allocated bit {}", i)),
+                                        v
+                                    )?))
+                                 })
+                                 .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: value
+                })
+            }
+
+            pub fn into_bits_be(&self) -> Vec<Boolean> {
+                self.bits.iter().rev().cloned().collect()
+            }
+
+            pub fn from_bits_be(bits: &[Boolean]) -> Self {
+                assert_eq!(bits.len(), $bits);
+
+                let mut value = Some(0 as $native);
+                for b in bits {
+                    value.as_mut().map(|v| *v <<= 1);
+
+                    match b.get_value() {
+                        Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                        Some(false) => {},
+                        None => { value = None; }
+                    }
+                }
+
+                $name {
+                    value: value,
+                    bits: bits.iter().rev().cloned().collect()
+                }
+            }
+
+
+            /// Turns the integer into its little-endian byte order representation.
+            pub fn into_bits(&self) -> Vec<Boolean> {
+                self.bits.clone()
+            }
+
+            /// Converts a little-endian byte order representation of bits into
+            /// an integer.
+            pub fn from_bits(bits: &[Boolean]) -> Self
+            {
+                assert_eq!(bits.len(), $bits);
+
+                let new_bits = bits.to_vec();
+
+                let mut value = Some(0 as $native);
+                for b in new_bits.iter().rev() {
+                    value.as_mut().map(|v| *v <<= 1);
+
+                    match b {
+                        &Boolean::Constant(b) => {
+                            if b {
+                                value.as_mut().map(|v| *v |= 1);
+                            }
+                        },
+                        &Boolean::Is(ref b) => {
+                            match b.get_value() {
+                                Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                                Some(false) => {},
+                                None => { value = None }
+                            }
+                        },
+                        &Boolean::Not(ref b) => {
+                            match b.get_value() {
+                                Some(false) => { value.as_mut().map(|v| *v |= 1); },
+                                Some(true) => {},
+                                None => { value = None }
+                            }
+                        }
+                    }
+                }
+
+                $name {
+                    value: value,
+                    bits: new_bits
+                }
+            }
+
+            pub fn rotr(&self, by: usize) -> Self {
+                let by = by % $bits;
+
+                let new_bits = self.bits.iter()
+                                        .skip(by)
+                                        .chain(self.bits.iter())
+                                        .take($bits)
+                                        .cloned()
+                                        .collect();
+
+                $name {
+                    bits: new_bits,
+                    value: self.value.map(|v| v.rotate_right(by as u32))
+                }
+            }
+
+            pub fn shr(&self, by: usize) -> Self {
+                let by = by % $bits;
+
+                let fill = Boolean::constant(false);
+
+                let new_bits = self.bits
+                                   .iter() // The bits are least significant first
+                                   .skip(by) // Skip the bits that will be lost during the shift
+                                   .chain(Some(&fill).into_iter().cycle()) // Rest will be zeros
+                                   .take($bits) // Only the width is needed!
+                                   .cloned()
+                                   .collect();
+
+                $name {
+                    bits: new_bits,
+                    value: self.value.map(|v| v >> by as u32)
+                }
+            }
+
+            /// XOR the integer with another one
+            pub fn xor<E, CS>(
+                &self,
+                mut cs: CS,
+                other: &Self
+            ) -> Result<Self, SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                let new_value = match (self.value, other.value) {
+                    (Some(a), Some(b)) => {
+                        Some(a ^ b)
+                    },
+                    _ => None
+                };
+
+                let bits = self.bits.iter()
+                                    .zip(other.bits.iter())
+                                    .enumerate()
+                                    .map(|(i, (a, b))| {
+                                        Boolean::xor(
+                                            cs.namespace(|| format!("xor of bit {}", i)),
+                                            a,
+                                            b
+                                        )
+                                    })
+                                    .collect::<Result<_, _>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: new_value
+                })
+            }
+
+            /// Perform modular addition of several integers, and return
+            /// whether the sum overflowed along with it.
+            pub fn addmany_with_overflow<E, CS>(
+                mut cs: CS,
+                operands: &[Self]
+            ) -> Result<(Self, Boolean), SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                // The sum must not overflow in the scalar field
+                assert!(E::Fr::NUM_BITS as usize >= 2 * $bits);
+                assert!(operands.len() >= 2);
+
+                let mut max_value = (operands.len() as u128) * (<$native>::max_value() as u128);
+
+                let mut result_value = Some(0u128);
+                let mut lc = LinearCombination::zero();
+                let mut all_constants = true;
+
+                for op in operands {
+                    match op.value {
+                        Some(val) => {
+                            result_value.as_mut().map(|v| *v += val as u128);
+                        },
+                        None => {
+                            result_value = None;
+                        }
+                    }
+
+                    lc = lc + &op.lc(CS::one());
+                    all_constants &= op.bits.iter().all(|b| b.is_constant());
+                }
+
+                if all_constants && result_value.is_some() {
+                    let result_value = result_value.unwrap();
+
+                    return Ok((
+                        $name::constant(result_value as $native),
+                        Boolean::constant(result_value > <$native>::max_value() as u128)
+                    ));
+                }
+
+                let mut result_bits = vec![];
+                let mut result_lc = LinearCombination::zero();
+
+                let mut coeff = E::Fr::one();
+                let mut i = 0;
+                while max_value != 0 {
+                    let b = AllocatedBit::alloc(
+                        cs.namespace(|| format!("result bit {}", i)),
+                        result_value.map(|v| (v >> i) & 1 == 1)
+                    )?;
+
+                    result_lc = result_lc + (coeff, b.get_variable());
+
+                    result_bits.push(Boolean::from(b));
+
+                    max_value >>= 1;
+                    i += 1;
+                    coeff.double();
+                }
+
+                cs.enforce(
+                    || "addition constraint",
+                    |_| lc,
+                    |lc| lc + CS::one(),
+                    |_| result_lc
+                );
+
+                // The sum overflowed iff any of the carry bits is set
+                let mut no_carry = Boolean::constant(true);
+                for (i, carry) in result_bits[$bits..].iter().enumerate() {
+                    no_carry = Boolean::and(
+                        cs.namespace(|| format!("no carry {}", i)),
+                        &no_carry,
+                        &carry.not()
+                    )?;
+                }
+
+                result_bits.truncate($bits);
+
+                Ok((
+                    $name {
+                        bits: result_bits,
+                        value: result_value.map(|v| v as $native)
+                    },
+                    no_carry.not()
+                ))
+            }
+
+            /// Perform modular subtraction of `other` from the integer, and
+            /// return whether it underflowed along with the difference. Costs
+            /// `bits + 2` constraints.
+            pub fn sub<E, CS>(
+                &self,
+                mut cs: CS,
+                other: &Self
+            ) -> Result<(Self, Boolean), SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                assert!(E::Fr::NUM_BITS as usize > $bits + 1);
+
+                let underflow_value = match (self.value, other.value) {
+                    (Some(a), Some(b)) => Some(a < b),
+                    _ => None
+                };
+                let result_value = match (self.value, other.value) {
+                    (Some(a), Some(b)) => Some(a.wrapping_sub(b)),
+                    _ => None
+                };
+
+                if self.bits.iter().chain(other.bits.iter()).all(|b| b.is_constant()) {
+                    return Ok((
+                        $name::constant(result_value.unwrap()),
+                        Boolean::constant(underflow_value.unwrap())
+                    ));
+                }
+
+                // self - other + 2^bits fits in one more bit, and the top one is
+                // set iff there is no underflow
+                let mut shift = E::Fr::one();
+                for _ in 0..$bits {
+                    shift.double();
+                }
+
+                let mut result_bits = Vec::with_capacity($bits + 1);
+                let mut result_lc = LinearCombination::zero();
+                let mut coeff = E::Fr::one();
+                for i in 0..($bits + 1) {
+                    let b = AllocatedBit::alloc(
+                        cs.namespace(|| format!("result bit {}", i)),
+                        match (result_value, underflow_value) {
+                            (Some(v), Some(underflow)) => Some(if i < $bits { (v >> i) & 1 == 1 } else { !underflow }),
+                            _ => None
+                        }
+                    )?;
+
+                    result_lc = result_lc + (coeff, b.get_variable());
+
+                    result_bits.push(Boolean::from(b));
+
+                    coeff.double();
+                }
+
+                cs.enforce(
+                    || "subtraction constraint",
+                    |_| self.lc(CS::one()) - &other.lc(CS::one()) + (shift, CS::one()),
+                    |lc| lc + CS::one(),
+                    |_| result_lc
+                );
+
+                let no_underflow = result_bits.pop().unwrap();
+
+                Ok((
+                    $name {
+                        bits: result_bits,
+                        value: result_value
+                    },
+                    no_underflow.not()
+                ))
+            }
+
+            /// Packs the integer into an `AllocatedNum`. Costs 1 constraint.
+            pub fn into_num<E, CS>(
+                &self,
+                mut cs: CS
+            ) -> Result<AllocatedNum<E>, SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+                    let value = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok(E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(value as u64)).unwrap())
+                })?;
+
+                cs.enforce(
+                    || "packing constraint",
+                    |_| self.lc(CS::one()),
+                    |lc| lc + CS::one(),
+                    |lc| lc + num.get_variable()
+                );
+
+                Ok(num)
+            }
+
+            /// Unpacks an `AllocatedNum` into an integer, which makes the
+            /// circuit unsatisfiable if it doesn't fit. Costs `bits + 1`
+            /// constraints.
+            pub fn from_num<E, CS>(
+                mut cs: CS,
+                num: &AllocatedNum<E>
+            ) -> Result<Self, SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>
+            {
+                // Only the lowest bits are allocated, so that a larger
+                // number makes the packing constraint fail
+                let value = num.get_value().map(|v| v.into_repr().as_ref()[0] as $native);
+
+                let result = Self::alloc(cs.namespace(|| "bits"), value)?;
+
+                cs.enforce(
+                    || "packing constraint",
+                    |_| result.lc(CS::one()),
+                    |lc| lc + CS::one(),
+                    |lc| lc + num.get_variable()
+                );
+
+                Ok(result)
+            }
+
+            fn lc<E: Engine>(&self, one: Variable) -> LinearCombination<E> {
+                let mut lc = LinearCombination::zero();
+                let mut coeff = E::Fr::one();
+                for bit in &self.bits {
+                    lc = lc + &bit.lc(one, coeff);
+                    coeff.double();
+                }
+
+                lc
+            }
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use pairing::{
+    Engine,
+};
+
+use ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+use super::num::AllocatedNum;
+
+use super::multieq::MultiEq;
+
+uint_impl!(
+    UInt64,
+    u64,
+    64,
+    "Represents an interpretation of 64 `Boolean` objects as an unsigned integer."
+);
+
+impl UInt64 {
+    /// Perform modular addition of several `UInt64` objects.
+    pub fn addmany<E, CS, M>(
+        mut cs: M,
+        operands: &[Self]
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>,
+              M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+    {
+        // Make some arbitrary bounds for ourselves to avoid overflows
+        // in the scalar field
+        assert!(E::Fr::NUM_BITS >= 128);
+        assert!(operands.len() >= 2); // Weird trivial cases that should never happen
+        assert!(operands.len() <= 10);
+
+        // Compute the maximum value of the sum so we allocate enough bits for
+        // the result
+        let mut max_value = (operands.len() as u128) * (u64::max_value() as u128);
+
+        // Keep track of the resulting value
+        let mut result_value = Some(0u128);
+
+        // This is a linear combination that we will enforce to equal the
+        // output
+        let mut lc = LinearCombination::zero();
+
+        let mut all_constants = true;
+
+        // Iterate over the operands
+        for op in operands {
+            // Accumulate the value
+            match op.value {
+                Some(val) => {
+                    result_value.as_mut().map(|v| *v += val as u128);
+                },
+                None => {
+                    // If any of our operands have unknown value, we won't
+                    // know the value of the result
+                    result_value = None;
+                }
+            }
+
+            // Iterate over each bit of the operand and add the operand to
+            // the linear combination
+            let mut coeff = E::Fr::one();
+            for bit in &op.bits {
+                lc = lc + &bit.lc(CS::one(), coeff);
+
+                all_constants &= bit.is_constant();
+
+                coeff.double();
+            }
+        }
+
+        // The value of the actual result is modulo 2^64
+        let modular_value = result_value.map(|v| v as u64);
+
+        if all_constants && modular_value.is_some() {
+            // We can just return a constant, rather than
+            // unpacking the result into allocated bits.
+
+            return Ok(UInt64::constant(modular_value.unwrap()));
+        }
+
+        // Storage area for the resulting bits
+        let mut result_bits = vec![];
+
+        // Linear combination representing the output,
+        // for comparison with the sum of the operands
+        let mut result_lc = LinearCombination::zero();
+
+        // Allocate each bit of the result
+        let mut coeff = E::Fr::one();
+        let mut i = 0;
+        while max_value != 0 {
+            // Allocate the bit
+            let b = AllocatedBit::alloc(
+                cs.namespace(|| format!("result bit {}", i)),
+                result_value.map(|v| (v >> i) & 1 == 1)
+            )?;
+
+            // Add this bit to the result combination
+            result_lc = result_lc + (coeff, b.get_variable());
+
+            result_bits.push(b.into());
+
+            max_value >>= 1;
+            i += 1;
+            coeff.double();
+        }
+
+        // Enforce equality between the sum and result
+        cs.get_root().enforce_equal(i, &lc, &result_lc);
+
+        // Discard carry bits that we don't care about
+        result_bits.truncate(64);
+
+        Ok(UInt64 {
+            bits: result_bits,
+            value: modular_value
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use ::circuit::boolean::{Boolean};
+    use ::circuit::num::AllocatedNum;
+    use super::{UInt64};
+    use pairing::bn256::{Bn256};
+    use ff::{Field};
+    use ::circuit::test::*;
+    use bellman::{ConstraintSystem};
+    use circuit::multieq::MultiEq;
+
+    #[test]
+    fn test_uint64_from_bits() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let v = (0..64).map(|_| Boolean::constant(rng.gen())).collect::<Vec<_>>();
+
+            let b = UInt64::from_bits(&v);
+
+            for (i, bit) in b.bits.iter().enumerate() {
+                match bit {
+                    &Boolean::Constant(bit) => {
+                        assert!(bit == ((b.value.unwrap() >> i) & 1 == 1));
+                    },
+                    _ => unreachable!()
+                }
+            }
+
+            let b = UInt64::from_bits_be(&b.into_bits_be());
+            for (a, b) in v.iter().zip(b.into_bits().iter()) {
+                assert_eq!(a.get_value(), b.get_value());
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_xor() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let mut expected = a ^ b ^ c;
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "first xor"), &b_bit).unwrap();
+            let r = r.xor(cs.namespace(|| "second xor"), &c_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+
+                expected >>= 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_constants() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let expected = a.wrapping_add(b).wrapping_add(c);
+
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(
+                    cs.namespace(|| "addition"),
+                    &[UInt64::constant(a), UInt64::constant(b), UInt64::constant(c)]
+                ).unwrap()
+            };
+
+            assert!(r.value == Some(expected));
+            assert!(r.bits.iter().all(|b| b.is_constant()));
+            assert_eq!(cs.num_constraints(), 0);
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+            let d: u64 = rng.gen();
+
+            let mut expected = (a ^ b).wrapping_add(c).wrapping_add(d);
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+            let d_bit = UInt64::alloc(cs.namespace(|| "d_bit"), Some(d)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap()
+            };
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                assert!(!b.is_constant());
+                assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+
+                expected >>= 1;
+            }
+
+            // Flip a bit and see if the addition constraint still works
+            if cs.get("addition/result bit 0/boolean").is_zero() {
+                cs.set("addition/result bit 0/boolean", Field::one());
+            } else {
+                cs.set("addition/result bit 0/boolean", Field::zero());
+            }
+
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_rotr() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut num: u64 = rng.gen();
+
+        let a = UInt64::constant(num);
+
+        for i in 0..64 {
+            let b = a.rotr(i);
+            assert_eq!(a.bits.len(), b.bits.len());
+
+            assert!(b.value.unwrap() == num);
+
+            let mut tmp = num;
+            for b in &b.bits {
+                assert_eq!(b.get_value().unwrap(), tmp & 1 == 1);
+
+                tmp >>= 1;
+            }
+
+            num = num.rotate_right(1);
+        }
+    }
+
+    #[test]
+    fn test_uint64_shr() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..50 {
+            for i in 0..64 {
+                let num: u64 = rng.gen();
+                let a = UInt64::constant(num).shr(i);
+                let b = UInt64::constant(num >> i);
+
+                assert_eq!(a.value.unwrap(), num >> i);
+
+                assert_eq!(a.bits.len(), b.bits.len());
+                for (a, b) in a.bits.iter().zip(b.bits.iter()) {
+                    assert_eq!(a.get_value().unwrap(), b.get_value().unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_with_overflow() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            // Small operands don't overflow
+            let (a, b, c): (u64, u64, u64) = if i % 2 == 0 {
+                (rng.gen(), rng.gen(), rng.gen())
+            } else {
+                (rng.gen::<u64>() >> 2, rng.gen::<u64>() >> 2, rng.gen::<u64>() >> 2)
+            };
+
+            let expected = a.wrapping_add(b).wrapping_add(c);
+            let expected_overflow = (a as u128) + (b as u128) + (c as u128) > u64::max_value() as u128;
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            let (r, overflow) = UInt64::addmany_with_overflow(cs.namespace(|| "addition"), &[a_bit, b_bit, c_bit]).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(r.value == Some(expected));
+            assert_eq!(overflow.get_value().unwrap(), expected_overflow);
+
+            let mut expected = expected;
+            for b in r.bits.iter() {
+                assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+                expected >>= 1;
+            }
+
+            // The carry can't be hidden
+            if expected_overflow {
+                for i in 64..66 {
+                    cs.set(&format!("addition/result bit {}/boolean", i), Field::zero());
+                }
+                assert!(!cs.is_satisfied());
+            }
+        }
+
+        // Constants overflow for free
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let (r, overflow) = UInt64::addmany_with_overflow(
+            &mut cs,
+            &[UInt64::constant(u64::max_value()), UInt64::constant(1)]
+        ).unwrap();
+        assert_eq!(r.value, Some(0));
+        assert_eq!(overflow.get_value(), Some(true));
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_uint64_sub() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = match i % 3 {
+                0 => a,
+                _ => rng.gen()
+            };
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::alloc(cs.namespace(|| "b_bit"), Some(b)).unwrap();
+
+            let (r, underflow) = a_bit.sub(cs.namespace(|| "subtraction"), &b_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints() - 128, 66);
+            assert!(r.value == Some(a.wrapping_sub(b)));
+            assert_eq!(underflow.get_value().unwrap(), a < b);
+
+            let mut expected = a.wrapping_sub(b);
+            for b in r.bits.iter() {
+                assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+                expected >>= 1;
+            }
+
+            // The underflow can't be hidden
+            cs.set(
+                "subtraction/result bit 64/boolean",
+                if a < b { Field::one() } else { Field::zero() }
+            );
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_num_conversion() {
+        use pairing::bn256::Fr;
+        use ff::PrimeField;
+
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a: u64 = rng.gen();
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+
+            let num = a_bit.into_num(cs.namespace(|| "into num")).unwrap();
+            assert!(num.get_value().unwrap() == Fr::from_str(&a.to_string()).unwrap());
+
+            let b_bit = UInt64::from_num(cs.namespace(|| "from num"), &num).unwrap();
+            assert_eq!(b_bit.value, Some(a));
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints() - 64, 1 + 65);
+        }
+
+        // 2^64 doesn't fit
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+            Ok(Fr::from_str("18446744073709551616").unwrap())
+        }).unwrap();
+        UInt64::from_num(cs.namespace(|| "from num"), &num).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
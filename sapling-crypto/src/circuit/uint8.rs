@@ -0,0 +1,109 @@
+use pairing::{
+    Engine,
+};
+
+use ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+use super::num::AllocatedNum;
+
+uint_impl!(
+    UInt8,
+    u8,
+    8,
+    "Represents an interpretation of 8 `Boolean` objects as an unsigned integer."
+);
+
+#[cfg(test)]
+mod test {
+    // The implementation is shared with `UInt64`, these tests cover the
+    // boundaries of the 8-bit width exhaustively.
+    use ::circuit::num::AllocatedNum;
+    use super::{UInt8};
+    use pairing::bn256::{Bn256, Fr};
+    use ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use bellman::{ConstraintSystem};
+
+    #[test]
+    fn test_uint8_addmany_with_overflow() {
+        for a in 0..256u64 {
+            for b in 0..256u64 {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+
+                let a_bit = UInt8::alloc(cs.namespace(|| "a_bit"), Some(a as u8)).unwrap();
+                let b_bit = UInt8::alloc(cs.namespace(|| "b_bit"), Some(b as u8)).unwrap();
+
+                let (r, overflow) = UInt8::addmany_with_overflow(cs.namespace(|| "addition"), &[a_bit, b_bit]).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(r.value, Some((a + b) as u8));
+                assert_eq!(UInt8::from_bits(&r.into_bits()).value, r.value);
+                assert_eq!(overflow.get_value().unwrap(), a + b > 255);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint8_sub() {
+        for a in 0..256u64 {
+            for b in 0..256u64 {
+                let (a, b) = (a as u8, b as u8);
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+
+                let a_bit = UInt8::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+                let b_bit = UInt8::alloc(cs.namespace(|| "b_bit"), Some(b)).unwrap();
+
+                let (r, underflow) = a_bit.sub(cs.namespace(|| "subtraction"), &b_bit).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints() - 16, 10);
+                assert_eq!(r.value, Some(a.wrapping_sub(b)));
+                assert_eq!(UInt8::from_bits(&r.into_bits()).value, r.value);
+                assert_eq!(underflow.get_value().unwrap(), a < b);
+
+                cs.set(
+                    "subtraction/result bit 8/boolean",
+                    if a < b { Field::one() } else { Field::zero() }
+                );
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint8_num_conversion() {
+        for a in 0..256u64 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a_bit = UInt8::alloc(cs.namespace(|| "a_bit"), Some(a as u8)).unwrap();
+            let num = a_bit.into_num(cs.namespace(|| "into num")).unwrap();
+            assert!(num.get_value().unwrap() == Fr::from_str(&a.to_string()).unwrap());
+
+            let b_bit = UInt8::from_num(cs.namespace(|| "from num"), &num).unwrap();
+            assert_eq!(b_bit.value, Some(a as u8));
+
+            assert!(cs.is_satisfied());
+        }
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str("256").unwrap())).unwrap();
+        UInt8::from_num(cs.namespace(|| "from num"), &num).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
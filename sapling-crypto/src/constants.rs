@@ -41,4 +41,11 @@ pub const NULLIFIER_POSITION_IN_TREE_GENERATOR_PERSONALIZATION: &'static [u8; 8]
 
 /// BLAKE2s Personalization hash of (R_x || message) in EdDSA variant with 256 bit hash
 pub const MATTER_EDDSA_BLAKE2S_PERSONALIZATION: &'static [u8; 8] 
-            = b"Matter_H";
\ No newline at end of file
+            = b"Matter_H";
+/// BLAKE2b Personalization hash of (sk || Pk || mode || message), the
+/// deterministic nonce of EdDSA signatures
+pub const MATTER_EDDSA_NONCE_PERSONALIZATION: &'static [u8; 16]
+            = b"Matter_EdDSA_Non";
+/// BLAKE2s Personalization for the MiMC-sponge round constants
+pub const MIMC_SPONGE_CONSTANTS_PERSONALIZATION: &'static [u8; 8]
+          = b"MiMC_Spg";
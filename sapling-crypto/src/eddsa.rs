@@ -1,8 +1,9 @@
 //! This is an implementation of EdDSA as refered in literature
-//! Generation of randomness is not specified
+//! Nonces are derived from the private key and the message by default,
+//! randomized signing is available with the `_randomized` methods
 
 use ff::{Field, PrimeField, PrimeFieldRepr};
-use rand::{Rng};
+use rand::{Rng, Rand};
 use std::io::{self, Read, Write};
 
 use jubjub::{
@@ -14,7 +15,7 @@ use jubjub::{
     ToUniform};
 use util::{hash_to_scalar, hash_to_scalar_s};
 
-use ::constants::{MATTER_EDDSA_BLAKE2S_PERSONALIZATION};
+use ::constants::{MATTER_EDDSA_BLAKE2S_PERSONALIZATION, MATTER_EDDSA_NONCE_PERSONALIZATION};
 
 fn read_scalar<E: JubjubEngine, R: Read>(reader: R) -> io::Result<E::Fs> {
     let mut s_repr = <E::Fs as PrimeField>::Repr::default();
@@ -41,6 +42,50 @@ fn h_star_s<E: JubjubEngine>(a: &[u8], b: &[u8]) -> E::Fs {
     hash_to_scalar_s::<E>(MATTER_EDDSA_BLAKE2S_PERSONALIZATION, a, b)
 }
 
+/// c = H*(R_x || R_y || Pk_x || Pk_y || M), the challenge of `verify`.
+fn challenge<E: JubjubEngine>(r: &Point<E, Unknown>, pk: &PublicKey<E>, msg: &[u8]) -> E::Fs {
+    let (r_g_x, r_g_y) = r.into_xy();
+    let mut r_g_x_bytes = [0u8; 32];
+    r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
+
+    let mut r_g_y_bytes = [0u8; 32];
+    r_g_y.into_repr().write_le(& mut r_g_y_bytes[..]).expect("has serialized r_g_y");
+
+    let (pk_x, pk_y) = pk.0.into_xy();
+    let mut pk_x_bytes = [0u8; 32];
+    pk_x.into_repr().write_le(& mut pk_x_bytes[..]).expect("has serialized pk_x");
+
+    let mut pk_y_bytes = [0u8; 32];
+    pk_y.into_repr().write_le(& mut pk_y_bytes[..]).expect("has serialized pk_y");
+
+    let concatenated: Vec<u8> = r_g_x_bytes.iter().chain(r_g_y_bytes.iter()).chain(pk_x_bytes.iter()).chain(pk_y_bytes.iter()).cloned().collect();
+
+    h_star::<E>(&concatenated[..], msg)
+}
+
+/// c = H*(R_x || M) with M padded to 256 bits, the challenge of `verify_for_snark`.
+fn snark_challenge<E: JubjubEngine>(r: &Point<E, Unknown>, msg: &[u8]) -> E::Fs {
+    let (r_g_x, _) = r.into_xy();
+    let mut r_g_x_bytes = [0u8; 32];
+    r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
+
+    let concatenated: Vec<u8> = r_g_x_bytes.iter().cloned().collect();
+
+    let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
+    msg_padded.resize(32, 0u8);
+
+    h_star_s::<E>(&concatenated[..], &msg_padded[..])
+}
+
+/// c = M padded to 256 bits, the challenge of `verify_for_raw_message`.
+fn raw_message_challenge<E: JubjubEngine>(msg: &[u8]) -> E::Fs {
+    // pad with zeroes to match representation length
+    let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
+    msg_padded.resize(32, 0u8);
+
+    E::Fs::to_uniform_32(msg_padded.as_ref())
+}
+
 #[derive(Copy, Clone)]
 pub struct SerializedSignature {
     rbar: [u8; 32],
@@ -89,14 +134,25 @@ impl<E: JubjubEngine> PrivateKey<E> {
         write_scalar::<E, W>(&self.0, writer)
     }
 
-    pub fn sign_raw_message<R: Rng>(
-        &self,
-        msg: &[u8],
-        rng: &mut R,
-        p_g: FixedGenerators,
-        params: &E::Params,
-        max_message_size: usize,
-    ) -> Signature<E> {
+    /// Derives the nonce of a signature from the private key, the public
+    /// key, the message mode and the message, in the style of Ed25519, so
+    /// no randomness is needed. The nonce differs whenever the challenge
+    /// may, since reusing it for two challenges reveals the key.
+    fn deterministic_nonce(&self, pk: &PublicKey<E>, msg: &[u8], mode: MessageMode) -> E::Fs {
+        let mut t = vec![];
+        write_scalar::<E, _>(&self.0, &mut t).expect("has serialized private key");
+        pk.write(&mut t).expect("has serialized public key");
+        t.push(match mode {
+            MessageMode::Hashed => 0,
+            MessageMode::ForSnark => 1,
+            MessageMode::Raw(_) => 2,
+        });
+
+        // r = H*(sk || Pk || mode || M)
+        hash_to_scalar::<E>(MATTER_EDDSA_NONCE_PERSONALIZATION, &t[..], msg)
+    }
+
+    fn random_nonce<R: Rng>(msg: &[u8], rng: &mut R) -> E::Fs {
         // T = (l_H + 128) bits of randomness
         // For H*, l_H = 512 bits
         let mut t = [0u8; 80];
@@ -105,141 +161,132 @@ impl<E: JubjubEngine> PrivateKey<E> {
         // Generate randomness using hash function based on some entropy and the message
         // Generation of randommess is completely off-chain, so we use BLAKE2b!
         // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+        h_star::<E>(&t[..], msg)
+    }
 
-        let pk = PublicKey::from_private(&self, p_g, params);
+    fn sign_with_nonce(
+        &self,
+        pk: &PublicKey<E>,
+        r: E::Fs,
+        msg: &[u8],
+        mode: MessageMode,
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
         let order_check = pk.0.mul(E::Fs::char(), params);
         assert!(order_check.eq(&Point::zero()));
 
         // R = r . P_G
-        let r_g = params.generator(p_g).mul(r, params);
-
-        // In a VERY LIMITED case of messages known to be unique due to application level
-        // and being less than the group order when interpreted as integer, one can sign
-        // the message directly without hashing
-
-        assert!(msg.len() <= max_message_size);
-        assert!(max_message_size * 8 <= E::Fs::CAPACITY as usize);
-        // we also pad message to max size
-
-        // pad with zeroes to match representation length
-        let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
-        for _ in 0..(32 - msg.len()) {
-            msg_padded.extend(&[0u8;1]);
-        }
+        let r_g: Point<E, Unknown> = params.generator(p_g).mul(r, params).into();
+
+        let c = match mode {
+            MessageMode::Hashed => challenge(&r_g, pk, msg),
+            MessageMode::ForSnark => {
+                // for hash function for a part that is INSIDE the zkSNARK it's
+                // BLAKE2s has 512 of input and 256 bits of output,
+                // so we use R_X || M as an input to hash only (without public key component),
+                // with a point coordinate and the message padded to 256 bits
+                assert!(msg.len() <= 32);
+                snark_challenge::<E>(&r_g, msg)
+            },
+            MessageMode::Raw(max_message_size) => {
+                // In a VERY LIMITED case of messages known to be unique due to application level
+                // and being less than the group order when interpreted as integer, one can sign
+                // the message directly without hashing
+                assert!(msg.len() <= max_message_size);
+                assert!(max_message_size * 8 <= E::Fs::CAPACITY as usize);
+                raw_message_challenge::<E>(msg)
+            }
+        };
 
-        // S = r + M . sk
-        let mut s = E::Fs::to_uniform_32(msg_padded.as_ref());
-        
+        // S = r + c . sk
+        let mut s = c;
         s.mul_assign(&self.0);
         s.add_assign(&r);
-    
-        let as_unknown = Point::from(r_g);
-        Signature { r: as_unknown, s: s }
+
+        Signature { r: r_g, s: s }
     }
 
-    pub fn sign_for_snark<R: Rng>(
+    /// Signs `msg` directly, see `sign_raw_message_randomized`. The nonce is
+    /// derived from the key and the message.
+    pub fn sign_raw_message(
         &self,
         msg: &[u8],
-        rng: &mut R,
         p_g: FixedGenerators,
         params: &E::Params,
+        max_message_size: usize,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
-
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
-
+        let mode = MessageMode::Raw(max_message_size);
         let pk = PublicKey::from_private(&self, p_g, params);
-        let order_check = pk.0.mul(E::Fs::char(), params);
-        assert!(order_check.eq(&Point::zero()));
-
-        // for hash function for a part that is INSIDE the zkSNARK it's 
-        // BLAKE2s has 512 of input and 256 bits of output,
-        // so we use R_X || M as an input to hash only (without public key component),
-        // with a point coordinate padded to 256 bits
-
-        // R = r . P_G
-        let r_g = params.generator(p_g).mul(r, params);
-
-        let (r_g_x, _) = r_g.into_xy();
-        let mut r_g_x_bytes = [0u8; 32];
-        r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
-
-        // we also pad message to 256 bits as LE
-
-        let concatenated: Vec<u8> = r_g_x_bytes.iter().cloned().collect();
-
-        let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
-        for _ in 0..(32-msg.len()) {
-            msg_padded.extend(&[0u8;1]);
-        }
-
-        // S = r + H*(R_X || M) . sk
-        let mut s = h_star_s::<E>(&concatenated[..], &msg_padded[..]);
-        s.mul_assign(&self.0);
-        s.add_assign(&r);
-    
-        let as_unknown = Point::from(r_g);
-        Signature { r: as_unknown, s: s }
+        let r = self.deterministic_nonce(&pk, msg, mode);
+        self.sign_with_nonce(&pk, r, msg, mode, p_g, params)
     }
 
-    pub fn sign<R: Rng>(
+    /// Signs a message of at most `max_message_size` bytes without hashing
+    /// it, with a nonce drawn from `rng`.
+    pub fn sign_raw_message_randomized<R: Rng>(
         &self,
         msg: &[u8],
         rng: &mut R,
         p_g: FixedGenerators,
         params: &E::Params,
+        max_message_size: usize,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
-
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
-
         let pk = PublicKey::from_private(&self, p_g, params);
-        let order_check = pk.0.mul(E::Fs::char(), params);
-        assert!(order_check.eq(&Point::zero()));
-
-        // for hash function for a part that is INSIDE the zkSNARK it's 
-        // BLAKE2s has 512 of input and 256 bits of output,
-        // so we use R_X || M as an input to hash only (without public key component), with point coordinates badded to 
-
-        // R = r . P_G
-        let r_g = params.generator(p_g).mul(r, params);
-
-        let (r_g_x, r_g_y) = r_g.into_xy();
-        let mut r_g_x_bytes = [0u8; 32];
-        r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
-
-        let mut r_g_y_bytes = [0u8; 32];
-        r_g_y.into_repr().write_le(& mut r_g_y_bytes[..]).expect("has serialized r_g_y");
+        let r = Self::random_nonce(msg, rng);
+        self.sign_with_nonce(&pk, r, msg, MessageMode::Raw(max_message_size), p_g, params)
+    }
 
-        let (pk_x, pk_y) = pk.0.into_xy();
-        let mut pk_x_bytes = [0u8; 32];
-        pk_x.into_repr().write_le(& mut pk_x_bytes[..]).expect("has serialized pk_x");
+    /// Signs `msg` for `verify_for_snark`. The nonce is derived from the
+    /// key and the message.
+    pub fn sign_for_snark(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let pk = PublicKey::from_private(&self, p_g, params);
+        let r = self.deterministic_nonce(&pk, msg, MessageMode::ForSnark);
+        self.sign_with_nonce(&pk, r, msg, MessageMode::ForSnark, p_g, params)
+    }
 
-        let mut pk_y_bytes = [0u8; 32];
-        pk_y.into_repr().write_le(& mut pk_y_bytes[..]).expect("has serialized pk_y");
+    /// Signs `msg` for `verify_for_snark` with a nonce drawn from `rng`.
+    pub fn sign_for_snark_randomized<R: Rng>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let pk = PublicKey::from_private(&self, p_g, params);
+        let r = Self::random_nonce(msg, rng);
+        self.sign_with_nonce(&pk, r, msg, MessageMode::ForSnark, p_g, params)
+    }
 
-        let concatenated: Vec<u8> = r_g_x_bytes.iter().chain(r_g_y_bytes.iter()).chain(pk_x_bytes.iter()).chain(pk_y_bytes.iter()).cloned().collect();
+    /// Signs `msg` for `verify`. The nonce is derived from the key and the
+    /// message.
+    pub fn sign(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let pk = PublicKey::from_private(&self, p_g, params);
+        let r = self.deterministic_nonce(&pk, msg, MessageMode::Hashed);
+        self.sign_with_nonce(&pk, r, msg, MessageMode::Hashed, p_g, params)
+    }
 
-        // S = r + H*(Rbar || Pk || M) . sk
-        let mut s = h_star::<E>(&concatenated[..], msg);
-        s.mul_assign(&self.0);
-        s.add_assign(&r);
-    
-        let as_unknown = Point::from(r_g);
-        Signature { r: as_unknown, s: s }
+    /// Signs `msg` for `verify` with a nonce drawn from `rng`.
+    pub fn sign_randomized<R: Rng>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let pk = PublicKey::from_private(&self, p_g, params);
+        let r = Self::random_nonce(msg, rng);
+        self.sign_with_nonce(&pk, r, msg, MessageMode::Hashed, p_g, params)
     }
 }
 
@@ -272,23 +319,7 @@ impl<E: JubjubEngine> PublicKey<E> {
         params: &E::Params,
     ) -> bool {
         // c = H*(Rbar || Pk || M)
-        let (r_g_x, r_g_y) = sig.r.into_xy();
-        let mut r_g_x_bytes = [0u8; 32];
-        r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
-
-        let mut r_g_y_bytes = [0u8; 32];
-        r_g_y.into_repr().write_le(& mut r_g_y_bytes[..]).expect("has serialized r_g_y");
-
-        let (pk_x, pk_y) = self.0.into_xy();
-        let mut pk_x_bytes = [0u8; 32];
-        pk_x.into_repr().write_le(& mut pk_x_bytes[..]).expect("has serialized pk_x");
-
-        let mut pk_y_bytes = [0u8; 32];
-        pk_y.into_repr().write_le(& mut pk_y_bytes[..]).expect("has serialized pk_y");
-
-        let concatenated: Vec<u8> = r_g_x_bytes.iter().chain(r_g_y_bytes.iter()).chain(pk_x_bytes.iter()).chain(pk_y_bytes.iter()).cloned().collect();
-
-        let c = h_star::<E>(&concatenated[..], msg);
+        let c = challenge(&sig.r, self, msg);
 
         // this one is for a simple sanity check. In application purposes the pk will always be in a right group 
         let order_check_pk = self.0.mul(E::Fs::char(), params);
@@ -331,11 +362,7 @@ impl<E: JubjubEngine> PublicKey<E> {
         // assert!(max_message_size * 8 <= E::Fs::Repr::len());
         // we also pad message to max size
 
-        // pad with zeroes to match representation length
-        let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
-        msg_padded.resize(32, 0u8);
-
-        let c = E::Fs::to_uniform_32(msg_padded.as_ref());
+        let c = raw_message_challenge::<E>(msg);
 
         // this one is for a simple sanity check. In application purposes the pk will always be in a right group 
         let order_check_pk = self.0.mul(E::Fs::char(), params);
@@ -373,16 +400,7 @@ impl<E: JubjubEngine> PublicKey<E> {
         params: &E::Params,
     ) -> bool {
         // c = H*(R_x || M)
-        let (r_g_x, _) = sig.r.into_xy();
-        let mut r_g_x_bytes = [0u8; 32];
-        r_g_x.into_repr().write_le(& mut r_g_x_bytes[..]).expect("has serialized r_g_x");
-
-        let concatenated: Vec<u8> = r_g_x_bytes.iter().cloned().collect();
-
-        let mut msg_padded : Vec<u8> = msg.iter().cloned().collect();
-        msg_padded.resize(32, 0u8);
-
-        let c = h_star_s::<E>(&concatenated[..], &msg_padded[..]);
+        let c = snark_challenge::<E>(&sig.r, msg);
 
         // this one is for a simple sanity check. In application purposes the pk will always be in a right group 
         let order_check_pk = self.0.mul(E::Fs::char(), params);
@@ -440,6 +458,128 @@ impl<E: JubjubEngine> PublicKey<E> {
     }
 }
 
+/// How the challenge of a signature is computed from its message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageMode {
+    /// As in `sign` and `verify`.
+    Hashed,
+    /// As in `sign_for_snark` and `verify_for_snark`.
+    ForSnark,
+    /// As in `sign_raw_message` and `verify_for_raw_message`, with the
+    /// given maximum message size.
+    Raw(usize),
+}
+
+pub struct BatchEntry<'a, E: JubjubEngine> {
+    pub vk: PublicKey<E>,
+    pub msg: &'a [u8],
+    pub sig: Signature<E>,
+    pub mode: MessageMode,
+}
+
+impl<'a, E: JubjubEngine> BatchEntry<'a, E> {
+    /// Returns the challenge of the signature, or `None` when it fails
+    /// the checks single verification does before its equation.
+    fn challenge(&self, params: &E::Params) -> Option<E::Fs> {
+        // R and the public key must be in the prime order subgroup for
+        // the equation to be checked without the cofactor
+        if self.vk.0.mul(E::Fs::char(), params) != Point::zero() {
+            return None;
+        }
+        if self.sig.r.mul(E::Fs::char(), params) != Point::zero() {
+            return None;
+        }
+
+        match self.mode {
+            MessageMode::Hashed => Some(challenge(&self.sig.r, &self.vk, self.msg)),
+            MessageMode::ForSnark => Some(snark_challenge::<E>(&self.sig.r, self.msg)),
+            MessageMode::Raw(max_message_size) => {
+                assert!(max_message_size * 8 <= E::Fs::CAPACITY as usize);
+                if self.msg.len() > max_message_size {
+                    return None;
+                }
+                Some(raw_message_challenge::<E>(self.msg))
+            }
+        }
+    }
+}
+
+/// Verifies a batch of signatures at once and returns the indices of the
+/// invalid ones, so the batch is valid when none is returned.
+///
+/// The verification equations are combined with random coefficients, so
+/// the generator is multiplied once for the whole batch. When the
+/// combination does not hold, the batch is split in halves until the
+/// invalid signatures are isolated.
+pub fn batch_verify<'a, E: JubjubEngine, R: Rng>(
+    rng: &mut R,
+    batch: &[BatchEntry<'a, E>],
+    p_g: FixedGenerators,
+    params: &E::Params,
+) -> Vec<usize>
+{
+    let mut invalid = vec![];
+    let mut entries = vec![];
+    for (i, entry) in batch.iter().enumerate() {
+        match entry.challenge(params) {
+            Some(c) => entries.push((i, c)),
+            None => invalid.push(i),
+        }
+    }
+
+    find_invalid(rng, batch, &entries, p_g, params, &mut invalid);
+    invalid.sort();
+
+    invalid
+}
+
+/// Pushes the indices of the invalid signatures among `entries`, pairs
+/// of an index in `batch` and the challenge of its signature.
+fn find_invalid<'a, E: JubjubEngine, R: Rng>(
+    rng: &mut R,
+    batch: &[BatchEntry<'a, E>],
+    entries: &[(usize, E::Fs)],
+    p_g: FixedGenerators,
+    params: &E::Params,
+    invalid: &mut Vec<usize>,
+)
+{
+    if entries.is_empty() {
+        return;
+    }
+
+    // 0 = sum z_i . (-S_i . P_G + R_i + c_i . vk_i) for random z_i
+    let mut acc = Point::<E, Unknown>::zero();
+    let mut s_acc = E::Fs::zero();
+    for &(i, c) in entries {
+        let entry = &batch[i];
+        let z = E::Fs::rand(rng);
+
+        let mut s = entry.sig.s;
+        s.mul_assign(&z);
+        s_acc.add_assign(&s);
+
+        let mut c = c;
+        c.mul_assign(&z);
+
+        acc = acc.add(&entry.sig.r.mul(z, params), params);
+        acc = acc.add(&entry.vk.0.mul(c, params), params);
+    }
+    acc = acc.add(&params.generator(p_g).mul(s_acc, params).negate().into(), params);
+
+    if acc.eq(&Point::zero()) {
+        return;
+    }
+
+    if entries.len() == 1 {
+        invalid.push(entries[0].0);
+    } else {
+        let (left, right) = entries.split_at(entries.len() / 2);
+        find_invalid(rng, batch, left, p_g, params, invalid);
+        find_invalid(rng, batch, right, p_g, params, invalid);
+    }
+}
+
 #[cfg(test)]
 mod baby_tests {
     use pairing::bn256::Bn256;
@@ -473,7 +613,7 @@ mod baby_tests {
         let vk = PublicKey::from_private(&sk, p_g, params);
 
         let msg = b"Foo bar";
-        let sig = sk.sign(msg, rng, p_g, params);
+        let sig = sk.sign(msg, p_g, params);
         assert!(vk.verify(msg, &sig, p_g, params));
 
         // in contrast to redjubjub, in this implementation out-of-group R is NOT allowed!
@@ -491,7 +631,7 @@ mod baby_tests {
     //         let sk = PrivateKey::<Bn256>(rng.gen());
     //         let vk = PublicKey::from_private(&sk, p_g, params);
     //         let msg = b"Foo bar";
-    //         let sig = sk.sign(msg, rng, p_g, params);
+    //         let sig = sk.sign(msg, p_g, params);
 
     //         let mut sk_bytes = [0u8; 32];
     //         let mut vk_bytes = [0u8; 32];
@@ -527,8 +667,8 @@ mod baby_tests {
             let msg1 = b"Foo bar";
             let msg2 = b"Spam eggs";
 
-            let sig1 = sk.sign(msg1, rng, p_g, params);
-            let sig2 = sk.sign(msg2, rng, p_g, params);
+            let sig1 = sk.sign(msg1, p_g, params);
+            let sig2 = sk.sign(msg2, p_g, params);
 
             assert!(vk.verify(msg1, &sig1, p_g, params));
             assert!(vk.verify(msg2, &sig2, p_g, params));
@@ -539,8 +679,8 @@ mod baby_tests {
             let rsk = sk.randomize(alpha);
             let rvk = vk.randomize(alpha, p_g, params);
 
-            let sig1 = rsk.sign(msg1, rng, p_g, params);
-            let sig2 = rsk.sign(msg2, rng, p_g, params);
+            let sig1 = rsk.sign_randomized(msg1, rng, p_g, params);
+            let sig2 = rsk.sign_randomized(msg2, rng, p_g, params);
 
             assert!(rvk.verify(msg1, &sig1, p_g, params));
             assert!(rvk.verify(msg2, &sig2, p_g, params));
@@ -562,8 +702,8 @@ mod baby_tests {
             let msg1 = b"Foo bar";
             let msg2 = b"Spam eggs";
 
-            let sig1 = sk.sign_for_snark(msg1, rng, p_g, params);
-            let sig2 = sk.sign_for_snark(msg2, rng, p_g, params);
+            let sig1 = sk.sign_for_snark(msg1, p_g, params);
+            let sig2 = sk.sign_for_snark(msg2, p_g, params);
 
             assert!(vk.verify_for_snark(msg1, &sig1, p_g, params));
             assert!(vk.verify_for_snark(msg2, &sig2, p_g, params));
@@ -574,8 +714,8 @@ mod baby_tests {
             let rsk = sk.randomize(alpha);
             let rvk = vk.randomize(alpha, p_g, params);
 
-            let sig1 = rsk.sign_for_snark(msg1, rng, p_g, params);
-            let sig2 = rsk.sign_for_snark(msg2, rng, p_g, params);
+            let sig1 = rsk.sign_for_snark_randomized(msg1, rng, p_g, params);
+            let sig2 = rsk.sign_for_snark_randomized(msg2, rng, p_g, params);
 
             assert!(rvk.verify_for_snark(msg1, &sig1, p_g, params));
             assert!(rvk.verify_for_snark(msg2, &sig2, p_g, params));
@@ -599,8 +739,8 @@ mod baby_tests {
 
             let max_message_size: usize = 16;
 
-            let sig1 = sk.sign_raw_message(msg1, rng, p_g, params, max_message_size);
-            let sig2 = sk.sign_raw_message(msg2, rng, p_g, params, max_message_size);
+            let sig1 = sk.sign_raw_message(msg1, p_g, params, max_message_size);
+            let sig2 = sk.sign_raw_message(msg2, p_g, params, max_message_size);
 
             assert!(vk.verify_for_raw_message(msg1, &sig1, p_g, params, max_message_size));
             assert!(vk.verify_for_raw_message(msg2, &sig2, p_g, params, max_message_size));
@@ -611,8 +751,8 @@ mod baby_tests {
             let rsk = sk.randomize(alpha);
             let rvk = vk.randomize(alpha, p_g, params);
 
-            let sig1 = rsk.sign_raw_message(msg1, rng, p_g, params, max_message_size);
-            let sig2 = rsk.sign_raw_message(msg2, rng, p_g, params, max_message_size);
+            let sig1 = rsk.sign_raw_message_randomized(msg1, rng, p_g, params, max_message_size);
+            let sig2 = rsk.sign_raw_message_randomized(msg2, rng, p_g, params, max_message_size);
 
             assert!(rvk.verify_for_raw_message(msg1, &sig1, p_g, params, max_message_size));
             assert!(rvk.verify_for_raw_message(msg2, &sig2, p_g, params, max_message_size));
@@ -632,4 +772,103 @@ mod baby_tests {
         let (x, y) = vk.0.into_xy();
         println!("Public generator x = {}, y = {}", x, y);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn batch_verify_reports_invalid_entries() {
+        let rng = &mut thread_rng();
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+
+        let msgs: Vec<Vec<u8>> = (0..12).map(|i| format!("withdrawal {}", i).into_bytes()).collect();
+        let mut batch = vec![];
+        for (i, msg) in msgs.iter().enumerate() {
+            let sk = PrivateKey::<Bn256>(rng.gen());
+            let vk = PublicKey::from_private(&sk, p_g, params);
+            let (sig, mode) = match i % 3 {
+                0 => (sk.sign(msg, p_g, params), MessageMode::Hashed),
+                1 => (sk.sign_for_snark(msg, p_g, params), MessageMode::ForSnark),
+                _ => (sk.sign_raw_message_randomized(msg, rng, p_g, params, 16), MessageMode::Raw(16)),
+            };
+            batch.push(BatchEntry { vk: vk, msg: &msg[..], sig: sig, mode: mode });
+        }
+
+        assert!(batch_verify(rng, &batch, p_g, params).is_empty());
+        assert!(batch_verify(rng, &batch[..0], p_g, params).is_empty());
+
+        // a signature of another message
+        batch[2].msg = &msgs[3][..];
+        // a signature checked in the wrong mode
+        batch[4].mode = MessageMode::Hashed;
+        // a public key out of the prime order subgroup
+        let p2 = loop {
+            let p = edwards::Point::<Bn256, _>::rand(rng, params).mul(Fs::char(), params);
+            if p != edwards::Point::zero() && p.double(params) == edwards::Point::zero() {
+                break p;
+            }
+        };
+        batch[7].vk = PublicKey(batch[7].vk.0.add(&p2, params));
+        // a raw message above its maximum size
+        batch[11].mode = MessageMode::Raw(4);
+
+        assert_eq!(batch_verify(rng, &batch, p_g, params), vec![2, 4, 7, 11]);
+        for (i, entry) in batch.iter().enumerate() {
+            let valid = match entry.mode {
+                MessageMode::Hashed => entry.vk.verify(entry.msg, &entry.sig, p_g, params),
+                MessageMode::ForSnark => entry.vk.verify_for_snark(entry.msg, &entry.sig, p_g, params),
+                MessageMode::Raw(max) => entry.msg.len() <= max
+                    && entry.vk.verify_for_raw_message(entry.msg, &entry.sig, p_g, params, max),
+            };
+            assert_eq!(valid, ![2, 4, 7, 11].contains(&i));
+        }
+    }
+
+    fn signature_bytes(sig: &Signature<Bn256>) -> Vec<u8> {
+        let mut bytes = vec![];
+        sig.r.write(&mut bytes).unwrap();
+        write_scalar::<Bn256, _>(&sig.s, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn deterministic_signature_vectors() {
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let sk = PrivateKey::<Bn256>(Fs::from_str("1234567890").unwrap());
+        let vk = PublicKey::from_private(&sk, p_g, params);
+        let msg = b"Foo bar";
+
+        let sig = sk.sign(msg, p_g, params);
+        let sig_for_snark = sk.sign_for_snark(msg, p_g, params);
+        let sig_raw = sk.sign_raw_message(msg, p_g, params, 16);
+        assert_eq!(
+            &signature_bytes(&sig)[..],
+            &hex!("d06ff01e0d7efa0ea08682c5e1faf1d38cf736f07af46f49455b1474fa22b508afc366a2aec5c3a47115db90a4d523bf52500aeadeddbe5ec1f1bb15e097bd00")[..]
+        );
+        assert_eq!(
+            &signature_bytes(&sig_for_snark)[..],
+            &hex!("a19e6aa26f5bbcc96b753c03e5d36de2560ac9c1a896ef1a45c53e4dd0231cacae0f5da749941189fee035266abe79c2cf800611be60f3d82e43ef1377baeb02")[..]
+        );
+        assert_eq!(
+            &signature_bytes(&sig_raw)[..],
+            &hex!("bf6c8ffd4f22a1e702c87b094e376e1e17e98301a9daf0d242647fa462211991f97c7e81aa08317bd67ce3bacc1eaf609c5333285a1a7018c613688776ffd005")[..]
+        );
+        assert!(vk.verify(msg, &sig, p_g, params));
+        assert!(vk.verify_for_snark(msg, &sig_for_snark, p_g, params));
+        assert!(vk.verify_for_raw_message(msg, &sig_raw, p_g, params, 16));
+
+        // signing again gives the same signature
+        assert_eq!(signature_bytes(&sk.sign(msg, p_g, params)), signature_bytes(&sig));
+
+        // the nonce depends on the message mode, so R is never reused
+        // for two different challenges
+        assert!(sig.r != sig_for_snark.r);
+        assert!(sig.r != sig_raw.r);
+        assert!(sig_for_snark.r != sig_raw.r);
+
+        // randomized signing is still available
+        let rng = &mut thread_rng();
+        let sig_randomized = sk.sign_randomized(msg, rng, p_g, params);
+        assert!(sig_randomized.r != sig.r);
+        assert!(vk.verify(msg, &sig_randomized, p_g, params));
+    }
+}
@@ -0,0 +1,243 @@
+//! This is an implementation of EdDSA over alt-babyjubjub whose
+//! challenge is a Poseidon hash, compatible with circomlib's
+//! `signPoseidon`, `verifyPoseidon` and `EdDSAPoseidonVerifier`.
+//!
+//! circomlib works with the `a = 168700` form of Baby Jubjub, so points
+//! are hashed and exchanged in those coordinates, see `to_circomlib_xy`.
+//! Keys and nonces are derived with BLAKE-512 like circomlib does.
+
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use pairing::bn256::{Bn256, Fr};
+
+use alt_babyjubjub::{
+    AltJubjubBn256,
+    JubjubParams,
+    ToUniform,
+    Unknown,
+    edwards::Point,
+    fs::{Fs, FsRepr}
+};
+use blake512::blake512;
+use poseidon::{PoseidonParams, poseidon_hash};
+
+/// x coordinate of circomlib's `Base8` generator, in circomlib's coordinates.
+pub const BASE8_X: &'static str = "5299619240641551281634865583518297030282874472190772894086521144482721001553";
+/// y coordinate of circomlib's `Base8` generator.
+pub const BASE8_Y: &'static str = "16950150798460657717958625567821834550301663161624707787222815936182638968203";
+
+/// Private key, the seed circomlib expands with BLAKE-512.
+pub struct PrivateKey(pub [u8; 32]);
+
+#[derive(Clone)]
+pub struct PublicKey(pub Point<Bn256, Unknown>);
+
+#[derive(Clone)]
+pub struct Signature {
+    pub r: Point<Bn256, Unknown>,
+    pub s: Fs,
+}
+
+/// Returns the coordinates of a point in circomlib's form of the curve.
+pub fn to_circomlib_xy(p: &Point<Bn256, Unknown>, params: &AltJubjubBn256) -> (Fr, Fr) {
+    let (mut x, y) = p.into_xy();
+    x.mul_assign(&params.scale().inverse().expect("scale is nonzero"));
+    (x, y)
+}
+
+/// Returns the point of coordinates `(x, y)` in circomlib's form of the
+/// curve, if it is on the curve.
+pub fn from_circomlib_xy(x: Fr, y: Fr, params: &AltJubjubBn256) -> Option<Point<Bn256, Unknown>> {
+    let mut x = x;
+    x.mul_assign(params.scale());
+    Point::from_xy(x, y, params)
+}
+
+/// Returns circomlib's `Base8` generator of the prime order subgroup.
+pub fn base8(params: &AltJubjubBn256) -> Point<Bn256, Unknown> {
+    from_circomlib_xy(
+        Fr::from_str(BASE8_X).unwrap(),
+        Fr::from_str(BASE8_Y).unwrap(),
+        params
+    ).expect("Base8 is on the curve")
+}
+
+/// Returns the challenge `Poseidon(R_x, R_y, A_x, A_y, M)` over circomlib's coordinates.
+pub fn challenge(
+    r: &Point<Bn256, Unknown>,
+    pk: &PublicKey,
+    msg: Fr,
+    params: &AltJubjubBn256,
+    poseidon_params: &PoseidonParams<Bn256>
+) -> Fr {
+    assert_eq!(poseidon_params.num_inputs(), 5);
+
+    let (r_x, r_y) = to_circomlib_xy(r, params);
+    let (a_x, a_y) = to_circomlib_xy(&pk.0, params);
+    poseidon_hash(poseidon_params, &[r_x, r_y, a_x, a_y, msg])
+}
+
+fn le_bytes(x: Fr) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.into_repr().write_le(&mut bytes).expect("has serialized field element");
+    bytes
+}
+
+impl PrivateKey {
+    /// Expands the seed into the pruned secret scalar, a multiple of the
+    /// cofactor, and the prefix of the nonce hash.
+    fn expand(&self) -> (FsRepr, Vec<u8>) {
+        let mut h = blake512(&self.0[..]);
+        h[0] &= 0xf8;
+        h[31] &= 0x7f;
+        h[31] |= 0x40;
+
+        let mut s = FsRepr::default();
+        s.read_le(&h[..32]).expect("has read secret scalar");
+        (s, h[32..].to_vec())
+    }
+
+    pub fn sign(
+        &self,
+        msg: Fr,
+        params: &AltJubjubBn256,
+        poseidon_params: &PoseidonParams<Bn256>
+    ) -> Signature {
+        let (s_repr, prefix) = self.expand();
+        let pk = PublicKey::from_private(self, params);
+
+        // r = H(prefix || M) mod l
+        let mut t = prefix;
+        t.extend(le_bytes(msg));
+        let r = Fs::to_uniform(&blake512(&t)[..]);
+
+        // R = r . Base8
+        let r_g = base8(params).mul(r, params);
+
+        // S = r + H(R || A || M) . s mod l
+        let h = challenge(&r_g, &pk, msg, params, poseidon_params);
+        let mut s_bytes = vec![];
+        s_repr.write_le(&mut s_bytes).expect("has serialized secret scalar");
+        let mut s = Fs::to_uniform_32(&le_bytes(h)[..]);
+        s.mul_assign(&Fs::to_uniform_32(&s_bytes[..]));
+        s.add_assign(&r);
+
+        Signature { r: r_g, s: s }
+    }
+}
+
+impl PublicKey {
+    pub fn from_private(privkey: &PrivateKey, params: &AltJubjubBn256) -> Self {
+        let (mut s, _) = privkey.expand();
+        s.shr(3);
+        PublicKey(base8(params).mul(s, params))
+    }
+
+    pub fn verify(
+        &self,
+        msg: Fr,
+        sig: &Signature,
+        params: &AltJubjubBn256,
+        poseidon_params: &PoseidonParams<Bn256>
+    ) -> bool {
+        // a key of small order would accept any signature
+        let pk_8 = self.0.double(params).double(params).double(params);
+        if pk_8 == Point::zero() {
+            return false;
+        }
+
+        // 0 = -S . Base8 + R + H(R || A || M) . (8 . A), the challenge is
+        // not reduced like in circomlib
+        let h = challenge(&sig.r, self, msg, params, poseidon_params);
+        pk_8.mul(FsRepr(h.into_repr().0), params).add(&sig.r, params).add(
+            &base8(params).mul(sig.s, params).negate(),
+            params
+        ) == Point::zero()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ff::PrimeField;
+    use pairing::bn256::Fr;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use poseidon::PoseidonParams;
+    use super::*;
+
+    /// The key of circomlib's EdDSA tests.
+    fn circomlib_key() -> PrivateKey {
+        PrivateKey(hex!("0001020304050607080900010203040506070809000102030405060708090001"))
+    }
+
+    #[test]
+    fn test_circomlib_vectors() {
+        let params = &AltJubjubBn256::new();
+        let poseidon_params = &PoseidonParams::new(5);
+        let sk = circomlib_key();
+        let pk = PublicKey::from_private(&sk, params);
+
+        assert_eq!(
+            to_circomlib_xy(&pk.0, params),
+            (
+                Fr::from_str("13277427435165878497778222415993513565335242147425444199013288855685581939618").unwrap(),
+                Fr::from_str("13622229784656158136036771217484571176836296686641868549125388198837476602820").unwrap()
+            )
+        );
+
+        // signing is deterministic, this pins the signature of circomlib's message
+        let msg = Fr::from_str("1234").unwrap();
+        let sig = sk.sign(msg, params, poseidon_params);
+        assert_eq!(
+            to_circomlib_xy(&sig.r, params),
+            (
+                Fr::from_str("11220723668893468001994760120794694848178115379170651044669708829805665054484").unwrap(),
+                Fr::from_str("2367470421002446880004241260470975644531657398480773647535134774673409612366").unwrap()
+            )
+        );
+        assert_eq!(
+            sig.s,
+            Fs::from_str("2010143491207902444122668013146870263468969134090678646686512037244361350365").unwrap()
+        );
+        assert!(pk.verify(msg, &sig, params, poseidon_params));
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let poseidon_params = &PoseidonParams::new(5);
+
+        for _ in 0..20 {
+            let sk = PrivateKey(rng.gen());
+            let pk = PublicKey::from_private(&sk, params);
+            let msg1: Fr = rng.gen();
+            let msg2: Fr = rng.gen();
+            let sig1 = sk.sign(msg1, params, poseidon_params);
+            let sig2 = sk.sign(msg2, params, poseidon_params);
+
+            assert!(pk.verify(msg1, &sig1, params, poseidon_params));
+            assert!(pk.verify(msg2, &sig2, params, poseidon_params));
+            assert!(!pk.verify(msg1, &sig2, params, poseidon_params));
+            assert!(!pk.verify(msg2, &sig1, params, poseidon_params));
+
+            let other = PublicKey::from_private(&PrivateKey(rng.gen()), params);
+            assert!(!other.verify(msg1, &sig1, params, poseidon_params));
+        }
+    }
+
+    #[test]
+    fn test_circomlib_coordinates() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+
+        // Base8 has the prime subgroup order
+        assert!(base8(params).mul(Fs::char(), params) == Point::zero());
+
+        for _ in 0..20 {
+            let p = Point::<Bn256, Unknown>::rand(&mut rng, params);
+            let (x, y) = to_circomlib_xy(&p, params);
+            assert!(from_circomlib_xy(x, y, params).unwrap() == p);
+        }
+    }
+}
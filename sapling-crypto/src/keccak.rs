@@ -0,0 +1,118 @@
+//! This is an implementation of Keccak-256 as used by Ethereum, which
+//! differs from SHA3-256 only in its padding.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+/// Round constants of Keccak-f[1600], added to the first lane by ι.
+pub const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+];
+
+/// Rotation offsets of ρ, indexed by `x + 5 * y`.
+pub const ROTATION_OFFSETS: [u32; 25] = [
+     0,  1, 62, 28, 27,
+    36, 44,  6, 55, 20,
+     3, 10, 43, 25, 39,
+    41, 45, 15, 21,  8,
+    18,  2, 61, 56, 14
+];
+
+/// Rate of Keccak-256 in bytes.
+pub const KECCAK256_RATE: usize = 136;
+
+/// Applies the Keccak-f[1600] permutation to the state, whose lanes
+/// are indexed by `x + 5 * y`.
+pub fn keccak_f1600(state: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // θ
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // ρ and π
+        let mut b = [0u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = state[x + 5 * y].rotate_left(ROTATION_OFFSETS[x + 5 * y]);
+            }
+        }
+
+        // χ
+        for x in 0..5 {
+            for y in 0..5 {
+                state[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // ι
+        state[0] ^= *rc;
+    }
+}
+
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut padded = input.to_vec();
+    padded.push(0x01);
+    while padded.len() % KECCAK256_RATE != 0 {
+        padded.push(0x00);
+    }
+    *padded.last_mut().unwrap() |= 0x80;
+
+    let mut state = [0u64; 25];
+    for block in padded.chunks(KECCAK256_RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= LittleEndian::read_u64(bytes);
+        }
+        keccak_f1600(&mut state);
+    }
+
+    let mut output = [0u8; 32];
+    for (lane, bytes) in state.iter().zip(output.chunks_mut(8)) {
+        LittleEndian::write_u64(bytes, *lane);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use crypto::sha3::Sha3;
+    use crypto::digest::Digest;
+
+    #[test]
+    fn test_keccak256_empty() {
+        assert_eq!(
+            keccak256(&[]),
+            hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+    }
+
+    #[test]
+    fn test_keccak256_against_reference() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for input_len in 0..(3 * KECCAK256_RATE + 1) {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut h = Sha3::keccak256();
+            h.input(&data);
+            let mut expected = [0u8; 32];
+            h.result(&mut expected);
+
+            assert_eq!(keccak256(&data), expected);
+        }
+    }
+}
@@ -25,6 +25,7 @@ pub mod group_hash;
 pub mod circuit;
 pub mod baby_pedersen_hash;
 pub mod pedersen_hash;
+pub mod baby_primitives;
 pub mod primitives;
 pub mod constants;
 pub mod redbabyjubjub;
@@ -32,6 +33,11 @@ pub mod redjubjub;
 pub mod baby_util;
 pub mod util;
 pub mod eddsa;
+pub mod eddsa_poseidon;
+pub mod poseidon;
+pub mod mimc;
+pub mod keccak;
+pub mod blake512;
 
 extern crate serde;
 #[macro_use]
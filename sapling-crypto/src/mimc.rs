@@ -0,0 +1,210 @@
+//! This is an implementation of the MiMC-sponge hash function: a sponge
+//! over the MiMC-Feistel permutation with the x^5 S-box, structured
+//! like the `MiMCSponge` template of circomlib.
+//!
+//! The round constants are derived from a BLAKE2s hash chain rather
+//! than from circomlib's seed, so hashes are not interchangeable with
+//! circomlib's.
+
+use pairing::{Engine};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use blake2_rfc::blake2s::Blake2s;
+
+use ::constants::{MIMC_SPONGE_CONSTANTS_PERSONALIZATION};
+
+/// Number of Feistel rounds of the permutation; enough for the x^5
+/// S-box over a 254 bit field.
+pub const MIMC_SPONGE_ROUNDS: usize = 220;
+
+pub struct MiMCSpongeParams<E: Engine> {
+    round_constants: Vec<E::Fr>
+}
+
+impl<E: Engine> MiMCSpongeParams<E> {
+    pub fn new() -> Self {
+        Self::with_rounds(MIMC_SPONGE_ROUNDS)
+    }
+
+    /// Derives the constants for a permutation of `rounds` rounds. The
+    /// constants of the first and the last round are zero.
+    pub fn with_rounds(rounds: usize) -> Self {
+        assert!(rounds >= 2);
+
+        let mut round_constants = vec![E::Fr::zero()];
+
+        let mut h = Blake2s::with_params(32, &[], &[], MIMC_SPONGE_CONSTANTS_PERSONALIZATION);
+        h.update(b"mimcsponge");
+        let mut digest = h.finalize();
+
+        while round_constants.len() < rounds - 1 {
+            let mut h = Blake2s::with_params(32, &[], &[], MIMC_SPONGE_CONSTANTS_PERSONALIZATION);
+            h.update(digest.as_ref());
+            digest = h.finalize();
+
+            // Keep the low bits of the digest, and try again with the next
+            // digest of the chain if they are not a field element
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_le(digest.as_ref()).unwrap();
+            let excess = repr.as_ref().len() as u32 * 64 - E::Fr::NUM_BITS;
+            repr.as_mut().last_mut().map(|limb| *limb &= !0u64 >> excess);
+
+            if let Ok(c) = E::Fr::from_repr(repr) {
+                round_constants.push(c);
+            }
+        }
+
+        round_constants.push(E::Fr::zero());
+
+        MiMCSpongeParams {
+            round_constants: round_constants
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+
+    pub fn round_constants(&self) -> &[E::Fr] {
+        &self.round_constants
+    }
+}
+
+/// Applies the MiMC-Feistel permutation keyed with `key` to (`xl`, `xr`).
+/// Every round but the last computes
+/// `xl, xr := xr + (xl + key + c_i)^5, xl`; the last round doesn't swap
+/// the halves.
+pub fn mimc_feistel<E: Engine>(
+    params: &MiMCSpongeParams<E>,
+    mut xl: E::Fr,
+    mut xr: E::Fr,
+    key: &E::Fr
+) -> (E::Fr, E::Fr)
+{
+    let rounds = params.rounds();
+
+    for (i, c) in params.round_constants().iter().enumerate() {
+        let mut t = xl;
+        t.add_assign(key);
+        t.add_assign(c);
+
+        let mut t5 = t;
+        t5.square();
+        t5.square();
+        t5.mul_assign(&t);
+        t5.add_assign(&xr);
+
+        if i < rounds - 1 {
+            xr = xl;
+            xl = t5;
+        } else {
+            xr = t5;
+        }
+    }
+
+    (xl, xr)
+}
+
+/// Absorbs `inputs` into the left half of the state, permuting after
+/// each of them, and squeezes `num_outputs` elements out of it.
+pub fn mimc_sponge<E: Engine>(
+    params: &MiMCSpongeParams<E>,
+    inputs: &[E::Fr],
+    key: &E::Fr,
+    num_outputs: usize
+) -> Vec<E::Fr>
+{
+    let mut xl = E::Fr::zero();
+    let mut xr = E::Fr::zero();
+
+    for input in inputs {
+        xl.add_assign(input);
+        let (l, r) = mimc_feistel(params, xl, xr, key);
+        xl = l;
+        xr = r;
+    }
+
+    let mut outputs = vec![xl];
+    while outputs.len() < num_outputs {
+        let (l, r) = mimc_feistel(params, xl, xr, key);
+        xl = l;
+        xr = r;
+        outputs.push(xl);
+    }
+    outputs.truncate(num_outputs);
+
+    outputs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_mimc_sponge_constants() {
+        let params = MiMCSpongeParams::<Bn256>::new();
+        let constants = params.round_constants();
+
+        assert_eq!(params.rounds(), 220);
+        assert!(constants[0].is_zero());
+        assert!(constants[219].is_zero());
+        assert!(constants[1..219].iter().all(|c| !c.is_zero()));
+        assert!(constants[1] != constants[2]);
+
+        // The constants are deterministic
+        assert!(MiMCSpongeParams::<Bn256>::new().round_constants() == constants);
+    }
+
+    #[test]
+    fn test_mimc_sponge() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = MiMCSpongeParams::<Bn256>::new();
+
+        let a: Fr = rng.gen();
+        let b: Fr = rng.gen();
+        let key = Fr::zero();
+
+        let out = mimc_sponge(&params, &[a, b], &key, 2);
+        assert_eq!(out.len(), 2);
+        assert!(out[0] != out[1]);
+
+        // A single output is a prefix of more outputs
+        assert_eq!(mimc_sponge(&params, &[a, b], &key, 1)[..], out[..1]);
+
+        // The order of the inputs and the key matter
+        assert!(mimc_sponge(&params, &[b, a], &key, 1)[0] != out[0]);
+        assert!(mimc_sponge(&params, &[a, b], &Fr::one(), 1)[0] != out[0]);
+
+        // Absorbing is adding to the left half and permuting
+        let (xl, xr) = mimc_feistel(&params, a, Fr::zero(), &key);
+        let mut l = xl;
+        l.add_assign(&b);
+        assert_eq!(mimc_feistel(&params, l, xr, &key).0, out[0]);
+    }
+
+    #[test]
+    fn test_mimc_sponge_vectors() {
+        let params = MiMCSpongeParams::<Bn256>::new();
+
+        // These pin the constants derived with the "MiMC_Spg" personalization
+        assert_eq!(
+            params.round_constants()[1],
+            Fr::from_str("7933661155532264068104107185869816059648909064320556534759439805865013382063").unwrap()
+        );
+        assert_eq!(
+            params.round_constants()[218],
+            Fr::from_str("13641221115771600039085562923767382278746247734387021209403777204651908065900").unwrap()
+        );
+
+        let inputs = [Fr::from_str("1").unwrap(), Fr::from_str("2").unwrap()];
+        let key = Fr::from_str("3").unwrap();
+        assert_eq!(
+            mimc_sponge(&params, &inputs, &key, 2),
+            vec![
+                Fr::from_str("19409956100751005455347415409363035086671136487007611500396612831725872564866").unwrap(),
+                Fr::from_str("10913281018962515006253917430560591967394682882756766498820716411830155541327").unwrap()
+            ]
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! This is an implementation of the Poseidon hash function, compatible
+//! with the `Poseidon(nInputs)` template of circomlib and with
+//! `poseidon` of circomlibjs.
+//!
+//! The round constants and the MDS matrix are not tabulated; they are
+//! derived the way the reference implementation derives them, from the
+//! Grain LFSR seeded with the parameters of the permutation.
+
+use pairing::{Engine};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+
+/// Number of full rounds, half of them before the partial rounds
+/// and half of them after.
+const FULL_ROUNDS: usize = 8;
+
+/// Number of partial rounds for each width of the state, starting
+/// with a width of 2 (one input).
+const PARTIAL_ROUNDS: [usize; 16] = [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68];
+
+/// Number of bits the Grain LFSR discards after being seeded.
+const GRAIN_WARMUP_BITS: usize = 160;
+
+pub struct PoseidonParams<E: Engine> {
+    t: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<E::Fr>,
+    mds_matrix: Vec<Vec<E::Fr>>
+}
+
+impl<E: Engine> PoseidonParams<E> {
+    /// Parameters for hashing `num_inputs` field elements, which
+    /// must be between 1 and 16.
+    pub fn new(num_inputs: usize) -> Self {
+        assert!(num_inputs >= 1 && num_inputs <= PARTIAL_ROUNDS.len(),
+                "unsupported number of Poseidon inputs");
+
+        let t = num_inputs + 1;
+        let full_rounds = FULL_ROUNDS;
+        let partial_rounds = PARTIAL_ROUNDS[t - 2];
+
+        let mut grain = GrainLfsr::new(E::Fr::NUM_BITS, t, full_rounds, partial_rounds);
+
+        // Round constants are rejection sampled
+        let mut round_constants = Vec::with_capacity((full_rounds + partial_rounds) * t);
+        while round_constants.len() < (full_rounds + partial_rounds) * t {
+            if let Ok(c) = E::Fr::from_repr(grain.next_repr::<E::Fr>(E::Fr::NUM_BITS)) {
+                round_constants.push(c);
+            }
+        }
+
+        // The MDS matrix is a Cauchy matrix 1 / (x_i + y_j) over 2t
+        // distinct elements, which are reduced rather than rejected
+        let mds_matrix = loop {
+            let mut elements: Vec<E::Fr> = vec![];
+            while elements.len() < 2 * t {
+                let mut repr = grain.next_repr::<E::Fr>(E::Fr::NUM_BITS);
+                while repr >= E::Fr::char() {
+                    repr.sub_noborrow(&E::Fr::char());
+                }
+                elements.push(E::Fr::from_repr(repr).unwrap());
+            }
+
+            let distinct = (0..2 * t).all(|i| (0..i).all(|j| elements[i] != elements[j]));
+            if !distinct {
+                continue;
+            }
+
+            let (xs, ys) = elements.split_at(t);
+            let matrix: Option<Vec<Vec<E::Fr>>> = xs.iter().map(|x| {
+                ys.iter().map(|y| {
+                    let mut sum = *x;
+                    sum.add_assign(y);
+                    sum.inverse()
+                }).collect()
+            }).collect();
+
+            if let Some(matrix) = matrix {
+                break matrix;
+            }
+        };
+
+        PoseidonParams {
+            t: t,
+            full_rounds: full_rounds,
+            partial_rounds: partial_rounds,
+            round_constants: round_constants,
+            mds_matrix: mds_matrix
+        }
+    }
+
+    /// Width of the state, one more than the number of inputs.
+    pub fn t(&self) -> usize {
+        self.t
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.t - 1
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// Returns true if round `round` applies the S-box to the whole
+    /// state rather than to its first element only.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        round < self.full_rounds / 2 || round >= self.full_rounds / 2 + self.partial_rounds
+    }
+
+    /// Constants added to the state at the start of round `round`.
+    pub fn round_constants(&self, round: usize) -> &[E::Fr] {
+        &self.round_constants[round * self.t..(round + 1) * self.t]
+    }
+
+    pub fn mds_matrix(&self) -> &[Vec<E::Fr>] {
+        &self.mds_matrix
+    }
+}
+
+/// The Grain LFSR in self-shrinking mode, used by the reference
+/// implementation of Poseidon to generate its constants.
+struct GrainLfsr {
+    state: [bool; 80],
+    head: usize
+}
+
+impl GrainLfsr {
+    fn new(field_size: u32, t: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut state = [false; 80];
+        let mut i = 0;
+
+        {
+            let mut append = |value: u64, width: usize| {
+                for bit in (0..width).rev() {
+                    state[i] = (value >> bit) & 1 == 1;
+                    i += 1;
+                }
+            };
+
+            // Prime field, x^5 S-box
+            append(1, 2);
+            append(0, 4);
+            append(field_size as u64, 12);
+            append(t as u64, 12);
+            append(full_rounds as u64, 10);
+            append(partial_rounds as u64, 10);
+            append((1 << 30) - 1, 30);
+        }
+
+        let mut grain = GrainLfsr {
+            state: state,
+            head: 0
+        };
+
+        for _ in 0..GRAIN_WARMUP_BITS {
+            grain.update();
+        }
+
+        grain
+    }
+
+    fn update(&mut self) -> bool {
+        let bit = |offset: usize| self.state[(self.head + offset) % 80];
+        let new_bit = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+
+        self.state[self.head] = new_bit;
+        self.head = (self.head + 1) % 80;
+
+        new_bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.update();
+            let bit = self.update();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Reads `num_bits` bits as a big-endian integer.
+    fn next_repr<F: PrimeField>(&mut self, num_bits: u32) -> F::Repr {
+        let mut repr = F::Repr::default();
+        for _ in 0..num_bits {
+            repr.mul2();
+            if self.next_bit() {
+                repr.add_nocarry(&F::Repr::from(1));
+            }
+        }
+
+        repr
+    }
+}
+
+/// Applies the Poseidon permutation to `state`.
+pub fn poseidon_permutation<E: Engine>(
+    params: &PoseidonParams<E>,
+    state: &mut [E::Fr]
+)
+{
+    assert_eq!(state.len(), params.t());
+
+    let sbox = |x: &mut E::Fr| {
+        let mut x4 = *x;
+        x4.square();
+        x4.square();
+        x.mul_assign(&x4);
+    };
+
+    for round in 0..(params.full_rounds() + params.partial_rounds()) {
+        for (s, c) in state.iter_mut().zip(params.round_constants(round)) {
+            s.add_assign(c);
+        }
+
+        if params.is_full_round(round) {
+            for s in state.iter_mut() {
+                sbox(s);
+            }
+        } else {
+            sbox(&mut state[0]);
+        }
+
+        let mixed: Vec<E::Fr> = params.mds_matrix().iter().map(|row| {
+            let mut acc = E::Fr::zero();
+            for (m, s) in row.iter().zip(state.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(s);
+                acc.add_assign(&tmp);
+            }
+            acc
+        }).collect();
+        state.copy_from_slice(&mixed);
+    }
+}
+
+/// Hashes `inputs`, whose number must match the parameters.
+pub fn poseidon_hash<E: Engine>(
+    params: &PoseidonParams<E>,
+    inputs: &[E::Fr]
+) -> E::Fr
+{
+    assert_eq!(inputs.len(), params.num_inputs());
+
+    let mut state = vec![E::Fr::zero()];
+    state.extend_from_slice(inputs);
+
+    poseidon_permutation(params, &mut state);
+
+    state[0]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_poseidon_constants() {
+        // First round constants of circomlib for the widths 2 and 3
+        let params = PoseidonParams::<Bn256>::new(1);
+        assert_eq!(params.round_constants(0)[0], Fr::from_str(
+            "4417881134626180770308697923359573201005643519861877412381846989312604493735"
+        ).unwrap());
+
+        let params = PoseidonParams::<Bn256>::new(2);
+        assert_eq!(params.partial_rounds(), 57);
+        assert_eq!(params.round_constants(0)[0], Fr::from_str(
+            "6745197990210204598374042828761989596302876299545964402857411729872131034734"
+        ).unwrap());
+    }
+
+    #[test]
+    fn test_poseidon_hash_circomlib_vectors() {
+        let to_fr = |v: &[u64]| -> Vec<Fr> {
+            v.iter().map(|x| Fr::from_str(&x.to_string()).unwrap()).collect()
+        };
+
+        let vectors: [(&[u64], &str); 3] = [
+            (&[1], "18586133768512220936620570745912940619677854269274689475585506675881198879027"),
+            (&[1, 2], "7853200120776062878684798364095072458815029376092732009249414926327459813530"),
+            (&[1, 2, 3, 4], "18821383157269793795438455681495246036402687001665670618754263018637548127333"),
+        ];
+
+        for &(inputs, expected) in vectors.iter() {
+            let params = PoseidonParams::<Bn256>::new(inputs.len());
+            assert_eq!(poseidon_hash(&params, &to_fr(inputs)), Fr::from_str(expected).unwrap());
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use blake2_rfc::blake2b::Blake2b;
 use blake2_rfc::blake2s::Blake2s;
+use ff::{PrimeField, PrimeFieldRepr};
 
 use jubjub::{JubjubEngine, ToUniform};
 
@@ -17,4 +18,45 @@ pub fn hash_to_scalar_s<E: JubjubEngine>(persona: &[u8], a: &[u8], b: &[u8]) ->
     hasher.update(b);
     let ret = hasher.finalize();
     E::Fs::to_uniform_32(ret.as_ref())
-}
\ No newline at end of file
+}
+
+/// BLAKE2b of `input` with an `output_len` byte digest and a
+/// personalization of at most 16 bytes. This is the native counterpart
+/// of the `circuit::blake2b` gadget.
+pub fn blake2b_hash(output_len: usize, personalization: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut hasher = Blake2b::with_params(output_len, &[], &[], personalization);
+    hasher.update(input);
+    hasher.finalize().as_bytes().to_vec()
+}
+
+/// BLAKE2s of `input` with a 32 byte digest and a personalization of
+/// at most 8 bytes. This is the native counterpart of the
+/// `circuit::blake2s` gadget.
+pub fn blake2s_hash(personalization: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut hasher = Blake2s::with_params(32, &[], &[], personalization);
+    hasher.update(input);
+    hasher.finalize().as_bytes().to_vec()
+}
+
+/// BLAKE2b-256 without personalization, which is what Substrate's
+/// `blake2_256` computes.
+pub fn blake2_256(input: &[u8]) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&blake2b_hash(32, &[], input));
+    output
+}
+
+/// Little-endian bytes of the canonical representation of `x`.
+pub fn write_le_bytes<F: PrimeField>(x: F) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.into_repr().write_le(&mut bytes).expect("has serialized field element");
+    bytes
+}
+
+/// Reinterprets a scalar as an element of the base field, which is how
+/// the EdDSA gadgets witness the signature scalar `s`.
+pub fn scalar_to_base<E: JubjubEngine>(s: E::Fs) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_le(&write_le_bytes(s)[..]).expect("scalar fits in the base field representation");
+    E::Fr::from_repr(repr).expect("scalar field is smaller than the base field")
+}
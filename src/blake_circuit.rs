@@ -1,11 +1,9 @@
 use sapling_crypto::circuit::blake2s::blake2s;
 use rand::{ChaChaRng, SeedableRng, Rng};
 use bellman::groth16::{Proof, Parameters, verify_proof, create_random_proof, prepare_verifying_key, generate_random_parameters};
-use num_bigint::BigInt;
-use num_traits::Num;
 use std::error::Error;
 
-use pairing::{bn256::{Fr, Bn256}};
+use pairing::{bn256::{Bn256}};
 
 use wasm_bindgen::prelude::*;
 
@@ -19,17 +17,15 @@ use bellman::{
     ConstraintSystem,
 };
 
-use ff::{PrimeField};
 use sapling_crypto::{
     circuit::{
         multipack,
-        num::{AllocatedNum},
+        merkle,
         boolean::{Boolean, AllocatedBit},
     }
 };
 
-pub const SUBSTRATE_BLAKE2_PERSONALIZATION: &'static [u8; 8]
-          = b"12345678";
+pub use blake_merkle_tree::SUBSTRATE_BLAKE2_PERSONALIZATION;
 
 /// Circuit for proving knowledge of preimage of leaf in merkle tree
 pub struct BlakeTreeCircuit {
@@ -53,16 +49,11 @@ impl<E: Engine> Circuit<E> for BlakeTreeCircuit {
         ).unwrap();
         multipack::pack_into_inputs(cs.namespace(|| "nullifier pack"), &nullifier)?;
 
-        let nullifier_field_pt: E::Fr = multipack::compute_multipacking::<E>(&booleans_to_bools(nullifier.clone()))[0];
-        let nullifier_alloc: AllocatedNum<E> = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || Ok(nullifier_field_pt))?;
-        nullifier_alloc.inputize(cs.namespace(|| "public input nullifier"))?;
-
         // secret is the right side of the preimage
         let secret: Vec<Boolean> = witness_u256(
             cs.namespace(|| "secret"),
             self.secret.as_ref().map(|v| &v[..])
         ).unwrap();
-        multipack::pack_into_inputs(cs.namespace(|| "secret pack"), &secret)?;
 
         // construct preimage using [nullifier_bits|secret_bits] concatenation
         let mut preimage: Vec<Boolean> = vec![];
@@ -74,56 +65,40 @@ impl<E: Engine> Circuit<E> for BlakeTreeCircuit {
         preimage.resize(512, Boolean::Constant(false));
 
         // compute leaf hash using pedersen hash of preimage
-        let mut hash = match blake2s(cs.namespace(|| "preimage hash"), &preimage, SUBSTRATE_BLAKE2_PERSONALIZATION) {
+        let hash = match blake2s(cs.namespace(|| "preimage hash"), &preimage, SUBSTRATE_BLAKE2_PERSONALIZATION) {
             Ok(value) => value,
             Err(e) => panic!("{:?}", e),
         };
 
         // reconstruct merkle root hash using the private merkle path
+        let mut path_bits = vec![];
+        let mut siblings = vec![];
         for i in 0..self.proof.len() {
 			if let Some((ref side, ref element)) = self.proof[i] {
-                let elt = witness_u256(
+                siblings.push(witness_u256(
                     cs.namespace(|| format!("elt {}", i)),
                     Some(element.as_ref())
-                ).unwrap();
-
-                // Swap the two if the current subtree is on the right
-                let (xl, xr): (Vec<Boolean>, Vec<Boolean>);
-                if !*side {
-                    xl = elt;
-                    xr = hash;
-                } else {
-                    xl = hash;
-                    xr = elt;
-                }
-
-                // build preimage of merkle hash as concatenation of left and right nodes
-                let mut preimage = vec![];
-                preimage.extend(xl.into_iter());
-                preimage.resize(256, Boolean::Constant(false));
-
-                preimage.extend(xr.iter().cloned());
-                preimage.resize(512, Boolean::Constant(false));
-
-
-                hash = blake2s(cs.namespace(|| format!("black hash depth: {}", i)), &preimage, SUBSTRATE_BLAKE2_PERSONALIZATION).unwrap();
+                )?);
+                path_bits.push(Boolean::from(AllocatedBit::alloc(
+                    cs.namespace(|| format!("position bit {}", i)),
+                    Some(*side)
+                )?));
             }
         }
+        let hash = merkle::compute_root(
+            cs.namespace(|| "merkle root"),
+            &hash,
+            &path_bits,
+            &siblings,
+            &merkle::Blake2sHasher::new(SUBSTRATE_BLAKE2_PERSONALIZATION)
+        )?;
 
         assert_eq!(hash.len(), 256);
-        let hash_pt = multipack::compute_multipacking::<E>(&booleans_to_bools(hash))[0];
-        let hash_alloc: AllocatedNum<E> = AllocatedNum::alloc(cs.namespace(|| "hash alloc"), || Ok(hash_pt))?;
-        hash_alloc.inputize(cs.namespace(|| "calculated root hash"))?;
+        multipack::pack_into_inputs(cs.namespace(|| "root pack"), &hash)?;
         Ok(())
     }
 }
 
-fn booleans_to_bools(booleans: Vec<Boolean>) -> Vec<bool> {
-    return booleans.clone().iter_mut().map(|b| {
-        b.get_value().unwrap()
-    }).collect();
-}
-
 fn print_booleans(booleans: Vec<Boolean>) {
     for i in 0..booleans.len() {
         println!("{:?}", booleans[i].get_value());
@@ -143,7 +118,7 @@ fn witness_bits<E, CS>(
     let bit_values = if let Some(value) = value {
         let mut tmp = vec![];
         for b in value.iter()
-                      .flat_map(|&m| (0..8).rev().map(move |i| m >> i & 1 == 1))
+                      .flat_map(|&m| (0..8).map(move |i| m >> i & 1 == 1))
                       .skip(skip_bits)
         {
             tmp.push(Some(b));
@@ -252,6 +227,9 @@ pub fn prove(
     })
 }
 
+/// Verifies a proof against the hex of the 32 bytes of the nullifier
+/// and of the merkle root, which the circuit exposes multipacked from
+/// their little-endian bits, as consumed and produced by `blake2s`.
 pub fn verify(
     params: &str,
     proof: &str,
@@ -260,21 +238,18 @@ pub fn verify(
 ) -> Result<KGVerify, Box<Error>> {
     let de_params = Parameters::read(&hex::decode(params)?[..], true)?;
     let pvk = prepare_verifying_key::<Bn256>(&de_params.vk);
-    // Nullifier
-    let nullifier_big = BigInt::from_str_radix(nullifier_hex, 16)?;
-    let nullifier_raw = &nullifier_big.to_str_radix(10);
-    let nullifier = Fr::from_str(nullifier_raw).ok_or("couldn't parse Fr")?;
-    // Root hash
-    let root_big = BigInt::from_str_radix(root_hex, 16)?;
-    let root_raw = &root_big.to_str_radix(10);
-    let root = Fr::from_str(root_raw).ok_or("couldn't parse Fr")?;
+    let nullifier = hex::decode(nullifier_hex)?;
+    let root = hex::decode(root_hex)?;
+    if nullifier.len() != 32 || root.len() != 32 {
+        return Err("nullifier and root must be 32 bytes".into());
+    }
+    let mut inputs = multipack::compute_multipacking::<Bn256>(&multipack::bytes_to_bits_le(&nullifier));
+    inputs.extend(multipack::compute_multipacking::<Bn256>(&multipack::bytes_to_bits_le(&root)));
     let result = verify_proof(
         &pvk,
         &Proof::read(&hex::decode(proof)?[..])?,
-        &[
-            nullifier,
-            root
-        ])?;
+        &inputs
+    )?;
 
     Ok(KGVerify{
         result: result
@@ -1,4 +1,4 @@
-use blake2_rfc::blake2s::{blake2s};
+use sapling_crypto::util::blake2s_hash;
 /// Binary Tree where leaves hold a stand-alone value.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Tree {
@@ -39,7 +39,7 @@ pub fn create_leaf_from_preimage(nullifier: [u8; 32], secret: [u8; 32]) -> Tree
     preimage.extend(nullifier.into_iter());
     preimage.extend(secret.into_iter());
 
-    hash.copy_from_slice(&blake2s(32, SUBSTRATE_BLAKE2_PERSONALIZATION, &preimage[..]).as_bytes()[0..32]);
+    hash.copy_from_slice(&blake2s_hash(SUBSTRATE_BLAKE2_PERSONALIZATION, &preimage[..]));
     return Tree::Empty {
         hash: hash,
         parent: None,
@@ -166,7 +166,7 @@ pub fn hash_leaf_pair(_index: usize, lhs: Tree, rhs: Tree) -> Box<Tree> {
     let mut preimage: Vec<u8> = vec![];
     preimage.extend(lhs.hash().into_iter());
     preimage.extend(rhs.hash().into_iter());
-    hash.copy_from_slice(&blake2s(32, SUBSTRATE_BLAKE2_PERSONALIZATION, &preimage[..]).as_bytes()[0..32]);
+    hash.copy_from_slice(&blake2s_hash(SUBSTRATE_BLAKE2_PERSONALIZATION, &preimage[..]));
     return Box::new(Tree::Node {
         hash: hash,
         left: Box::new(lhs),
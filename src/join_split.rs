@@ -0,0 +1,529 @@
+use rand::{Rng, Rand};
+use bellman::{
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    SynthesisError,
+};
+use std::error::Error;
+
+use ff::{Field, PrimeField};
+use sapling_crypto::{
+    babyjubjub::{
+        JubjubEngine,
+        JubjubBn256,
+    },
+    circuit::{
+        Assignment,
+        num::{AllocatedNum},
+        boolean::{self, Boolean}
+    }
+};
+
+use pairing::{bn256::{Bn256, Fr}};
+use merkle_tree::{create_leaf_from_preimage, create_leaf_from_commitment, create_preimage_commitment};
+use zk_util::{KGGenerate, NoteParams, NoteStatement, generate_params, check_path_depth, check_merkle_path};
+use {note_leaf, committed_note_leaf, merkle_root, pack_value, expose_value};
+
+/// Number of notes spent and created by a join-split.
+pub const NOTES: usize = 2;
+
+/// A note spent by a join-split, with its merkle path.
+#[derive(Clone)]
+pub struct JoinSplitInput<E: JubjubEngine> {
+    pub nullifier: Option<E::Fr>,
+    pub secret: Option<E::Fr>,
+    pub value: Option<u64>,
+    pub path: Vec<Option<(bool, E::Fr)>>,
+}
+
+/// A note created by a join-split, known to its sender only by the
+/// commitment to its preimage.
+#[derive(Clone)]
+pub struct JoinSplitOutput<E: JubjubEngine> {
+    pub commitment: Option<E::Fr>,
+    pub value: Option<u64>,
+}
+
+/// Circuit for spending two notes of the tree into two new notes and a
+/// public withdrawal.
+///
+/// The public inputs are the root, the nullifiers of the spent notes, the
+/// leaves of the created notes, the recipient of the withdrawal and the
+/// withdrawn value, in that order. The
+/// membership of a spent note is only enforced when its value is nonzero,
+/// so that empty notes can pad a transaction spending a single note. Since
+/// the nullifier of an empty note could then be anyone's, its leaf, which
+/// is bound to its secret, is published in place of its nullifier.
+///
+/// A created note is given by the preimage commitment its recipient
+/// discloses, so its sender cannot spend it.
+pub struct JoinSplitCircuit<'a, E: JubjubEngine> {
+    pub inputs: Vec<JoinSplitInput<E>>,
+    pub outputs: Vec<JoinSplitOutput<E>>,
+    pub public_withdrawal: Option<u64>,
+    pub recipient: Option<E::Fr>,
+    pub root: Option<E::Fr>,
+    pub params: &'a E::Params,
+}
+
+impl<'a, E: JubjubEngine> Circuit<E> for JoinSplitCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        assert_eq!(self.inputs.len(), NOTES);
+        assert_eq!(self.outputs.len(), NOTES);
+
+        let root = cs.alloc_input(|| "root", || Ok(*self.root.get()?))?;
+        // in_1 + in_2 - out_1 - out_2 - public_withdrawal
+        let mut balance = LinearCombination::<E>::zero();
+
+        let mut nullifiers = vec![];
+        for (i, input) in self.inputs.into_iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("input {}", i));
+
+            let nullifier = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || Ok(*input.nullifier.get()?))?;
+            let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || Ok(*input.secret.get()?))?;
+            let value_bits = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value"), input.value)?;
+            let packed_value = pack_value::<E>(CS::one(), &value_bits);
+            let value = packed_value.lc(E::Fr::one());
+            balance = balance + &value;
+
+            let leaf = note_leaf(
+                cs.namespace(|| "leaf"),
+                &nullifier,
+                &secret,
+                &value_bits,
+                self.params
+            )?;
+
+            let value_num = AllocatedNum::alloc(cs.namespace(|| "value num"), || Ok(*packed_value.get_value().get()?))?;
+            cs.enforce(
+                || "value num is the packed value bits",
+                |lc| lc + value_num.get_variable(),
+                |lc| lc + CS::one(),
+                |_| value.clone()
+            );
+            let is_empty = value_num.is_zero(cs.namespace(|| "note is empty"))?;
+            let public_nullifier = AllocatedNum::conditionally_select(
+                cs.namespace(|| "public nullifier"),
+                &leaf,
+                &nullifier,
+                &is_empty
+            )?;
+            public_nullifier.inputize(cs.namespace(|| "public input nullifier"))?;
+
+            let computed_root = merkle_root(
+                cs.namespace(|| "merkle path"),
+                &leaf,
+                &input.path,
+                self.params
+            )?;
+            cs.enforce(
+                || "root is the reconstructed merkle root unless the note is empty",
+                |lc| lc + computed_root.get_variable() - root,
+                |_| value,
+                |lc| lc
+            );
+
+            nullifiers.push(nullifier);
+        }
+
+        // spending the same note twice would count its value twice
+        let same_nullifier = AllocatedNum::equals(
+            cs.namespace(|| "nullifiers equal"),
+            &nullifiers[0],
+            &nullifiers[1]
+        )?;
+        Boolean::enforce_equal(
+            cs.namespace(|| "nullifiers are distinct"),
+            &same_nullifier,
+            &Boolean::constant(false)
+        )?;
+
+        for (i, output) in self.outputs.into_iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("output {}", i));
+
+            let commitment = AllocatedNum::alloc(cs.namespace(|| "commitment"), || Ok(*output.commitment.get()?))?;
+            let value_bits = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value"), output.value)?;
+            balance = balance - &pack_value::<E>(CS::one(), &value_bits).lc(E::Fr::one());
+
+            let leaf = committed_note_leaf(
+                cs.namespace(|| "leaf"),
+                &commitment,
+                &value_bits,
+                self.params
+            )?;
+            leaf.inputize(cs.namespace(|| "public input commitment"))?;
+        }
+
+        let recipient = self.recipient;
+        let recipient = AllocatedNum::alloc(cs.namespace(|| "recipient"), || Ok(*recipient.get()?))?;
+        recipient.inputize(cs.namespace(|| "public input recipient"))?;
+
+        let withdrawal_bits = boolean::u64_into_boolean_vec_le(
+            cs.namespace(|| "public withdrawal"),
+            self.public_withdrawal
+        )?;
+        expose_value(cs.namespace(|| "public input withdrawal"), &withdrawal_bits)?;
+        balance = balance - &pack_value::<E>(CS::one(), &withdrawal_bits).lc(E::Fr::one());
+
+        cs.enforce(
+            || "inputs balance outputs and withdrawal",
+            |_| balance,
+            |lc| lc + CS::one(),
+            |lc| lc
+        );
+
+        Ok(())
+    }
+}
+
+/// A note of the pool, whose leaf is `create_leaf_from_preimage(nullifier, secret, value)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub nullifier: Fr,
+    pub secret: Fr,
+    pub value: u64,
+}
+
+impl Note {
+    /// Creates a note of `value` with a random nullifier and secret.
+    pub fn random<R: Rng>(value: u64, rng: &mut R) -> Note {
+        Note {
+            nullifier: Fr::rand(rng),
+            secret: Fr::rand(rng),
+            value: value,
+        }
+    }
+
+    /// Returns the leaf committing to the note.
+    pub fn leaf(&self) -> Fr {
+        *create_leaf_from_preimage(self.nullifier, self.secret, self.value).hash()
+    }
+
+    /// Returns the commitment to the preimage of the note, which is what
+    /// its recipient discloses to have it created by a join-split.
+    pub fn commitment(&self) -> Fr {
+        create_preimage_commitment(self.nullifier, self.secret)
+    }
+
+    /// Returns the note as created by a join-split.
+    pub fn output(&self) -> OutputNote {
+        OutputNote {
+            commitment: self.commitment(),
+            value: self.value,
+        }
+    }
+
+    /// Returns the nullifier published when spending the note, its leaf
+    /// when it is empty, see `JoinSplitCircuit`.
+    pub fn public_nullifier(&self) -> Fr {
+        if self.value == 0 {
+            self.leaf()
+        } else {
+            self.nullifier
+        }
+    }
+}
+
+/// A note created by a join-split for the recipient who disclosed
+/// `commitment`, see `Note::commitment`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputNote {
+    pub commitment: Fr,
+    pub value: u64,
+}
+
+impl OutputNote {
+    /// Returns the leaf committing to the note, that of the `Note` the
+    /// commitment was computed from.
+    pub fn leaf(&self) -> Fr {
+        *create_leaf_from_commitment(self.commitment, self.value).hash()
+    }
+}
+
+/// A join-split transaction together with its witness.
+#[derive(Clone, Debug)]
+pub struct JoinSplit {
+    pub inputs: Vec<(Note, Vec<Option<(bool, Fr)>>)>,
+    pub outputs: Vec<OutputNote>,
+    pub public_withdrawal: u64,
+    pub recipient: Fr,
+    pub root: Fr,
+}
+
+impl JoinSplit {
+    /// Builds a join-split spending at most two notes, each with its merkle
+    /// path to `root` in a tree of `depth` levels, into at most two notes.
+    /// The difference of their values is withdrawn publicly to `recipient`.
+    ///
+    /// Missing notes are padded with random empty ones. Fails when a path
+    /// does not lead to `root` or the outputs exceed the inputs.
+    pub fn new<R: Rng>(
+        mut inputs: Vec<(Note, Vec<Option<(bool, Fr)>>)>,
+        mut outputs: Vec<OutputNote>,
+        recipient: Fr,
+        root: Fr,
+        depth: usize,
+        rng: &mut R
+    ) -> Result<JoinSplit, Box<Error>> {
+        if inputs.len() > NOTES || outputs.len() > NOTES {
+            return Err(format!("a join-split spends and creates at most {} notes", NOTES).into());
+        }
+        for &(ref note, ref path) in &inputs {
+            if note.value == 0 {
+                check_path_depth(path, depth)?;
+            } else {
+                check_merkle_path(note.leaf(), path, root, depth)?;
+            }
+        }
+        while inputs.len() < NOTES {
+            inputs.push((Note::random(0, rng), vec![Some((true, Fr::zero())); depth]));
+        }
+        while outputs.len() < NOTES {
+            outputs.push(Note::random(0, rng).output());
+        }
+        if inputs[0].0.nullifier == inputs[1].0.nullifier {
+            return Err("a note cannot be spent twice".into());
+        }
+
+        let total_in = inputs.iter().map(|&(ref note, _)| note.value as u128).sum::<u128>();
+        let total_out = outputs.iter().map(|note| note.value as u128).sum::<u128>();
+        if total_out > total_in {
+            return Err("outputs exceed inputs".into());
+        }
+        if total_in - total_out > u64::max_value() as u128 {
+            return Err("public withdrawal does not fit in 64 bits".into());
+        }
+
+        Ok(JoinSplit {
+            inputs: inputs,
+            outputs: outputs,
+            public_withdrawal: (total_in - total_out) as u64,
+            recipient: recipient,
+            root: root,
+        })
+    }
+}
+
+impl<'a> NoteStatement<'a> for JoinSplit {
+    type Circuit = JoinSplitCircuit<'a, Bn256>;
+
+    fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.root];
+        inputs.extend(self.inputs.iter().map(|&(ref note, _)| note.public_nullifier()));
+        inputs.extend(self.outputs.iter().map(|note| note.leaf()));
+        inputs.push(self.recipient);
+        inputs.push(Fr::from_str(&self.public_withdrawal.to_string()).unwrap());
+        inputs
+    }
+
+    fn circuit(&self, params: &'a NoteParams) -> JoinSplitCircuit<'a, Bn256> {
+        JoinSplitCircuit {
+            inputs: self.inputs.iter().map(|&(ref note, ref path)| JoinSplitInput {
+                nullifier: Some(note.nullifier),
+                secret: Some(note.secret),
+                value: Some(note.value),
+                path: path.clone(),
+            }).collect(),
+            outputs: self.outputs.iter().map(|note| JoinSplitOutput {
+                commitment: Some(note.commitment),
+                value: Some(note.value),
+            }).collect(),
+            public_withdrawal: Some(self.public_withdrawal),
+            recipient: Some(self.recipient),
+            root: Some(self.root),
+            params: &params.jubjub,
+        }
+    }
+}
+
+/// Builds a `JoinSplitCircuit` without an assignment for a tree of `depth` levels.
+fn blank_circuit(j_params: &JubjubBn256, depth: usize) -> JoinSplitCircuit<Bn256> {
+    JoinSplitCircuit {
+        inputs: vec![JoinSplitInput {
+            nullifier: None,
+            secret: None,
+            value: None,
+            path: vec![Some((true, Fr::zero())); depth],
+        }; NOTES],
+        outputs: vec![JoinSplitOutput {
+            commitment: None,
+            value: None,
+        }; NOTES],
+        public_withdrawal: None,
+        recipient: None,
+        root: None,
+        params: j_params,
+    }
+}
+
+/// Generates parameters for join-splits of notes of a tree of `depth` levels.
+pub fn generate(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>> {
+    let j_params = &JubjubBn256::new();
+    generate_params(seed_slice, blank_circuit(j_params, depth as usize))
+}
+
+#[cfg(test)]
+mod test {
+    use ff::Field;
+    use pairing::{bn256::Fr};
+    use rand::{ChaChaRng, SeedableRng, Rand};
+
+    use merkle_tree::tree_with_leaves;
+    use zk_util::{NoteParams, NoteStatement, synthesize_statement, check_statement_proof};
+    use super::{Note, JoinSplit, generate};
+
+    /// Builds a tree of `depth` levels holding `notes` and random leaves,
+    /// returning its root and the merkle paths of the notes.
+    fn tree_with_notes(notes: &[Note], depth: usize, rng: &mut ChaChaRng) -> (Fr, Vec<Vec<Option<(bool, Fr)>>>) {
+        let leaves: Vec<Fr> = notes.iter().map(|note| note.leaf()).collect();
+        tree_with_leaves(&leaves, depth, rng)
+    }
+
+    #[test]
+    fn test_join_split_circuit() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let spent = vec![Note::random(70, rng), Note::random(50, rng)];
+        let (root, paths) = tree_with_notes(&spent, 2, rng);
+
+        let join_split = JoinSplit::new(
+            spent.into_iter().zip(paths.into_iter()).collect(),
+            vec![Note::random(90, rng).output(), Note::random(10, rng).output()],
+            Fr::rand(rng),
+            root,
+            2,
+            rng
+        ).unwrap();
+        assert_eq!(join_split.public_withdrawal, 20);
+
+        let cs = synthesize_statement(&join_split, params);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 8);
+        assert!(cs.verify(&join_split.public_inputs()));
+
+        // creating more value than is spent is unsatisfiable
+        let mut inflated = join_split.clone();
+        inflated.outputs[0].value += 1;
+        let cs = synthesize_statement(&inflated, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("inputs balance outputs and withdrawal"));
+
+        // a note outside of the tree cannot be spent
+        let mut forged = join_split.clone();
+        forged.inputs[1].0.secret = Fr::rand(rng);
+        let cs = synthesize_statement(&forged, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("input 1/root is the reconstructed merkle root unless the note is empty"));
+
+        // nor can the same note be spent twice
+        let mut doubled = join_split.clone();
+        doubled.inputs[1] = doubled.inputs[0].clone();
+        doubled.public_withdrawal += 20;
+        let cs = synthesize_statement(&doubled, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("nullifiers are distinct/enforce equal to zero"));
+    }
+
+    #[test]
+    fn test_join_split_padding() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let spent = vec![Note::random(100, rng)];
+        let (root, paths) = tree_with_notes(&spent, 2, rng);
+        let inputs: Vec<_> = spent.into_iter().zip(paths.into_iter()).collect();
+
+        // a single note is split into one note and a withdrawal
+        let join_split = JoinSplit::new(inputs.clone(), vec![Note::random(60, rng).output()], Fr::rand(rng), root, 2, rng).unwrap();
+        assert_eq!(join_split.inputs[1].0.value, 0);
+        assert_eq!(join_split.outputs[1].value, 0);
+        assert_eq!(join_split.public_withdrawal, 40);
+        let cs = synthesize_statement(&join_split, params);
+        assert!(cs.is_satisfied());
+
+        assert_eq!(join_split.public_inputs()[2], join_split.inputs[1].0.leaf());
+
+        assert!(JoinSplit::new(inputs.clone(), vec![Note::random(101, rng).output()], Fr::rand(rng), root, 2, rng).is_err());
+        assert!(JoinSplit::new(inputs.clone(), vec![], Fr::rand(rng), Fr::rand(rng), 2, rng).is_err());
+        assert!(JoinSplit::new(inputs, vec![], Fr::rand(rng), root, 3, rng).is_err());
+    }
+
+    #[test]
+    fn test_join_split_empty_note_nullifier() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let victim = Note::random(100, rng);
+        let (root, _) = tree_with_notes(&[victim.clone()], 2, rng);
+
+        // an empty note needs no path, so it may carry the nullifier of
+        // a note of the tree
+        let dummy = Note {
+            nullifier: victim.nullifier,
+            secret: Fr::rand(rng),
+            value: 0,
+        };
+        let join_split = JoinSplit::new(vec![(dummy, vec![Some((true, Fr::zero())); 2])], vec![], Fr::rand(rng), root, 2, rng).unwrap();
+        let cs = synthesize_statement(&join_split, params);
+        assert!(cs.is_satisfied());
+
+        // but that nullifier is not the one published
+        let mut public_inputs = join_split.public_inputs();
+        assert!(cs.verify(&public_inputs));
+        assert!(!public_inputs.contains(&victim.nullifier));
+        public_inputs[1] = victim.nullifier;
+        assert!(!cs.verify(&public_inputs));
+    }
+
+    #[test]
+    fn test_join_split_output_commitment() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let spent = vec![Note::random(100, rng)];
+        let (root, paths) = tree_with_notes(&spent, 2, rng);
+
+        // the recipient keeps the preimage of the note and only discloses
+        // its commitment to the sender
+        let received = Note::random(100, rng);
+        let join_split = JoinSplit::new(
+            spent.into_iter().zip(paths.into_iter()).collect(),
+            vec![received.output()],
+            Fr::rand(rng),
+            root,
+            2,
+            rng
+        ).unwrap();
+        let cs = synthesize_statement(&join_split, params);
+        assert!(cs.is_satisfied());
+        assert_eq!(join_split.public_inputs()[3], received.leaf());
+
+        // which is then enough for the recipient to spend it
+        let (root, paths) = tree_with_notes(&[received.clone()], 2, rng);
+        let join_split = JoinSplit::new(
+            vec![(received, paths[0].clone())],
+            vec![],
+            Fr::rand(rng),
+            root,
+            2,
+            rng
+        ).unwrap();
+        let cs = synthesize_statement(&join_split, params);
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_join_split_proof() {
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let spent = vec![Note::random(5, rng), Note::random(7, rng)];
+        let (root, paths) = tree_with_notes(&spent, 2, rng);
+        let join_split = JoinSplit::new(
+            spent.into_iter().zip(paths.into_iter()).collect(),
+            vec![Note::random(12, rng).output()],
+            Fr::rand(rng),
+            root,
+            2,
+            rng
+        ).unwrap();
+
+        // neither the withdrawn value nor its recipient can be changed
+        let params = generate(seed_slice, 2).unwrap().params;
+        check_statement_proof(seed_slice, &params, &join_split, &[5, 6]);
+    }
+}
@@ -21,28 +21,48 @@ use bellman::{
     Circuit,
     SynthesisError,
     ConstraintSystem,
+    Variable,
 };
 
 use ff::{Field, PrimeField};
 use sapling_crypto::{
     babyjubjub::{
         JubjubEngine,
+        FixedGenerators,
     },
     circuit::{
-        num::{AllocatedNum},
+        Assignment,
+        num::{AllocatedNum, Num},
+        baby_ecc,
         baby_pedersen_hash,
-        boolean::{Boolean, AllocatedBit}
-    }
+        merkle,
+        boolean::{self, Boolean, AllocatedBit}
+    },
+    poseidon::PoseidonParams,
 };
 
 use pairing::{bn256::{Fr}};
 
 mod blake_circuit;
 mod blake_merkle_tree;
+pub mod join_split;
 mod merkle_tree;
+pub mod multi_withdraw;
+pub mod owned_note;
 mod zk_util;
 
-use zk_util::{generate, prove, verify};
+use zk_util::{generate, prove, verify, verify_committed, generate_quaternary, prove_quaternary, verify_quaternary};
+pub use zk_util::{NoteParams, NoteStatement, prove_statement, verify_public_inputs};
+
+/// How a withdrawal reveals the value of the spent note.
+#[derive(Clone)]
+enum ValueExposure<E: JubjubEngine> {
+    /// The value is a public input.
+    Public,
+    /// A Pedersen commitment to the value, blinded by the given
+    /// randomness, is exposed instead of the value itself.
+    Committed(Option<E::Fs>),
+}
 
 /// Circuit for proving knowledge of preimage of leaf in merkle tree
 struct MerkleTreeCircuit<'a, E: JubjubEngine> {
@@ -50,7 +70,13 @@ struct MerkleTreeCircuit<'a, E: JubjubEngine> {
     nullifier: Option<E::Fr>,
     // secret
     secret: Option<E::Fr>,
+    // value of the note
+    value: Option<u64>,
+    // whether the value or a commitment to it is public
+    value_exposure: ValueExposure<E>,
     proof: Vec<Option<(bool, E::Fr)>>,
+    // expected merkle root; the reconstructed root is exposed when absent
+    root: Option<E::Fr>,
     params: &'a E::Params,
 }
 
@@ -74,64 +100,350 @@ impl<'a, E: JubjubEngine> Circuit<E> for MerkleTreeCircuit<'a, E> {
                 None => E::Fr::zero(),
             })
         )?;
-        // construct preimage using [nullifier_bits|secret_bits] concatenation
-        let mut preimage = vec![];
-        preimage.extend(nullifier.into_bits_le_strict(cs.namespace(|| "nullifier bits"))?
-            .into_iter()
-            .take(Fr::NUM_BITS as usize));
-        preimage.extend(secret.into_bits_le_strict(cs.namespace(|| "secret bits"))?
-            .into_iter()
-            .take(Fr::NUM_BITS as usize));
-        // compute leaf hash using pedersen hash of preimage
-        let mut hash = baby_pedersen_hash::pedersen_hash(
-            cs.namespace(|| "computation of leaf pedersen hash"),
-            baby_pedersen_hash::Personalization::NoteCommitment,
-            &preimage,
+        // value of the note; its 64-bit decomposition is the range check
+        let value_bits = boolean::u64_into_boolean_vec_le(
+            cs.namespace(|| "value"),
+            self.value
+        )?;
+        match self.value_exposure {
+            ValueExposure::Public => {
+                expose_value(cs.namespace(|| "public input value"), &value_bits)?;
+            },
+            ValueExposure::Committed(randomness) => {
+                expose_value_commitment(
+                    cs.namespace(|| "value commitment"),
+                    &value_bits,
+                    randomness,
+                    self.params
+                )?;
+            },
+        }
+        // compute the leaf of the note
+        let hash = note_leaf(
+            cs.namespace(|| "leaf"),
+            &nullifier,
+            &secret,
+            &value_bits,
             self.params
-        )?.get_x().clone();
+        )?;
         // reconstruct merkle root hash using the private merkle path
-        for i in 0..self.proof.len() {
-			if let Some((ref side, ref element)) = self.proof[i] {
-                let elt = AllocatedNum::alloc(cs.namespace(|| format!("elt {}", i)), || Ok(*element))?;
-                let right_side = Boolean::from(AllocatedBit::alloc(
-                    cs.namespace(|| format!("position bit {}", i)),
-                    Some(*side)).unwrap()
-                );
-                // Swap the two if the current subtree is on the right
-                let (xl, xr) = AllocatedNum::conditionally_reverse(
-                    cs.namespace(|| format!("conditional reversal of preimage {}", i)),
-                    &elt,
-                    &hash,
-                    &right_side
-                )?;
-                // build preimage of merkle hash as concatenation of left and right nodes
-                let mut preimage = vec![];
-                preimage.extend(xl.into_bits_le_strict(cs.namespace(|| format!("xl into bits {}", i)))?);
-                preimage.extend(xr.into_bits_le_strict(cs.namespace(|| format!("xr into bits {}", i)))?);
-                // Compute the new subtree value
-                let personalization = baby_pedersen_hash::Personalization::MerkleTree(i as usize);
-                hash = baby_pedersen_hash::pedersen_hash(
-                    cs.namespace(|| format!("computation of pedersen hash {}", i)),
-                    personalization,
-                    &preimage,
-                    self.params
-                )?.get_x().clone(); // Injective encoding
+        let hash = merkle_root(
+            cs.namespace(|| "merkle path"),
+            &hash,
+            &self.proof,
+            self.params
+        )?;
+        // expose the merkle root and enforce that it is the reconstructed one
+        let root = cs.alloc_input(|| "root", || Ok(match self.root {
+            Some(r) => r,
+            None => *hash.get_value().get()?,
+        }))?;
+        cs.enforce(
+            || "root is the reconstructed merkle root",
+            |lc| lc + root,
+            |lc| lc + CS::one(),
+            |lc| lc + hash.get_variable()
+        );
+        println!("THE ROOT HASH {:?}", hash.get_value());
+        Ok(())
+    }
+}
+
+/// Circuit for proving knowledge of the preimage of a leaf in a
+/// quaternary Poseidon merkle tree, which needs half the levels of the
+/// binary tree of `MerkleTreeCircuit`.
+///
+/// The public inputs are the nullifier, the value and the root, in that
+/// order, as for a `MerkleTreeCircuit` with a public value.
+struct QuaternaryTreeCircuit<'a, E: JubjubEngine> {
+    nullifier: Option<E::Fr>,
+    secret: Option<E::Fr>,
+    value: Option<u64>,
+    // for every level, the little-endian position bits of the node
+    // among its siblings and the three other children in order
+    path: Vec<Option<((bool, bool), [E::Fr; 3])>>,
+    // expected merkle root; the reconstructed root is exposed when absent
+    root: Option<E::Fr>,
+    params: &'a E::Params,
+    // must hash four inputs
+    poseidon_params: &'a PoseidonParams<E>,
+}
+
+impl<'a, E: JubjubEngine> Circuit<E> for QuaternaryTreeCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let nullifier = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || Ok(*self.nullifier.get()?))?;
+        nullifier.inputize(cs.namespace(|| "public input nullifier"))?;
+        let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || Ok(*self.secret.get()?))?;
+        let value_bits = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value"), self.value)?;
+        expose_value(cs.namespace(|| "public input value"), &value_bits)?;
+        let leaf = note_leaf(
+            cs.namespace(|| "leaf"),
+            &nullifier,
+            &secret,
+            &value_bits,
+            self.params
+        )?;
+
+        let mut path_bits = vec![];
+        let mut siblings = vec![];
+        for (i, elt) in self.path.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("level {}", i));
+            let low = AllocatedBit::alloc(cs.namespace(|| "low bit"), elt.map(|((low, _), _)| low))?;
+            let high = AllocatedBit::alloc(cs.namespace(|| "high bit"), elt.map(|((_, high), _)| high))?;
+            path_bits.push((Boolean::from(low), Boolean::from(high)));
+            let mut level_siblings = vec![];
+            for j in 0..3 {
+                level_siblings.push(AllocatedNum::alloc(
+                    cs.namespace(|| format!("sibling {}", j)),
+                    || Ok(elt.get()?.1[j])
+                )?);
             }
+            siblings.push(level_siblings);
         }
-        hash.inputize(cs)?;
-        println!("THE ROOT HASH {:?}", hash.get_value());
+        let computed_root = merkle::compute_root_quaternary(
+            cs.namespace(|| "merkle root"),
+            &leaf,
+            &path_bits,
+            &siblings,
+            self.poseidon_params
+        )?;
+
+        let root = cs.alloc_input(|| "root", || Ok(match self.root {
+            Some(r) => r,
+            None => *computed_root.get_value().get()?,
+        }))?;
+        cs.enforce(
+            || "root is the reconstructed merkle root",
+            |lc| lc + root,
+            |lc| lc + CS::one(),
+            |lc| lc + computed_root.get_variable()
+        );
+
         Ok(())
     }
 }
 
+/// Computes the leaf of a note, the leaf of the value bits committed to
+/// by the preimage commitment of `nullifier` and `secret`.
+fn note_leaf<E, CS>(
+    mut cs: CS,
+    nullifier: &AllocatedNum<E>,
+    secret: &AllocatedNum<E>,
+    value_bits: &[Boolean],
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let commitment = preimage_commitment(
+        cs.namespace(|| "preimage commitment"),
+        nullifier,
+        secret,
+        params
+    )?;
+
+    committed_note_leaf(cs, &commitment, value_bits, params)
+}
+
+/// Computes the commitment to the spending preimage of a note, the
+/// pedersen hash of the [nullifier_bits|secret_bits] concatenation.
+/// It is all the recipient of a note has to disclose to its sender.
+fn preimage_commitment<E, CS>(
+    cs: CS,
+    nullifier: &AllocatedNum<E>,
+    secret: &AllocatedNum<E>,
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    pair_commitment(cs, baby_pedersen_hash::Personalization::PreimageCommitment, nullifier, secret, params)
+}
+
+/// Computes the commitment binding a note to the key owning it, the
+/// pedersen hash of the [commitment_bits|owner_x_bits] concatenation.
+/// It takes the place of the preimage commitment in the leaf.
+fn owner_commitment<E, CS>(
+    cs: CS,
+    commitment: &AllocatedNum<E>,
+    owner_x: &AllocatedNum<E>,
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    pair_commitment(cs, baby_pedersen_hash::Personalization::OwnerCommitment, commitment, owner_x, params)
+}
+
+fn pair_commitment<E, CS>(
+    mut cs: CS,
+    personalization: baby_pedersen_hash::Personalization,
+    lhs: &AllocatedNum<E>,
+    rhs: &AllocatedNum<E>,
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let mut preimage = vec![];
+    preimage.extend(lhs.into_bits_le_strict(cs.namespace(|| "lhs bits"))?
+        .into_iter()
+        .take(Fr::NUM_BITS as usize));
+    preimage.extend(rhs.into_bits_le_strict(cs.namespace(|| "rhs bits"))?
+        .into_iter()
+        .take(Fr::NUM_BITS as usize));
+
+    Ok(baby_pedersen_hash::pedersen_hash(
+        cs.namespace(|| "computation of pedersen hash"),
+        personalization,
+        &preimage,
+        params
+    )?.get_x().clone())
+}
+
+/// Computes the leaf of a note from the commitment to its preimage, the
+/// pedersen hash of the [commitment_bits|value_bits] concatenation.
+fn committed_note_leaf<E, CS>(
+    mut cs: CS,
+    commitment: &AllocatedNum<E>,
+    value_bits: &[Boolean],
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let mut preimage = vec![];
+    preimage.extend(commitment.into_bits_le_strict(cs.namespace(|| "commitment bits"))?
+        .into_iter()
+        .take(Fr::NUM_BITS as usize));
+    preimage.extend(value_bits.iter().cloned());
+
+    Ok(baby_pedersen_hash::pedersen_hash(
+        cs.namespace(|| "computation of leaf pedersen hash"),
+        baby_pedersen_hash::Personalization::NoteCommitment,
+        &preimage,
+        params
+    )?.get_x().clone())
+}
+
+/// Reconstructs the root of the Pedersen merkle tree holding `leaf` from
+/// its private merkle path. Enforcing the root is left to the caller.
+fn merkle_root<E, CS>(
+    mut cs: CS,
+    leaf: &AllocatedNum<E>,
+    path: &[Option<(bool, E::Fr)>],
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let mut path_bits = vec![];
+    let mut siblings = vec![];
+    for (i, elt) in path.iter().enumerate() {
+        if let Some((ref side, ref element)) = *elt {
+            siblings.push(AllocatedNum::alloc(cs.namespace(|| format!("elt {}", i)), || Ok(*element))?);
+            path_bits.push(Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| format!("position bit {}", i)),
+                Some(*side)
+            )?));
+        }
+    }
+
+    merkle::compute_root(
+        cs.namespace(|| "merkle root"),
+        leaf,
+        &path_bits,
+        &siblings,
+        &merkle::PedersenHasher::new(params)
+    )
+}
+
+/// Packs little-endian value bits into a number.
+fn pack_value<E: JubjubEngine>(one: Variable, value_bits: &[Boolean]) -> Num<E> {
+    let mut packed = Num::zero();
+    let mut coeff = E::Fr::one();
+    for bit in value_bits {
+        packed = packed.add_bool_with_coeff(one, bit, coeff);
+        coeff.double();
+    }
+
+    packed
+}
+
+/// Exposes the value packed from its little-endian bits as a public input.
+fn expose_value<E, CS>(
+    mut cs: CS,
+    value_bits: &[Boolean]
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let packed = pack_value::<E>(CS::one(), value_bits);
+
+    let value = cs.alloc_input(|| "value", || Ok(*packed.get_value().get()?))?;
+    cs.enforce(
+        || "value is the packed value bits",
+        |lc| lc + value,
+        |lc| lc + CS::one(),
+        |_| packed.lc(E::Fr::one())
+    );
+
+    Ok(())
+}
+
+/// Exposes the Pedersen commitment to the value over the babyjubjub
+/// value commitment generators, as computed by
+/// `baby_primitives::ValueCommitment::cm`.
+fn expose_value_commitment<E, CS>(
+    mut cs: CS,
+    value_bits: &[Boolean],
+    randomness: Option<E::Fs>,
+    params: &E::Params
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    // Compute the note value in the exponent
+    let value = baby_ecc::fixed_base_multiplication(
+        cs.namespace(|| "compute the value in the exponent"),
+        FixedGenerators::ValueCommitmentValue,
+        value_bits,
+        params
+    )?;
+
+    // Booleanize the randomness; it does not need to be "in the field"
+    let rcv = boolean::field_into_boolean_vec_le(
+        cs.namespace(|| "rcv"),
+        randomness
+    )?;
+
+    // Compute the randomness in the exponent
+    let rcv = baby_ecc::fixed_base_multiplication(
+        cs.namespace(|| "computation of rcv"),
+        FixedGenerators::ValueCommitmentRandomness,
+        &rcv,
+        params
+    )?;
+
+    // Compute the Pedersen commitment to the value and expose it
+    let cv = value.add(
+        cs.namespace(|| "computation of cv"),
+        &rcv,
+        params
+    )?;
+    cv.inputize(cs.namespace(|| "commitment point"))?;
+
+    Ok(())
+}
+
 #[wasm_bindgen]
 extern "C" {
     fn alert(s: &str);
 }
 
 #[wasm_bindgen(catch)]
-pub fn generate_tree(seed_slice: &[u32], depth: u32) -> Result<JsValue, JsValue> {
-    let res = generate(seed_slice, depth);
+pub fn generate_tree(seed_slice: &[u32], depth: u32, commit_value: bool) -> Result<JsValue, JsValue> {
+    let res = generate(seed_slice, depth, commit_value);
     if res.is_ok() {
         Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
     } else {
@@ -145,10 +457,24 @@ pub fn prove_tree(
     params: &str,
     nullifier_hex: &str,
     secret_hex: &str,
+    value: &str,
+    value_randomness_hex: Option<String>,
     proof_path_hex: &str,
-    proof_path_sides: &str
+    proof_path_sides: &str,
+    root_hex: Option<String>
 ) -> Result<JsValue, JsValue> {
-    let res = prove(seed_slice, params, nullifier_hex, secret_hex, proof_path_hex, proof_path_sides);
+    let value = value.parse::<u64>().map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let res = prove(
+        seed_slice,
+        params,
+        nullifier_hex,
+        secret_hex,
+        value,
+        value_randomness_hex.as_ref().map(|r| &r[..]),
+        proof_path_hex,
+        proof_path_sides,
+        root_hex.as_ref().map(|r| &r[..])
+    );
     if res.is_ok() {
         Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
     } else {
@@ -161,9 +487,81 @@ pub fn verify_tree(
     params: &str,
     proof: &str,
     nullifier_hex: &str,
+    value: &str,
+    root_hex: &str
+) -> Result<JsValue, JsValue> {
+    let value = value.parse::<u64>().map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let res = verify(params, proof, nullifier_hex, value, root_hex);
+    if res.is_ok() {
+        Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
+    } else {
+        Err(JsValue::from_str(&res.err().unwrap().to_string()))
+    }
+}
+
+#[wasm_bindgen(catch)]
+pub fn verify_tree_committed(
+    params: &str,
+    proof: &str,
+    nullifier_hex: &str,
+    value_commitment_hex: &str,
+    root_hex: &str
+) -> Result<JsValue, JsValue> {
+    let res = verify_committed(params, proof, nullifier_hex, value_commitment_hex, root_hex);
+    if res.is_ok() {
+        Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
+    } else {
+        Err(JsValue::from_str(&res.err().unwrap().to_string()))
+    }
+}
+
+#[wasm_bindgen(catch)]
+pub fn generate_quaternary_tree(seed_slice: &[u32], depth: u32) -> Result<JsValue, JsValue> {
+    let res = generate_quaternary(seed_slice, depth);
+    if res.is_ok() {
+        Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
+    } else {
+        Err(JsValue::from_str(&res.err().unwrap().to_string()))
+    }
+}
+
+#[wasm_bindgen(catch)]
+pub fn prove_quaternary_tree(
+    seed_slice: &[u32],
+    params: &str,
+    nullifier_hex: &str,
+    secret_hex: &str,
+    value: &str,
+    proof_path_hex: &str,
+    proof_path_positions: &str
+) -> Result<JsValue, JsValue> {
+    let value = value.parse::<u64>().map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let res = prove_quaternary(
+        seed_slice,
+        params,
+        nullifier_hex,
+        secret_hex,
+        value,
+        proof_path_hex,
+        proof_path_positions
+    );
+    if res.is_ok() {
+        Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
+    } else {
+        Err(JsValue::from_str(&res.err().unwrap().to_string()))
+    }
+}
+
+#[wasm_bindgen(catch)]
+pub fn verify_quaternary_tree(
+    params: &str,
+    proof: &str,
+    nullifier_hex: &str,
+    value: &str,
     root_hex: &str
 ) -> Result<JsValue, JsValue> {
-    let res = verify(params, proof, nullifier_hex, root_hex);
+    let value = value.parse::<u64>().map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let res = verify_quaternary(params, proof, nullifier_hex, value, root_hex);
     if res.is_ok() {
         Ok(JsValue::from_serde(&res.ok().unwrap()).unwrap())
     } else {
@@ -174,11 +572,14 @@ pub fn verify_tree(
 #[cfg(test)]
 mod test {
     use std::fs;
+    use ff::{PrimeField, PrimeFieldRepr};
     use pairing::{bn256::{Bn256, Fr}};
     use sapling_crypto::{
         babyjubjub::{
             JubjubBn256,
-        }
+            fs::Fs,
+        },
+        baby_primitives::ValueCommitment,
     };
     use rand::{ChaChaRng, SeedableRng};
 
@@ -191,7 +592,7 @@ mod test {
     use rand::Rand;
     use rand::Rng;
 
-    use super::{MerkleTreeCircuit, generate, prove, verify};
+    use super::{MerkleTreeCircuit, ValueExposure, generate, prove, verify, verify_committed, generate_quaternary, prove_quaternary, verify_quaternary};
     use blake_circuit::BlakeTreeCircuit;
     use merkle_tree::{create_leaf_list, create_leaf_from_preimage, build_merkle_tree_with_proof};
     use time::PreciseTime;
@@ -217,7 +618,10 @@ mod test {
             params: j_params,
             nullifier: Some(Fr::rand(rng)),
             secret: Some(Fr::rand(rng)),
+            value: Some(rng.gen()),
+            value_exposure: ValueExposure::Public,
             proof: proof_vec,
+            root: None,
         };
 
         m_circuit.synthesize(&mut cs).unwrap();
@@ -234,7 +638,7 @@ mod test {
         println!("generating setup...");        
         let nullifier = Fr::rand(rng);
         let secret = Fr::rand(rng);
-        let leaf = *create_leaf_from_preimage(nullifier, secret).hash();
+        let leaf = *create_leaf_from_preimage(nullifier, secret, 1000).hash();
         let mut leaves = vec![leaf];
         for _ in 0..7 {
             leaves.push(Fr::rand(rng));
@@ -248,6 +652,7 @@ mod test {
         //     nullifier: Some(nullifier),
         //     secret: Some(secret),
         //     proof: proof.clone(),
+        //     root: None,
         // };
         // m_circuit.synthesize(&mut cs).unwrap();
 
@@ -265,14 +670,17 @@ mod test {
                 None => {},
             }
         }
-        let params = generate(seed_slice, proof.len() as u32).unwrap().params;
+        let params = generate(seed_slice, proof.len() as u32, false).unwrap().params;
         let proof_hex = prove(
             seed_slice,
             &params,
             nullifier_hex,
             secret_hex,
+            1000,
+            None,
             &proof_path_hex,
             &proof_path_sides,
+            None,
         ).unwrap();
 
         fs::write("test/test.params", params).unwrap();
@@ -281,11 +689,147 @@ mod test {
         let the_proof = &String::from_utf8(fs::read("test/test.proof").unwrap()).unwrap();
         
         // let h = &String::from_utf8(fs::read("test/test_tree.h").unwrap()).unwrap();
-        let verify = verify(parameters, the_proof, &nullifier_hex, &root_hex).unwrap();
+        let verify = verify(parameters, the_proof, &nullifier_hex, 1000, &root_hex).unwrap();
         // println!("{:?}", cs.which_is_unsatisfied());
         println!("Did the circuit work!? {:?}", verify.result);
     }
 
+    #[test]
+    fn test_prove_preflight_check() {
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let nullifier = Fr::rand(rng);
+        let secret = Fr::rand(rng);
+        let leaf = *create_leaf_from_preimage(nullifier, secret, 1000).hash();
+        let mut leaves = vec![leaf];
+        for _ in 0..3 {
+            leaves.push(Fr::rand(rng));
+        }
+        let tree_nodes = create_leaf_list(leaves, 2);
+        let (_r, proof) = build_merkle_tree_with_proof(tree_nodes, 2, 2, leaf, vec![]);
+        let root_hex = &_r.root.hash().to_hex();
+
+        let mut proof_path_hex: String = "".to_string();
+        let mut proof_path_sides: String = "".to_string();
+        for inx in 0..proof.len() {
+            if let Some((right_side, pt)) = proof[inx] {
+                proof_path_hex.push_str(&pt.to_hex());
+                proof_path_sides.push_str(if right_side { &"1" } else { &"0" });
+            }
+        }
+        let params = generate(seed_slice, proof.len() as u32, false).unwrap().params;
+
+        // a consistent path passes the check and the proof verifies
+        let proof_hex = prove(
+            seed_slice,
+            &params,
+            &nullifier.to_hex(),
+            &secret.to_hex(),
+            1000,
+            None,
+            &proof_path_hex,
+            &proof_path_sides,
+            Some(root_hex),
+        ).unwrap();
+        assert!(verify(&params, &proof_hex.proof, &nullifier.to_hex(), 1000, root_hex).unwrap().result);
+        assert!(!verify(&params, &proof_hex.proof, &nullifier.to_hex(), 1001, root_hex).unwrap().result);
+
+        // a wrong sibling is reported instead of producing a useless proof
+        let wrong_sibling = Fr::rand(rng).to_hex() + &proof_path_hex[64..];
+        let err = prove(
+            seed_slice,
+            &params,
+            &nullifier.to_hex(),
+            &secret.to_hex(),
+            1000,
+            None,
+            &wrong_sibling,
+            &proof_path_sides,
+            Some(root_hex),
+        ).err().unwrap();
+        assert!(err.to_string().contains("root is the reconstructed merkle root"));
+
+        // parameters for a deeper tree are rejected before proving
+        let deeper_params = generate(seed_slice, proof.len() as u32 + 1, false).unwrap().params;
+        let err = prove(
+            seed_slice,
+            &deeper_params,
+            &nullifier.to_hex(),
+            &secret.to_hex(),
+            1000,
+            None,
+            &proof_path_hex,
+            &proof_path_sides,
+            None,
+        ).err().unwrap();
+        assert!(err.to_string().contains("circuit shape does not match"));
+    }
+
+    #[test]
+    fn test_committed_value() {
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let nullifier = Fr::rand(rng);
+        let secret = Fr::rand(rng);
+        let value = 0xffff_ffff_ffff_ffff;
+        let leaf = *create_leaf_from_preimage(nullifier, secret, value).hash();
+        let mut leaves = vec![leaf];
+        for _ in 0..3 {
+            leaves.push(Fr::rand(rng));
+        }
+        let tree_nodes = create_leaf_list(leaves, 2);
+        let (_r, proof) = build_merkle_tree_with_proof(tree_nodes, 2, 2, leaf, vec![]);
+        let root_hex = &_r.root.hash().to_hex();
+
+        let mut proof_path_hex: String = "".to_string();
+        let mut proof_path_sides: String = "".to_string();
+        for inx in 0..proof.len() {
+            if let Some((right_side, pt)) = proof[inx] {
+                proof_path_hex.push_str(&pt.to_hex());
+                proof_path_sides.push_str(if right_side { &"1" } else { &"0" });
+            }
+        }
+        let params = generate(seed_slice, proof.len() as u32, true).unwrap().params;
+        let randomness = Fs::rand(rng);
+        let mut randomness_bytes = vec![];
+        randomness.into_repr().write_be(&mut randomness_bytes).unwrap();
+        let randomness_hex = &hex::encode(&randomness_bytes);
+
+        let proof_hex = prove(
+            seed_slice,
+            &params,
+            &nullifier.to_hex(),
+            &secret.to_hex(),
+            value,
+            Some(randomness_hex),
+            &proof_path_hex,
+            &proof_path_sides,
+            Some(root_hex),
+        ).unwrap();
+        let value_commitment = proof_hex.value_commitment.unwrap();
+        assert!(verify_committed(&params, &proof_hex.proof, &nullifier.to_hex(), &value_commitment, root_hex).unwrap().result);
+
+        // the commitment binds the value
+        let (x, y) = ValueCommitment::<Bn256> {
+            value: value - 1,
+            randomness: randomness,
+        }.cm(&JubjubBn256::new()).into_xy();
+        assert!(!verify_committed(&params, &proof_hex.proof, &nullifier.to_hex(), &(x.to_hex() + &y.to_hex()), root_hex).unwrap().result);
+
+        // the leaf binds the value
+        let err = prove(
+            seed_slice,
+            &params,
+            &nullifier.to_hex(),
+            &secret.to_hex(),
+            value - 1,
+            Some(randomness_hex),
+            &proof_path_hex,
+            &proof_path_sides,
+            Some(root_hex),
+        ).err().unwrap();
+        assert!(err.to_string().contains("root is the reconstructed merkle root"));
+    }
 
     use merkle_tree::compute_root_from_proof;
 
@@ -318,7 +862,7 @@ mod test {
 
         let nullifier = Fr::rand(rng);
         let secret = Fr::rand(rng);
-        let leaf = *create_leaf_from_preimage(nullifier, secret).hash();
+        let leaf = *create_leaf_from_preimage(nullifier, secret, 1000).hash();
         println!("\nrandom target created in {} s\n\n", start.to(PreciseTime::now()).num_milliseconds() as f64 / 1000.0);
         let mut leaves = vec![leaf];
         for _ in 0..7 {
@@ -338,6 +882,99 @@ mod test {
         assert!(_computed_root == *_r.root.hash());
     }
 
+    use merkle_tree::{QuaternaryMerkleTree, compute_root_from_quaternary_proof};
+
+    #[test]
+    fn test_quaternary_merkle_tree() {
+        use sapling_crypto::circuit::{
+            boolean::{AllocatedBit, Boolean},
+            merkle::compute_root_quaternary,
+            num::AllocatedNum,
+        };
+        use sapling_crypto::poseidon::PoseidonParams;
+        use bellman::ConstraintSystem;
+
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = PoseidonParams::<Bn256>::new(4);
+        let depth = 3;
+
+        let leaves: Vec<Fr> = (0..50).map(|_| Fr::rand(rng)).collect();
+        let tree = QuaternaryMerkleTree::new(leaves.clone(), depth, &params);
+
+        for &index in &[0, 7, 49] {
+            let proof = tree.proof(index);
+            assert_eq!(proof.len(), depth);
+            assert!(compute_root_from_quaternary_proof(leaves[index], &proof, &params) == tree.root());
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaves[index])).unwrap();
+            let mut path_bits = vec![];
+            let mut siblings = vec![];
+            for (i, &((low, high), level_siblings)) in proof.iter().enumerate() {
+                let mut cs = cs.namespace(|| format!("level {}", i));
+                let low = AllocatedBit::alloc(cs.namespace(|| "low bit"), Some(low)).unwrap();
+                let high = AllocatedBit::alloc(cs.namespace(|| "high bit"), Some(high)).unwrap();
+                path_bits.push((Boolean::from(low), Boolean::from(high)));
+                siblings.push(level_siblings.iter().enumerate().map(|(j, s)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", j)), || Ok(*s)).unwrap()
+                }).collect::<Vec<_>>());
+            }
+            let root = compute_root_quaternary(
+                cs.namespace(|| "root"),
+                &leaf,
+                &path_bits,
+                &siblings,
+                &params
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(root.get_value().unwrap() == tree.root());
+        }
+    }
+
+    #[test]
+    fn test_quaternary_proof() {
+        use sapling_crypto::poseidon::PoseidonParams;
+
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let poseidon_params = PoseidonParams::<Bn256>::new(4);
+        let depth = 2;
+
+        let nullifier = Fr::rand(rng);
+        let secret = Fr::rand(rng);
+        let leaf = *create_leaf_from_preimage(nullifier, secret, 1000).hash();
+        let mut leaves: Vec<Fr> = (0..10).map(|_| Fr::rand(rng)).collect();
+        leaves.insert(6, leaf);
+        let tree = QuaternaryMerkleTree::new(leaves, depth, &poseidon_params);
+
+        let mut proof_path_hex = String::new();
+        let mut proof_path_positions = String::new();
+        for ((low, high), siblings) in tree.proof(6) {
+            for sibling in siblings.iter() {
+                proof_path_hex.push_str(&sibling.to_hex());
+            }
+            proof_path_positions.push_str(&(low as u8 + 2 * high as u8).to_string());
+        }
+
+        let nullifier_hex = &nullifier.to_hex();
+        let root_hex = &tree.root().to_hex();
+        let params = generate_quaternary(seed_slice, depth as u32).unwrap().params;
+        let proof = prove_quaternary(
+            seed_slice,
+            &params,
+            nullifier_hex,
+            &secret.to_hex(),
+            1000,
+            &proof_path_hex,
+            &proof_path_positions
+        ).unwrap().proof;
+
+        assert!(verify_quaternary(&params, &proof, nullifier_hex, 1000, root_hex).unwrap().result);
+        assert!(!verify_quaternary(&params, &proof, nullifier_hex, 1001, root_hex).unwrap().result);
+        assert!(!verify_quaternary(&params, &proof, nullifier_hex, 1000, &Fr::rand(rng).to_hex()).unwrap().result);
+    }
+
     #[test]
     fn test_blake_merkle_circuit() {
         let mut cs = TestConstraintSystem::<Bn256>::new();
@@ -421,7 +1058,12 @@ mod test {
         let the_proof = &String::from_utf8(fs::read("test/test.proof").unwrap()).unwrap();
         
         // let h = &String::from_utf8(fs::read("test/test_tree.h").unwrap()).unwrap();
-        let verify = verify(parameters, the_proof, &hex::encode(nullifier), &hex::encode(_r.root.hash())).unwrap();
+        let verify = blake_circuit::verify(parameters, the_proof, &hex::encode(nullifier), &hex::encode(_r.root.hash())).unwrap();
         println!("Did the circuit work!? {:?}", verify.result);
+        assert!(verify.result);
+
+        // the exposed root is the reconstructed one
+        let other_root = rng.gen::<[u8; 32]>();
+        assert!(!blake_circuit::verify(parameters, the_proof, &hex::encode(nullifier), &hex::encode(other_root)).unwrap().result);
     }
 }
@@ -4,6 +4,10 @@ use sapling_crypto::{
     babyjubjub::{
         JubjubBn256,
     },
+    poseidon::{
+        PoseidonParams,
+        poseidon_hash,
+    },
 };
 
 /// Binary Tree where leaves hold a stand-alone value.
@@ -36,18 +40,66 @@ pub struct MerkleTree {
     pub root: Tree,
 }
 
-pub fn create_leaf_from_preimage(nullifier: pairing::bn256::Fr, secret: pairing::bn256::Fr) -> Tree {
+/// Creates the leaf of a note, the leaf of the 64-bit `value` committed
+/// to by the preimage commitment of `nullifier` and `secret`.
+pub fn create_leaf_from_preimage(nullifier: pairing::bn256::Fr, secret: pairing::bn256::Fr, value: u64) -> Tree {
+    create_leaf_from_commitment(create_preimage_commitment(nullifier, secret), value)
+}
+
+/// Creates the commitment to the spending preimage of a note, the
+/// pedersen hash of the little-endian bits of `nullifier` and `secret`.
+pub fn create_preimage_commitment(nullifier: pairing::bn256::Fr, secret: pairing::bn256::Fr) -> pairing::bn256::Fr {
+    create_pair_commitment(
+        sapling_crypto::baby_pedersen_hash::Personalization::PreimageCommitment,
+        nullifier,
+        secret
+    )
+}
+
+/// Creates the commitment binding a note to the key owning it, the
+/// pedersen hash of the little-endian bits of the preimage `commitment`
+/// and the x coordinate of the owner's public key. It takes the place
+/// of the preimage commitment in the leaf of an owned note.
+pub fn create_owner_commitment(commitment: pairing::bn256::Fr, owner_x: pairing::bn256::Fr) -> pairing::bn256::Fr {
+    create_pair_commitment(
+        sapling_crypto::baby_pedersen_hash::Personalization::OwnerCommitment,
+        commitment,
+        owner_x
+    )
+}
+
+fn create_pair_commitment(
+    personalization: sapling_crypto::baby_pedersen_hash::Personalization,
+    lhs: pairing::bn256::Fr,
+    rhs: pairing::bn256::Fr
+) -> pairing::bn256::Fr {
     let params = &JubjubBn256::new();
-    let mut lhs: Vec<bool> = BitIterator::new(nullifier.into_repr()).collect();
-    let mut rhs: Vec<bool> = BitIterator::new(secret.into_repr()).collect();
+    let mut lhs: Vec<bool> = BitIterator::new(lhs.into_repr()).collect();
+    let mut rhs: Vec<bool> = BitIterator::new(rhs.into_repr()).collect();
     lhs.reverse();
     rhs.reverse();
-    let hash = sapling_crypto::baby_pedersen_hash::pedersen_hash::<Bn256, _>(
-        sapling_crypto::baby_pedersen_hash::Personalization::NoteCommitment,
+    sapling_crypto::baby_pedersen_hash::pedersen_hash::<Bn256, _>(
+        personalization,
         lhs.into_iter()
            .take(Fr::NUM_BITS as usize)
            .chain(rhs.into_iter().take(Fr::NUM_BITS as usize)),
         params
+    ).into_xy().0
+}
+
+/// Creates the leaf of a note from the commitment to its preimage, the
+/// pedersen hash of the little-endian bits of `commitment` and the
+/// 64-bit `value`.
+pub fn create_leaf_from_commitment(commitment: pairing::bn256::Fr, value: u64) -> Tree {
+    let params = &JubjubBn256::new();
+    let mut bits: Vec<bool> = BitIterator::new(commitment.into_repr()).collect();
+    bits.reverse();
+    let hash = sapling_crypto::baby_pedersen_hash::pedersen_hash::<Bn256, _>(
+        sapling_crypto::baby_pedersen_hash::Personalization::NoteCommitment,
+        bits.into_iter()
+           .take(Fr::NUM_BITS as usize)
+           .chain((0..64).map(|i| (value >> i) & 1 == 1)),
+        params
     ).into_xy().0;
     return Tree::Empty {
         hash: hash,
@@ -217,3 +269,97 @@ pub fn compute_root_from_proof(leaf: pairing::bn256::Fr, path: Vec<Option<(bool,
 
     return hash;
 }
+
+/// Builds a tree of `depth` levels holding `leaves` followed by random
+/// leaves, returning its root and the merkle paths of `leaves`.
+#[cfg(test)]
+pub fn tree_with_leaves<R: ::rand::Rng>(
+    leaves: &[pairing::bn256::Fr],
+    depth: usize,
+    rng: &mut R
+) -> (pairing::bn256::Fr, Vec<Vec<Option<(bool, pairing::bn256::Fr)>>>) {
+    let mut nodes = leaves.to_vec();
+    while nodes.len() < 1 << depth {
+        nodes.push(rng.gen());
+    }
+    let mut root = <pairing::bn256::Fr>::zero();
+    let mut paths = vec![];
+    for leaf in leaves {
+        let (tree, path) = build_merkle_tree_with_proof(create_leaf_list(nodes.clone(), depth), depth, depth, *leaf, vec![]);
+        root = *tree.root.hash();
+        paths.push(path);
+    }
+
+    (root, paths)
+}
+
+/// Quaternary tree whose nodes are the Poseidon hash of their four
+/// children, which needs half the levels of a binary tree.
+#[derive(Clone, Debug)]
+pub struct QuaternaryMerkleTree {
+    /// Levels of node hashes, from the leaves up to the root.
+    pub levels: Vec<Vec<pairing::bn256::Fr>>,
+}
+
+impl QuaternaryMerkleTree {
+    /// Builds a tree of `depth` levels over `leaves`, padded with zeros
+    /// to `4^depth` leaves. `params` must hash four inputs.
+    pub fn new(
+        mut leaves: Vec<pairing::bn256::Fr>,
+        depth: usize,
+        params: &PoseidonParams<Bn256>
+    ) -> QuaternaryMerkleTree {
+        assert_eq!(params.num_inputs(), 4);
+        assert!(leaves.len() <= 1 << (2 * depth));
+        leaves.resize(1 << (2 * depth), <pairing::bn256::Fr>::zero());
+
+        let mut levels = vec![leaves];
+        for _ in 0..depth {
+            let next = levels.last().unwrap()
+                .chunks(4)
+                .map(|children| poseidon_hash(params, children))
+                .collect();
+            levels.push(next);
+        }
+
+        return QuaternaryMerkleTree { levels: levels };
+    }
+
+    pub fn root(&self) -> pairing::bn256::Fr {
+        return self.levels.last().unwrap()[0];
+    }
+
+    /// Returns the authentication path of the leaf at `index`: for every
+    /// level, the little-endian position bits of the node among its
+    /// siblings and the three other children in order.
+    pub fn proof(&self, mut index: usize) -> Vec<((bool, bool), [pairing::bn256::Fr; 3])> {
+        let mut path = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            let position = index % 4;
+            let first = index - position;
+            let siblings: Vec<pairing::bn256::Fr> = (first..first + 4)
+                .filter(|&i| i != index)
+                .map(|i| level[i])
+                .collect();
+            path.push(((position & 1 == 1, position & 2 == 2), [siblings[0], siblings[1], siblings[2]]));
+            index /= 4;
+        }
+
+        return path;
+    }
+}
+
+pub fn compute_root_from_quaternary_proof(
+    leaf: pairing::bn256::Fr,
+    path: &[((bool, bool), [pairing::bn256::Fr; 3])],
+    params: &PoseidonParams<Bn256>
+) -> pairing::bn256::Fr {
+    let mut hash = leaf;
+    for &((low, high), siblings) in path {
+        let mut children = siblings.to_vec();
+        children.insert(low as usize + 2 * high as usize, hash);
+        hash = poseidon_hash(params, &children);
+    }
+
+    return hash;
+}
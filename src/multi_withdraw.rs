@@ -0,0 +1,279 @@
+use bellman::{
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    SynthesisError,
+};
+use std::error::Error;
+
+use ff::{Field, PrimeField};
+use sapling_crypto::{
+    babyjubjub::{
+        JubjubEngine,
+        JubjubBn256,
+    },
+    circuit::{
+        Assignment,
+        num::{AllocatedNum},
+        boolean::{self, Boolean}
+    }
+};
+
+use pairing::{bn256::{Bn256, Fr}};
+use join_split::Note;
+use zk_util::{KGGenerate, NoteParams, NoteStatement, generate_params, check_merkle_path};
+use {note_leaf, merkle_root, pack_value};
+
+/// A note withdrawn by a `MultiWithdrawCircuit`, with its merkle path.
+#[derive(Clone)]
+pub struct WithdrawnNote<E: JubjubEngine> {
+    pub nullifier: Option<E::Fr>,
+    pub secret: Option<E::Fr>,
+    pub value: Option<u64>,
+    pub path: Vec<Option<(bool, E::Fr)>>,
+}
+
+/// Circuit for withdrawing several notes of the same tree to one
+/// recipient with a single proof.
+///
+/// The public inputs are the root, the nullifier of every note, the
+/// recipient and the total value of the notes, in that order.
+///
+/// The number of notes is not part of the type: it is fixed by the
+/// parameters, and a circuit with a different number of `notes` than
+/// the one they were generated for does not produce a valid proof.
+pub struct MultiWithdrawCircuit<'a, E: JubjubEngine> {
+    pub notes: Vec<WithdrawnNote<E>>,
+    pub recipient: Option<E::Fr>,
+    pub root: Option<E::Fr>,
+    pub params: &'a E::Params,
+}
+
+impl<'a, E: JubjubEngine> Circuit<E> for MultiWithdrawCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let root = cs.alloc_input(|| "root", || Ok(*self.root.get()?))?;
+        let mut total = LinearCombination::<E>::zero();
+        let mut total_value = Some(E::Fr::zero());
+
+        let mut nullifiers = vec![];
+        for (i, note) in self.notes.into_iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("note {}", i));
+
+            let nullifier = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || Ok(*note.nullifier.get()?))?;
+            nullifier.inputize(cs.namespace(|| "public input nullifier"))?;
+            let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || Ok(*note.secret.get()?))?;
+            let value_bits = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value"), note.value)?;
+            let value = pack_value::<E>(CS::one(), &value_bits);
+            total = total + &value.lc(E::Fr::one());
+            total_value = match (total_value, value.get_value()) {
+                (Some(mut total_value), Some(value)) => {
+                    total_value.add_assign(&value);
+                    Some(total_value)
+                },
+                _ => None,
+            };
+
+            let leaf = note_leaf(
+                cs.namespace(|| "leaf"),
+                &nullifier,
+                &secret,
+                &value_bits,
+                self.params
+            )?;
+            let computed_root = merkle_root(
+                cs.namespace(|| "merkle path"),
+                &leaf,
+                &note.path,
+                self.params
+            )?;
+            cs.enforce(
+                || "root is the reconstructed merkle root",
+                |lc| lc + root,
+                |lc| lc + CS::one(),
+                |lc| lc + computed_root.get_variable()
+            );
+
+            nullifiers.push(nullifier);
+        }
+
+        // withdrawing the same note twice would pay its value twice
+        for i in 0..nullifiers.len() {
+            for j in (i + 1)..nullifiers.len() {
+                let same_nullifier = AllocatedNum::equals(
+                    cs.namespace(|| format!("nullifiers {} and {} equal", i, j)),
+                    &nullifiers[i],
+                    &nullifiers[j]
+                )?;
+                Boolean::enforce_equal(
+                    cs.namespace(|| format!("nullifiers {} and {} are distinct", i, j)),
+                    &same_nullifier,
+                    &Boolean::constant(false)
+                )?;
+            }
+        }
+
+        let recipient = self.recipient;
+        let recipient = AllocatedNum::alloc(cs.namespace(|| "recipient"), || Ok(*recipient.get()?))?;
+        recipient.inputize(cs.namespace(|| "public input recipient"))?;
+
+        let value = cs.alloc_input(|| "total value", || Ok(*total_value.get()?))?;
+        cs.enforce(
+            || "total value is the sum of the note values",
+            |lc| lc + value,
+            |lc| lc + CS::one(),
+            |_| total
+        );
+
+        Ok(())
+    }
+}
+
+/// A withdrawal of several notes to one recipient, together with its witness.
+#[derive(Clone, Debug)]
+pub struct MultiWithdraw {
+    pub notes: Vec<(Note, Vec<Option<(bool, Fr)>>)>,
+    pub recipient: Fr,
+    pub root: Fr,
+}
+
+impl MultiWithdraw {
+    /// Builds a withdrawal of `notes`, each with its merkle path to `root`
+    /// in a tree of `depth` levels, to `recipient`.
+    ///
+    /// Fails when a path does not lead to `root` or a note is repeated.
+    pub fn new(
+        notes: Vec<(Note, Vec<Option<(bool, Fr)>>)>,
+        recipient: Fr,
+        root: Fr,
+        depth: usize
+    ) -> Result<MultiWithdraw, Box<Error>> {
+        for (i, &(ref note, ref path)) in notes.iter().enumerate() {
+            check_merkle_path(note.leaf(), path, root, depth)
+                .map_err(|e| format!("note {}: {}", i, e))?;
+            if notes[..i].iter().any(|&(ref other, _)| other.nullifier == note.nullifier) {
+                return Err(format!("note {} is withdrawn twice", i).into());
+            }
+        }
+
+        Ok(MultiWithdraw {
+            notes: notes,
+            recipient: recipient,
+            root: root,
+        })
+    }
+
+    /// Returns the total value of the withdrawn notes.
+    pub fn total_value(&self) -> u128 {
+        self.notes.iter().map(|&(ref note, _)| note.value as u128).sum()
+    }
+}
+
+impl<'a> NoteStatement<'a> for MultiWithdraw {
+    type Circuit = MultiWithdrawCircuit<'a, Bn256>;
+
+    fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.root];
+        inputs.extend(self.notes.iter().map(|&(ref note, _)| note.nullifier));
+        inputs.push(self.recipient);
+        inputs.push(Fr::from_str(&self.total_value().to_string()).unwrap());
+        inputs
+    }
+
+    fn circuit(&self, params: &'a NoteParams) -> MultiWithdrawCircuit<'a, Bn256> {
+        MultiWithdrawCircuit {
+            notes: self.notes.iter().map(|&(ref note, ref path)| WithdrawnNote {
+                nullifier: Some(note.nullifier),
+                secret: Some(note.secret),
+                value: Some(note.value),
+                path: path.clone(),
+            }).collect(),
+            recipient: Some(self.recipient),
+            root: Some(self.root),
+            params: &params.jubjub,
+        }
+    }
+}
+
+/// Builds a `MultiWithdrawCircuit` without an assignment for `notes` notes
+/// of a tree of `depth` levels.
+fn blank_circuit(j_params: &JubjubBn256, depth: usize, notes: usize) -> MultiWithdrawCircuit<Bn256> {
+    MultiWithdrawCircuit {
+        notes: vec![WithdrawnNote {
+            nullifier: None,
+            secret: None,
+            value: None,
+            path: vec![Some((true, Fr::zero())); depth],
+        }; notes],
+        recipient: None,
+        root: None,
+        params: j_params,
+    }
+}
+
+/// Generates parameters for withdrawing `notes` notes of a tree of `depth` levels at once.
+///
+/// The parameters only prove withdrawals of exactly `notes` notes.
+pub fn generate(seed_slice: &[u32], depth: u32, notes: u32) -> Result<KGGenerate, Box<Error>> {
+    let j_params = &JubjubBn256::new();
+    generate_params(seed_slice, blank_circuit(j_params, depth as usize, notes as usize))
+}
+
+#[cfg(test)]
+mod test {
+    use pairing::{bn256::Fr};
+    use rand::{ChaChaRng, SeedableRng, Rand};
+
+    use join_split::Note;
+    use merkle_tree::tree_with_leaves;
+    use zk_util::{NoteParams, NoteStatement, synthesize_statement, check_statement_proof};
+    use super::{MultiWithdraw, generate};
+
+    /// Builds a withdrawal of `k` notes of `value` from a tree of `depth` levels.
+    fn withdraw_notes(k: usize, value: u64, depth: usize, rng: &mut ChaChaRng) -> MultiWithdraw {
+        let notes: Vec<Note> = (0..k).map(|_| Note::random(value, rng)).collect();
+        let leaves: Vec<Fr> = notes.iter().map(|note| note.leaf()).collect();
+        let (root, paths) = tree_with_leaves(&leaves, depth, rng);
+        MultiWithdraw::new(notes.into_iter().zip(paths.into_iter()).collect(), Fr::rand(rng), root, depth).unwrap()
+    }
+
+    #[test]
+    fn test_multi_withdraw_circuit() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let withdraw = withdraw_notes(3, 100, 2, rng);
+        assert_eq!(withdraw.total_value(), 300);
+
+        let cs = synthesize_statement(&withdraw, params);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 1 + 1 + 3 + 2);
+        assert!(cs.verify(&withdraw.public_inputs()));
+
+        // every note must be in the tree
+        let mut forged = withdraw.clone();
+        forged.notes[2].0.value = 200;
+        let cs = synthesize_statement(&forged, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("note 2/root is the reconstructed merkle root"));
+
+        // and withdrawn once
+        let mut doubled = withdraw.clone();
+        doubled.notes[2] = doubled.notes[0].clone();
+        let cs = synthesize_statement(&doubled, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("nullifiers 0 and 2 are distinct/enforce equal to zero"));
+
+        let notes = withdraw.notes.clone();
+        assert!(MultiWithdraw::new(vec![notes[0].clone(), notes[0].clone()], withdraw.recipient, withdraw.root, 2).is_err());
+        assert!(MultiWithdraw::new(notes.clone(), withdraw.recipient, Fr::rand(rng), 2).is_err());
+        assert!(MultiWithdraw::new(notes, withdraw.recipient, withdraw.root, 3).is_err());
+    }
+
+    #[test]
+    fn test_multi_withdraw_proof() {
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let withdraw = withdraw_notes(2, 10, 2, rng);
+
+        // the proof is bound to the recipient and the total value
+        let params = generate(seed_slice, 2, 2).unwrap().params;
+        check_statement_proof(seed_slice, &params, &withdraw, &[3, 4]);
+    }
+}
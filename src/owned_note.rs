@@ -0,0 +1,382 @@
+use bellman::{
+    Circuit,
+    ConstraintSystem,
+    SynthesisError,
+};
+use std::error::Error;
+
+use ff::{Field, PrimeField};
+use sapling_crypto::{
+    alt_babyjubjub::{
+        AltJubjubBn256,
+        FixedGenerators,
+        JubjubParams,
+        Unknown,
+        edwards,
+    },
+    babyjubjub::JubjubBn256,
+    circuit::{
+        Assignment,
+        num::{AllocatedNum},
+        ecc::EdwardsPoint,
+        baby_eddsa::EddsaSignature,
+        poseidon,
+        boolean
+    },
+    eddsa::{PrivateKey, PublicKey, Signature},
+    poseidon::{PoseidonParams, poseidon_hash},
+    util::{scalar_to_base, write_le_bytes},
+};
+
+use pairing::{bn256::{Bn256, Fr}};
+use join_split::Note;
+use merkle_tree::{create_leaf_from_commitment, create_owner_commitment};
+use zk_util::{KGGenerate, NoteParams, NoteStatement, generate_params, check_merkle_path};
+use {preimage_commitment, owner_commitment, committed_note_leaf, merkle_root, pack_value};
+
+/// Generator of the EdDSA keys owning notes.
+pub const OWNER_GENERATOR: FixedGenerators = FixedGenerators::SpendingKeyGenerator;
+
+/// Circuit for withdrawing a note owned by an EdDSA key over the
+/// alt-babyjubjub curve. Knowing the note preimage is not enough: the
+/// owner must sign the withdrawal, see `withdrawal_message`.
+///
+/// The public inputs are the root, the nullifier, the recipient and the
+/// value, in that order.
+pub struct OwnedNoteCircuit<'a> {
+    pub nullifier: Option<Fr>,
+    pub secret: Option<Fr>,
+    pub value: Option<u64>,
+    pub owner: Option<edwards::Point<Bn256, Unknown>>,
+    pub signature: Option<Signature<Bn256>>,
+    pub recipient: Option<Fr>,
+    pub path: Vec<Option<(bool, Fr)>>,
+    pub root: Option<Fr>,
+    pub params: &'a JubjubBn256,
+    pub alt_params: &'a AltJubjubBn256,
+    pub poseidon_params: &'a PoseidonParams<Bn256>,
+}
+
+impl<'a> Circuit<Bn256> for OwnedNoteCircuit<'a> {
+    fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let root = cs.alloc_input(|| "root", || Ok(*self.root.get()?))?;
+
+        let nullifier = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || Ok(*self.nullifier.get()?))?;
+        nullifier.inputize(cs.namespace(|| "public input nullifier"))?;
+        let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || Ok(*self.secret.get()?))?;
+        let value_bits = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value bits"), self.value)?;
+        let owner = EdwardsPoint::witness(cs.namespace(|| "owner"), self.owner.clone(), self.alt_params)?;
+
+        let leaf = {
+            let mut cs = cs.namespace(|| "leaf");
+            let commitment = preimage_commitment(
+                cs.namespace(|| "preimage commitment"),
+                &nullifier,
+                &secret,
+                self.params
+            )?;
+            let commitment = owner_commitment(
+                cs.namespace(|| "owner commitment"),
+                &commitment,
+                owner.get_x(),
+                self.params
+            )?;
+            committed_note_leaf(cs, &commitment, &value_bits, self.params)?
+        };
+
+        let computed_root = merkle_root(
+            cs.namespace(|| "merkle path"),
+            &leaf,
+            &self.path,
+            self.params
+        )?;
+        cs.enforce(
+            || "root is the reconstructed merkle root",
+            |lc| lc + root,
+            |lc| lc + CS::one(),
+            |lc| lc + computed_root.get_variable()
+        );
+
+        let recipient = AllocatedNum::alloc(cs.namespace(|| "recipient"), || Ok(*self.recipient.get()?))?;
+        recipient.inputize(cs.namespace(|| "public input recipient"))?;
+
+        let packed = pack_value::<Bn256>(CS::one(), &value_bits);
+        let value = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(*packed.get_value().get()?))?;
+        value.inputize(cs.namespace(|| "public input value"))?;
+        cs.enforce(
+            || "value is the packed value bits",
+            |lc| lc + value.get_variable(),
+            |lc| lc + CS::one(),
+            |_| packed.lc(Fr::one())
+        );
+
+        // The owner signs the withdrawal, so a leaked note preimage is
+        // not enough to redirect it
+        let message = poseidon::poseidon_hash(
+            cs.namespace(|| "withdrawal message"),
+            &[nullifier, recipient, value],
+            self.poseidon_params
+        )?;
+        let message_bits = message.into_bits_le_strict(cs.namespace(|| "withdrawal message bits"))?;
+
+        let generator = self.alt_params.generator(OWNER_GENERATOR).clone();
+        let (generator_x, generator_y) = generator.into_xy();
+        let generator = EdwardsPoint::witness(cs.namespace(|| "generator"), Some(generator), self.alt_params)?;
+        cs.enforce(
+            || "generator x is fixed",
+            |lc| lc + generator.get_x().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (generator_x, CS::one())
+        );
+        cs.enforce(
+            || "generator y is fixed",
+            |lc| lc + generator.get_y().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (generator_y, CS::one())
+        );
+
+        let r = EdwardsPoint::witness(
+            cs.namespace(|| "signature r"),
+            self.signature.as_ref().map(|signature| signature.r.clone()),
+            self.alt_params
+        )?;
+        let s = AllocatedNum::alloc(
+            cs.namespace(|| "signature s"),
+            || Ok(scalar_to_base::<Bn256>(self.signature.as_ref().get()?.s))
+        )?;
+        let signature = EddsaSignature { r: r, s: s, pk: owner };
+        signature.verify_eddsa_for_snark(
+            cs.namespace(|| "signature verification"),
+            self.alt_params,
+            &message_bits,
+            generator
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A note committing to the public key of its owner. Its leaf is that of
+/// `note` with the preimage commitment bound to the owner, see
+/// `create_owner_commitment`.
+#[derive(Clone)]
+pub struct OwnedNote {
+    pub note: Note,
+    pub owner: PublicKey<Bn256>,
+}
+
+impl OwnedNote {
+    /// Returns the leaf committing to the note.
+    pub fn leaf(&self) -> Fr {
+        let (owner_x, _) = self.owner.0.into_xy();
+        let commitment = create_owner_commitment(self.note.commitment(), owner_x);
+        *create_leaf_from_commitment(commitment, self.note.value).hash()
+    }
+}
+
+/// Returns the message the owner of `note` signs to withdraw it to
+/// `recipient`: the little-endian bytes of the Poseidon hash of the
+/// nullifier, the recipient and the value.
+pub fn withdrawal_message(note: &OwnedNote, recipient: Fr, poseidon_params: &PoseidonParams<Bn256>) -> Vec<u8> {
+    let value = Fr::from_str(&note.note.value.to_string()).unwrap();
+    write_le_bytes(poseidon_hash(poseidon_params, &[note.note.nullifier, recipient, value]))
+}
+
+/// Signs the withdrawal of `note` to `recipient` with the key owning it.
+pub fn sign_withdrawal(
+    owner: &PrivateKey<Bn256>,
+    note: &OwnedNote,
+    recipient: Fr,
+    alt_params: &AltJubjubBn256,
+    poseidon_params: &PoseidonParams<Bn256>
+) -> Signature<Bn256> {
+    owner.sign_for_snark(&withdrawal_message(note, recipient, poseidon_params), OWNER_GENERATOR, alt_params)
+}
+
+/// A signed withdrawal of an owned note, together with its witness.
+#[derive(Clone)]
+pub struct OwnedWithdraw {
+    pub note: OwnedNote,
+    pub path: Vec<Option<(bool, Fr)>>,
+    pub recipient: Fr,
+    pub root: Fr,
+    pub signature: Signature<Bn256>,
+}
+
+impl OwnedWithdraw {
+    /// Builds a withdrawal of `note`, with its merkle path to `root` in a
+    /// tree of `depth` levels, to `recipient`.
+    ///
+    /// Fails when the path does not lead to `root` or `signature` is not
+    /// the owner's signature of `withdrawal_message`.
+    pub fn new(
+        note: OwnedNote,
+        path: Vec<Option<(bool, Fr)>>,
+        recipient: Fr,
+        root: Fr,
+        depth: usize,
+        signature: Signature<Bn256>
+    ) -> Result<OwnedWithdraw, Box<Error>> {
+        check_merkle_path(note.leaf(), &path, root, depth)?;
+        let alt_params = &AltJubjubBn256::new();
+        let message = withdrawal_message(&note, recipient, &PoseidonParams::new(3));
+        if !note.owner.verify_for_snark(&message, &signature, OWNER_GENERATOR, alt_params) {
+            return Err("withdrawal is not signed by the owner of the note".into());
+        }
+
+        Ok(OwnedWithdraw {
+            note: note,
+            path: path,
+            recipient: recipient,
+            root: root,
+            signature: signature,
+        })
+    }
+}
+
+impl<'a> NoteStatement<'a> for OwnedWithdraw {
+    type Circuit = OwnedNoteCircuit<'a>;
+
+    fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.root,
+            self.note.note.nullifier,
+            self.recipient,
+            Fr::from_str(&self.note.note.value.to_string()).unwrap(),
+        ]
+    }
+
+    fn circuit(&self, params: &'a NoteParams) -> OwnedNoteCircuit<'a> {
+        OwnedNoteCircuit {
+            nullifier: Some(self.note.note.nullifier),
+            secret: Some(self.note.note.secret),
+            value: Some(self.note.note.value),
+            owner: Some(self.note.owner.0.clone()),
+            signature: Some(self.signature.clone()),
+            recipient: Some(self.recipient),
+            path: self.path.clone(),
+            root: Some(self.root),
+            params: &params.jubjub,
+            alt_params: &params.alt_jubjub,
+            poseidon_params: &params.poseidon,
+        }
+    }
+}
+
+/// Builds an `OwnedNoteCircuit` without an assignment for a tree of `depth` levels.
+fn blank_circuit(params: &NoteParams, depth: usize) -> OwnedNoteCircuit {
+    OwnedNoteCircuit {
+        nullifier: None,
+        secret: None,
+        value: None,
+        owner: None,
+        signature: None,
+        recipient: None,
+        path: vec![Some((true, Fr::zero())); depth],
+        root: None,
+        params: &params.jubjub,
+        alt_params: &params.alt_jubjub,
+        poseidon_params: &params.poseidon,
+    }
+}
+
+/// Generates parameters for withdrawing owned notes of a tree of `depth` levels.
+pub fn generate(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>> {
+    generate_params(seed_slice, blank_circuit(&NoteParams::new(), depth as usize))
+}
+
+#[cfg(test)]
+mod test {
+    use ff::{BitIterator, Field, PrimeField};
+    use pairing::{bn256::{Bn256, Fr}};
+    use rand::{ChaChaRng, SeedableRng, Rng, Rand};
+    use sapling_crypto::alt_babyjubjub::{AltJubjubBn256, Unknown, edwards::Point};
+    use sapling_crypto::eddsa::{PrivateKey, PublicKey};
+    use sapling_crypto::poseidon::PoseidonParams;
+
+    use join_split::Note;
+    use merkle_tree::tree_with_leaves;
+    use zk_util::{NoteParams, NoteStatement, synthesize_statement, check_statement_proof};
+    use super::{OwnedNote, OwnedWithdraw, OWNER_GENERATOR, sign_withdrawal, generate};
+
+    /// Builds a signed withdrawal of a note of `value` from a tree of `depth` levels.
+    fn withdraw_note(owner: &PrivateKey<Bn256>, value: u64, depth: usize, rng: &mut ChaChaRng) -> OwnedWithdraw {
+        let alt_params = &AltJubjubBn256::new();
+        let note = OwnedNote {
+            note: Note::random(value, rng),
+            owner: PublicKey::from_private(owner, OWNER_GENERATOR, alt_params),
+        };
+        let (root, mut paths) = tree_with_leaves(&[note.leaf()], depth, rng);
+        let recipient = Fr::rand(rng);
+        let signature = sign_withdrawal(owner, &note, recipient, alt_params, &PoseidonParams::new(3));
+        OwnedWithdraw::new(note, paths.remove(0), recipient, root, depth, signature).unwrap()
+    }
+
+    #[test]
+    fn test_owned_note_circuit() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let params = &NoteParams::new();
+        let owner = PrivateKey::<Bn256>(rng.gen());
+        let withdraw = withdraw_note(&owner, 100, 2, rng);
+
+        let cs = synthesize_statement(&withdraw, params);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 1 + 4);
+        assert!(cs.verify(&withdraw.public_inputs()));
+
+        // the signature is bound to the recipient
+        let mut redirected = withdraw.clone();
+        redirected.recipient = Fr::rand(rng);
+        assert!(!synthesize_statement(&redirected, params).is_satisfied());
+
+        // and a thief knowing the note preimage cannot sign for it
+        let thief = PrivateKey::<Bn256>(rng.gen());
+        let mut stolen = withdraw.clone();
+        stolen.signature = sign_withdrawal(&thief, &stolen.note, stolen.recipient, &params.alt_jubjub, &params.poseidon);
+        assert!(!synthesize_statement(&stolen, params).is_satisfied());
+
+        // nor hash another point than R in the signature challenge
+        let mut cs = synthesize_statement(&withdraw, params);
+        let (other_x, _) = Point::<Bn256, Unknown>::rand(rng, &params.alt_jubjub).into_xy();
+        for (i, bit) in BitIterator::new(other_x.into_repr()).skip(256 - Fr::NUM_BITS as usize).enumerate() {
+            cs.set(
+                &format!("signature verification/Serialize R_X/bit {}/boolean", i),
+                if bit { Fr::one() } else { Fr::zero() }
+            );
+        }
+        assert!(cs.which_is_unsatisfied().unwrap().starts_with("signature verification/Serialize R_X/"));
+
+        // nor claim the note with his own key
+        stolen.note.owner = PublicKey::from_private(&thief, OWNER_GENERATOR, &params.alt_jubjub);
+        let cs = synthesize_statement(&stolen, params);
+        assert_eq!(cs.which_is_unsatisfied(), Some("root is the reconstructed merkle root"));
+
+        let note = withdraw.note.clone();
+        assert!(OwnedWithdraw::new(note.clone(), withdraw.path.clone(), Fr::rand(rng), withdraw.root, 2, withdraw.signature.clone()).is_err());
+        assert!(OwnedWithdraw::new(note, withdraw.path.clone(), withdraw.recipient, Fr::rand(rng), 2, withdraw.signature.clone()).is_err());
+    }
+
+    #[test]
+    fn test_owned_note_leaf_is_bound_to_the_owner() {
+        let rng = &mut ChaChaRng::from_seed(&[1u32, 1u32, 1u32, 1u32]);
+        let alt_params = &AltJubjubBn256::new();
+        let owner = PublicKey::from_private(&PrivateKey::<Bn256>(rng.gen()), OWNER_GENERATOR, alt_params);
+        let note = OwnedNote { note: Note::random(100, rng), owner: owner };
+
+        // a plain note cannot be opened to the leaf of an owned one
+        let (owner_x, _) = note.owner.0.into_xy();
+        let plain = Note { nullifier: note.note.commitment(), secret: owner_x, value: note.note.value };
+        assert!(plain.leaf() != note.leaf());
+        assert!(note.note.leaf() != note.leaf());
+    }
+
+    #[test]
+    fn test_owned_note_proof() {
+        let seed_slice = &[1u32, 1u32, 1u32, 1u32];
+        let rng = &mut ChaChaRng::from_seed(seed_slice);
+        let owner = PrivateKey::<Bn256>(rng.gen());
+        let withdraw = withdraw_note(&owner, 10, 2, rng);
+
+        check_statement_proof(seed_slice, &generate(seed_slice, 2).unwrap().params, &withdraw, &[1, 2, 3]);
+    }
+}
@@ -1,19 +1,35 @@
 use rand::{ChaChaRng, SeedableRng};
-use bellman::groth16::{Proof, Parameters, verify_proof, create_random_proof, prepare_verifying_key, generate_random_parameters};
+use bellman::{Circuit, SynthesisError};
+use bellman::checker::ConstraintChecker;
+use bellman::groth16::{Proof, Parameters, CompiledCircuit, verify_proof, create_random_proof, create_random_proof_compiled, prepare_verifying_key, generate_random_parameters};
 use num_bigint::BigInt;
 use num_traits::Num;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
+use std::rc::Rc;
 
 
 use ff::{PrimeField, Field};
 use sapling_crypto::{
+    alt_babyjubjub::AltJubjubBn256,
     babyjubjub::{
         JubjubBn256,
+        fs::Fs,
     },
+    baby_primitives::ValueCommitment,
+    poseidon::PoseidonParams,
 };
 
 use pairing::{bn256::{Bn256, Fr}};
-use MerkleTreeCircuit;
+use merkle_tree::compute_root_from_proof;
+use {MerkleTreeCircuit, QuaternaryTreeCircuit, ValueExposure};
+
+thread_local! {
+    /// `MerkleTreeCircuit`s compiled once per merkle path length and value
+    /// exposure, so that proving only has to compute the witness of the circuit.
+    static COMPILED_CIRCUITS: RefCell<HashMap<(usize, bool), Rc<CompiledCircuit<Bn256>>>> = RefCell::new(HashMap::new());
+}
 
 #[derive(Serialize)]
 pub struct KGGenerate {
@@ -23,6 +39,8 @@ pub struct KGGenerate {
 #[derive(Serialize)]
 pub struct KGProof {
     pub proof: String,
+    // x and y of the value commitment when the value is not public
+    pub value_commitment: Option<String>,
     // pub nullifier: String,
     // pub secret: String,
     // pub leaf: String,
@@ -34,9 +52,9 @@ pub struct KGVerify {
     pub result: bool
 }
 
-pub fn generate(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>> {
-    let rng = &mut ChaChaRng::from_seed(seed_slice);
-    let j_params = &JubjubBn256::new();
+/// Builds a `MerkleTreeCircuit` without an assignment for a merkle path of `depth` levels,
+/// exposing a commitment to the note value instead of the value when `commit_value` is set.
+fn blank_circuit(j_params: &JubjubBn256, depth: usize, commit_value: bool) -> MerkleTreeCircuit<Bn256> {
     let mut proof_elts = vec![];
 
     for _ in 0..depth {
@@ -45,15 +63,60 @@ pub fn generate(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>
             pairing::bn256::Fr::zero(),
         )));
     }
-    let params = generate_random_parameters::<Bn256, _, _>(
-        MerkleTreeCircuit {
-            params: j_params,
-            nullifier: None,
-            secret: None,
-            proof: proof_elts,
-        },
-        rng,
-    )?;
+
+    MerkleTreeCircuit {
+        params: j_params,
+        nullifier: None,
+        secret: None,
+        value: None,
+        value_exposure: if commit_value { ValueExposure::Committed(None) } else { ValueExposure::Public },
+        proof: proof_elts,
+        root: None,
+    }
+}
+
+/// Returns the compiled `MerkleTreeCircuit` for a merkle path of `depth` levels,
+/// compiling it on first use.
+fn compiled_circuit(j_params: &JubjubBn256, depth: usize, commit_value: bool) -> Result<Rc<CompiledCircuit<Bn256>>, SynthesisError> {
+    COMPILED_CIRCUITS.with(|circuits| {
+        if let Some(compiled) = circuits.borrow().get(&(depth, commit_value)) {
+            return Ok(compiled.clone());
+        }
+
+        let compiled = Rc::new(CompiledCircuit::compile(blank_circuit(j_params, depth, commit_value))?);
+        circuits.borrow_mut().insert((depth, commit_value), compiled.clone());
+        Ok(compiled)
+    })
+}
+
+/// Generates parameters for withdrawing a note from a tree of `depth` levels.
+///
+/// With `commit_value` the proofs expose a Pedersen commitment to the
+/// note value instead of the value itself.
+pub fn generate(seed_slice: &[u32], depth: u32, commit_value: bool) -> Result<KGGenerate, Box<Error>> {
+    let j_params = &JubjubBn256::new();
+    generate_params(seed_slice, blank_circuit(j_params, depth as usize, commit_value))
+}
+
+/// Generates parameters for withdrawing a note from a quaternary tree of `depth` levels.
+pub fn generate_quaternary(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>> {
+    let j_params = &JubjubBn256::new();
+    let poseidon_params = &PoseidonParams::new(4);
+    generate_params(seed_slice, QuaternaryTreeCircuit {
+        nullifier: None,
+        secret: None,
+        value: None,
+        path: vec![None; depth as usize],
+        root: None,
+        params: j_params,
+        poseidon_params: poseidon_params,
+    })
+}
+
+/// Generates hex encoded parameters for `circuit`, a circuit without an assignment.
+pub fn generate_params<C: Circuit<Bn256>>(seed_slice: &[u32], circuit: C) -> Result<KGGenerate, Box<Error>> {
+    let rng = &mut ChaChaRng::from_seed(seed_slice);
+    let params = generate_random_parameters::<Bn256, _, _>(circuit, rng)?;
 
     let mut v = vec![];
 
@@ -64,13 +127,101 @@ pub fn generate(seed_slice: &[u32], depth: u32) -> Result<KGGenerate, Box<Error>
     })
 }
 
+/// Creates a hex encoded proof of the assignment of `circuit` with the
+/// hex encoded `params` generated by `generate_params`.
+pub fn prove_circuit<C: Circuit<Bn256>>(seed_slice: &[u32], params: &str, circuit: C) -> Result<KGProof, Box<Error>> {
+    let de_params = Parameters::<Bn256>::read(&hex::decode(params)?[..], true)?;
+    let rng = &mut ChaChaRng::from_seed(seed_slice);
+    let proof = create_random_proof(circuit, &de_params, rng)?;
+
+    let mut v = vec![];
+    proof.write(&mut v)?;
+    Ok(KGProof {
+        proof: hex::encode(&v[..]),
+        value_commitment: None,
+    })
+}
+
+/// The curve and hash parameters the circuits spending notes of the
+/// tree are built with.
+pub struct NoteParams {
+    pub jubjub: JubjubBn256,
+    pub alt_jubjub: AltJubjubBn256,
+    pub poseidon: PoseidonParams<Bn256>,
+}
+
+impl NoteParams {
+    pub fn new() -> NoteParams {
+        NoteParams {
+            jubjub: JubjubBn256::new(),
+            alt_jubjub: AltJubjubBn256::new(),
+            poseidon: PoseidonParams::new(3),
+        }
+    }
+}
+
+/// A statement about notes of the tree together with its witness, such
+/// as a join-split or a withdrawal.
+pub trait NoteStatement<'a> {
+    type Circuit: Circuit<Bn256>;
+
+    /// Returns the circuit assigned with the witness of the statement.
+    fn circuit(&self, params: &'a NoteParams) -> Self::Circuit;
+
+    /// Returns the public inputs of the proof of the statement, in
+    /// allocation order.
+    fn public_inputs(&self) -> Vec<Fr>;
+}
+
+/// Creates a hex encoded proof of `statement` with the hex encoded
+/// `params` of its circuit. The proof is verified against the
+/// public inputs of the statement by `verify_public_inputs`.
+pub fn prove_statement<S>(seed_slice: &[u32], params: &str, statement: &S) -> Result<KGProof, Box<Error>>
+    where for<'a> S: NoteStatement<'a>
+{
+    let note_params = NoteParams::new();
+    prove_circuit(seed_slice, params, statement.circuit(&note_params))
+}
+
+/// Checks that a merkle `path` has one element per level of a tree
+/// of `depth` levels.
+pub fn check_path_depth(path: &[Option<(bool, Fr)>], depth: usize) -> Result<(), Box<Error>> {
+    if path.iter().filter(|elt| elt.is_some()).count() != depth {
+        return Err(format!("merkle path must have {} levels", depth).into());
+    }
+    Ok(())
+}
+
+/// Checks that a merkle `path` of a tree of `depth` levels leads
+/// from `leaf` to `root`.
+pub fn check_merkle_path(leaf: Fr, path: &[Option<(bool, Fr)>], root: Fr, depth: usize) -> Result<(), Box<Error>> {
+    check_path_depth(path, depth)?;
+    if compute_root_from_proof(leaf, path.to_vec()) != root {
+        return Err("merkle path does not lead to the root".into());
+    }
+    Ok(())
+}
+
+/// Proves knowledge of the preimage of a leaf on the given merkle path.
+///
+/// The note `value` is a public input, unless `value_randomness_hex` is
+/// given; then the commitment to the value under that randomness is exposed
+/// instead and returned with the proof. This must match the parameters.
+///
+/// When `expected_root_hex` is given, the assignment is first checked
+/// against the circuit and the first unsatisfied constraint is returned
+/// as an error, instead of spending the full proving time on a proof
+/// that will not verify.
 pub fn prove(
         seed_slice: &[u32],
         params: &str,
         nullifier_hex: &str,
         secret_hex: &str,
+        value: u64,
+        value_randomness_hex: Option<&str>,
         mut proof_path_hex: &str,
         mut proof_path_sides: &str,
+        expected_root_hex: Option<&str>,
 ) -> Result<KGProof, Box<Error>> {
     let de_params = Parameters::<Bn256>::read(&hex::decode(params)?[..], true)?;
     let j_params = &JubjubBn256::new();
@@ -83,6 +234,15 @@ pub fn prove(
     let secret_big = BigInt::from_str_radix(secret_hex, 16)?;
     let secret_raw = &secret_big.to_str_radix(10);
     let secret = Fr::from_str(secret_raw).ok_or("couldn't parse Fr")?;
+    // Value commitment randomness
+    let value_exposure = match value_randomness_hex {
+        Some(randomness_hex) => {
+            let randomness_big = BigInt::from_str_radix(randomness_hex, 16)?;
+            let randomness_raw = &randomness_big.to_str_radix(10);
+            ValueExposure::Committed(Some(Fs::from_str(randomness_raw).ok_or("couldn't parse Fs")?))
+        },
+        None => ValueExposure::Public,
+    };
     // Proof path
     let mut proof_p_big: Vec<Option<(bool, pairing::bn256::Fr)>> = vec![];
     let proof_len = proof_path_sides.len();
@@ -103,27 +263,143 @@ pub fn prove(
         )));
     }
 
-    let proof = create_random_proof(
+    // Expected root
+    let root = match expected_root_hex {
+        Some(root_hex) => {
+            let root_big = BigInt::from_str_radix(root_hex, 16)?;
+            let root_raw = &root_big.to_str_radix(10);
+            Some(Fr::from_str(root_raw).ok_or("couldn't parse Fr")?)
+        },
+        None => None,
+    };
+
+    if root.is_some() {
+        let unsatisfied = ConstraintChecker::<Bn256>::check(MerkleTreeCircuit {
+            params: j_params,
+            nullifier: Some(nullifier),
+            secret: Some(secret),
+            value: Some(value),
+            value_exposure: value_exposure.clone(),
+            proof: proof_p_big.clone(),
+            root: root,
+        })?;
+        if let Some(unsatisfied) = unsatisfied {
+            return Err(format!("inputs are inconsistent with the circuit: {}", unsatisfied).into());
+        }
+    }
+
+    let value_commitment = match value_exposure {
+        ValueExposure::Committed(Some(randomness)) => {
+            let (x, y) = ValueCommitment::<Bn256> {
+                value: value,
+                randomness: randomness,
+            }.cm(j_params).into_xy();
+            Some(x.to_hex() + &y.to_hex())
+        },
+        _ => None,
+    };
+
+    let compiled = compiled_circuit(j_params, proof_p_big.len(), value_commitment.is_some())?;
+    let proof = create_random_proof_compiled(
+        &compiled,
         MerkleTreeCircuit {
             params: j_params,
             nullifier: Some(nullifier),
             secret: Some(secret),
+            value: Some(value),
+            value_exposure: value_exposure,
             proof: proof_p_big,
+            root: root,
         },
         &de_params,
         rng
-    ).unwrap();
+    )?;
     println!("hello");
     let mut v = vec![];
     proof.write(&mut v)?;
     Ok(KGProof {
         proof: hex::encode(&v[..]),
+        value_commitment: value_commitment,
     })
 }
 
-pub fn verify(params: &str, proof: &str, nullifier_hex: &str, root_hex: &str) -> Result<KGVerify, Box<Error>> {
-    let de_params = Parameters::read(&hex::decode(params)?[..], true)?;
-    let pvk = prepare_verifying_key::<Bn256>(&de_params.vk);
+/// Proves knowledge of the preimage of a leaf on the given path of a
+/// quaternary tree.
+///
+/// `proof_path_hex` holds the three siblings of the node at every level,
+/// in order, and `proof_path_positions` the position of the node among
+/// them, a digit from 0 to 3 per level.
+pub fn prove_quaternary(
+        seed_slice: &[u32],
+        params: &str,
+        nullifier_hex: &str,
+        secret_hex: &str,
+        value: u64,
+        mut proof_path_hex: &str,
+        proof_path_positions: &str,
+) -> Result<KGProof, Box<Error>> {
+    let j_params = &JubjubBn256::new();
+    let poseidon_params = &PoseidonParams::new(4);
+    if proof_path_hex.len() != 3 * 64 * proof_path_positions.len() {
+        return Err("proof path must hold three siblings per level".into());
+    }
+    let mut path = vec![];
+    for position in proof_path_positions.chars() {
+        let position = match position.to_digit(4) {
+            Some(position) => position,
+            None => return Err("couldn't parse position".into()),
+        };
+        let mut siblings = [Fr::zero(); 3];
+        for sibling in siblings.iter_mut() {
+            let (sibling_hex, rest) = proof_path_hex.split_at(64);
+            proof_path_hex = rest;
+            let sibling_big = BigInt::from_str_radix(sibling_hex, 16)?;
+            *sibling = Fr::from_str(&sibling_big.to_str_radix(10)).ok_or("couldn't parse Fr")?;
+        }
+        path.push(Some(((position & 1 == 1, position & 2 == 2), siblings)));
+    }
+    let nullifier_big = BigInt::from_str_radix(nullifier_hex, 16)?;
+    let nullifier = Fr::from_str(&nullifier_big.to_str_radix(10)).ok_or("couldn't parse Fr")?;
+    let secret_big = BigInt::from_str_radix(secret_hex, 16)?;
+    let secret = Fr::from_str(&secret_big.to_str_radix(10)).ok_or("couldn't parse Fr")?;
+
+    prove_circuit(seed_slice, params, QuaternaryTreeCircuit {
+        nullifier: Some(nullifier),
+        secret: Some(secret),
+        value: Some(value),
+        path: path,
+        root: None,
+        params: j_params,
+        poseidon_params: poseidon_params,
+    })
+}
+
+/// Verifies a withdrawal of a note of the public `value`.
+pub fn verify(params: &str, proof: &str, nullifier_hex: &str, value: u64, root_hex: &str) -> Result<KGVerify, Box<Error>> {
+    let value = Fr::from_str(&value.to_string()).ok_or("couldn't parse Fr")?;
+    verify_inputs(params, proof, nullifier_hex, &[value], root_hex)
+}
+
+/// Verifies a withdrawal of a note of the public `value` from a quaternary tree.
+pub fn verify_quaternary(params: &str, proof: &str, nullifier_hex: &str, value: u64, root_hex: &str) -> Result<KGVerify, Box<Error>> {
+    verify(params, proof, nullifier_hex, value, root_hex)
+}
+
+/// Verifies a withdrawal exposing the value commitment returned by `prove`,
+/// the hex of its x coordinate followed by its y coordinate.
+pub fn verify_committed(params: &str, proof: &str, nullifier_hex: &str, value_commitment_hex: &str, root_hex: &str) -> Result<KGVerify, Box<Error>> {
+    if value_commitment_hex.len() != 128 {
+        return Err("value commitment must be 64 bytes".into());
+    }
+    let (x_hex, y_hex) = value_commitment_hex.split_at(64);
+    let x_big = BigInt::from_str_radix(x_hex, 16)?;
+    let x = Fr::from_str(&x_big.to_str_radix(10)).ok_or("couldn't parse Fr")?;
+    let y_big = BigInt::from_str_radix(y_hex, 16)?;
+    let y = Fr::from_str(&y_big.to_str_radix(10)).ok_or("couldn't parse Fr")?;
+    verify_inputs(params, proof, nullifier_hex, &[x, y], root_hex)
+}
+
+fn verify_inputs(params: &str, proof: &str, nullifier_hex: &str, value_inputs: &[Fr], root_hex: &str) -> Result<KGVerify, Box<Error>> {
     // Nullifier
     let nullifier_big = BigInt::from_str_radix(nullifier_hex, 16)?;
     let nullifier_raw = &nullifier_big.to_str_radix(10);
@@ -132,15 +408,53 @@ pub fn verify(params: &str, proof: &str, nullifier_hex: &str, root_hex: &str) ->
     let root_big = BigInt::from_str_radix(root_hex, 16)?;
     let root_raw = &root_big.to_str_radix(10);
     let root = Fr::from_str(root_raw).ok_or("couldn't parse Fr")?;
+    let mut inputs = vec![nullifier];
+    inputs.extend_from_slice(value_inputs);
+    inputs.push(root);
+    verify_public_inputs(params, proof, &inputs)
+}
+
+/// Verifies a hex encoded proof against the hex encoded `params` and the
+/// public inputs of its circuit, in allocation order.
+pub fn verify_public_inputs(params: &str, proof: &str, public_inputs: &[Fr]) -> Result<KGVerify, Box<Error>> {
+    let de_params = Parameters::read(&hex::decode(params)?[..], true)?;
+    let pvk = prepare_verifying_key::<Bn256>(&de_params.vk);
     let result = verify_proof(
         &pvk,
         &Proof::read(&hex::decode(proof)?[..])?,
-        &[
-            nullifier,
-            root
-        ])?;
+        public_inputs
+    )?;
 
     Ok(KGVerify{
         result: result
     })
 }
+
+/// Synthesizes the circuit of `statement` in a `TestConstraintSystem`.
+#[cfg(test)]
+pub fn synthesize_statement<'a, S: NoteStatement<'a>>(
+    statement: &S,
+    params: &'a NoteParams
+) -> ::sapling_crypto::circuit::test::TestConstraintSystem<Bn256> {
+    let mut cs = ::sapling_crypto::circuit::test::TestConstraintSystem::new();
+    statement.circuit(params).synthesize(&mut cs).unwrap();
+    cs
+}
+
+/// Proves `statement` with `params`, and checks that the proof verifies
+/// against its public inputs, but not once one of the inputs at
+/// `bound_inputs` is changed.
+#[cfg(test)]
+pub fn check_statement_proof<S>(seed_slice: &[u32], params: &str, statement: &S, bound_inputs: &[usize])
+    where for<'a> S: NoteStatement<'a>
+{
+    let proof = prove_statement(seed_slice, params, statement).unwrap().proof;
+    let public_inputs = statement.public_inputs();
+    assert!(verify_public_inputs(params, &proof, &public_inputs).unwrap().result);
+
+    for &i in bound_inputs {
+        let mut tampered = public_inputs.clone();
+        tampered[i].add_assign(&Fr::one());
+        assert!(!verify_public_inputs(params, &proof, &tampered).unwrap().result);
+    }
+}